@@ -0,0 +1,54 @@
+//! Benchmarks `/api/search` through the full router, and the per-request template copy it used to make.
+//!
+//! Run with `cargo bench --bench search`.
+use axum::{body::Body, http::{header::CONTENT_TYPE, Request, StatusCode}, Router};
+use criterion::{criterion_group, criterion_main, Criterion};
+use shortcut::{app, config::Config, TERA};
+use std::hint::black_box;
+use tera::Tera;
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+
+/// Builds the app on a fresh database seeded with shortcuts through `/api/post`.
+async fn seeded_app() -> Router {
+  let database = std::env::temp_dir().join(format!("shortcut-bench-{}.db", std::process::id()));
+  let _ = std::fs::remove_file(&database);
+
+  let config = Config { database_url: format!("sqlite://{}?mode=rwc", database.display()), ..Config::default() };
+  let app: Router = app(config).await.expect("Failed to build the app").router;
+
+  for index in 0..200 {
+    let request = Request::post("/api/post")
+      .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+      .body(Body::from(format!("keyword=keyword{}&url=https://example.com/{}", index, index)))
+      .unwrap();
+
+    app.clone().oneshot(request).await.unwrap();
+  }
+
+  app
+}
+
+fn search(c: &mut Criterion) {
+  let runtime = Runtime::new().unwrap();
+  let app: Router = runtime.block_on(seeded_app());
+
+  c.bench_function("/api/search", |b| b.to_async(&runtime).iter(|| async {
+    let request = Request::get("/api/search?keyword=keyword1").body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+  }));
+}
+
+/// What each request did before the templates were shared, against what it does now.
+fn templates(c: &mut Criterion) {
+  let mut group = c.benchmark_group("templates");
+
+  group.bench_function("clone", |b| b.iter(|| black_box(Tera::clone(&TERA.load()))));
+  group.bench_function("borrow", |b| b.iter(|| black_box(TERA.load())));
+  group.finish();
+}
+
+criterion_group!(benches, search, templates);
+criterion_main!(benches);
\ No newline at end of file
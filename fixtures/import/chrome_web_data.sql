@@ -0,0 +1,15 @@
+-- The parts of the `keywords` table of a Chrome `Web Data` database read by the import.
+CREATE TABLE keywords (
+  id INTEGER PRIMARY KEY,
+  short_name VARCHAR NOT NULL,
+  keyword VARCHAR NOT NULL,
+  favicon_url VARCHAR NOT NULL,
+  url VARCHAR NOT NULL,
+  date_created INTEGER DEFAULT 0,
+  last_modified INTEGER DEFAULT 0
+);
+
+INSERT INTO keywords (id, short_name, keyword, favicon_url, url) VALUES
+  (1, 'Google', 'google.com', '', '{google:baseURL}search?q={searchTerms}&{google:RLZ}'),
+  (2, 'YouTube', 'yt', '', 'https://www.youtube.com/results?search_query={searchTerms}'),
+  (3, 'crates.io', 'crates', '', 'https://crates.io/search?q={searchTerms}');
@@ -0,0 +1,22 @@
+-- The parts of the `moz_places` and `moz_keywords` tables of a Firefox `places.sqlite` database read by the import.
+CREATE TABLE moz_places (
+  id INTEGER PRIMARY KEY,
+  url LONGVARCHAR,
+  title LONGVARCHAR
+);
+
+CREATE TABLE moz_keywords (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  keyword TEXT UNIQUE,
+  place_id INTEGER,
+  post_data TEXT
+);
+
+INSERT INTO moz_places (id, url, title) VALUES
+  (1, 'https://www.mozilla.org/', 'Mozilla'),
+  (2, 'https://doc.rust-lang.org/std/?search=%s', 'Rust std'),
+  (3, 'https://duckduckgo.com/?q=%S', 'DuckDuckGo');
+
+INSERT INTO moz_keywords (keyword, place_id) VALUES
+  ('rs', 2),
+  ('ddg', 3);
@@ -0,0 +1 @@
+ALTER TABLE shortcut DROP COLUMN version;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE shortcut DROP COLUMN managed;
\ No newline at end of file
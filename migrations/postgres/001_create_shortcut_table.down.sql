@@ -0,0 +1,2 @@
+-- Drops every shortcut, only for reverting to an empty database.
+DROP TABLE IF EXISTS shortcut;
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- The PostgreSQL schema, kept at the same versions as the SQLite migrations so `shortcut migrate` works the same on both.
+CREATE TABLE IF NOT EXISTS shortcut (
+  id        BIGSERIAL PRIMARY KEY,
+  created   BIGINT  NOT NULL,
+  updated   BIGINT  NOT NULL,
+  keyword   TEXT  NOT NULL  UNIQUE,
+  url       TEXT  NOT NULL
+);
\ No newline at end of file
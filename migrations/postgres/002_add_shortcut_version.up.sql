@@ -0,0 +1,2 @@
+-- Incremented on every update, used as the ETag for optimistic concurrency.
+ALTER TABLE shortcut ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Marks shortcuts synced from the declarative shortcuts file, which are read-only in the UI and API.
+ALTER TABLE shortcut ADD COLUMN managed BOOLEAN NOT NULL DEFAULT FALSE;
\ No newline at end of file
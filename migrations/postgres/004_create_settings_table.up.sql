@@ -0,0 +1,6 @@
+-- Options changed from the settings page while the server is running, each value is stored as JSON.
+CREATE TABLE IF NOT EXISTS settings (
+  key       TEXT  NOT NULL  PRIMARY KEY,
+  value     TEXT  NOT NULL,
+  updated   BIGINT  NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,50 @@
+use crate::error::AutostartError;
+
+use auto_launch::{AutoLaunch, AutoLaunchBuilder};
+use std::{env, path::PathBuf};
+
+/// The name the login item is registered under.
+const APP_NAME: &str = "shortcut";
+
+/// Builds the login item for the running binary, which is started with `--minimized` so it doesn't open the UI on every login.
+fn auto_launch() -> Result<AutoLaunch, AutostartError> {
+  let path: PathBuf = env::current_exe().map_err(|err| AutostartError::Unavailable(err.to_string()))?;
+  let path: &str = path.to_str().ok_or(AutostartError::Unavailable(format!("invalid path {}", path.display())))?;
+
+  AutoLaunchBuilder::new()
+    .set_app_name(APP_NAME)
+    .set_app_path(path)
+    .set_args(&["--minimized"])
+    .build()
+    .map_err(|err| AutostartError::Unavailable(err.to_string()))
+}
+
+/// Checks whether shortcut is registered to start on login.
+///
+/// # Returns
+/// - `Result<bool, AutostartError>`, will be whether it's registered, or `AutostartError::FailedToRead` if that can't be told.
+pub fn is_enabled() -> Result<bool, AutostartError> {
+  auto_launch()?
+    .is_enabled()
+    .map_err(|err| AutostartError::FailedToRead(err.to_string()))
+}
+
+/// Registers (or unregisters) shortcut to start on login.
+///
+/// # Parameters
+/// - `enabled`: Whether shortcut should start on login.
+///
+/// # Returns
+/// - `Result<(), AutostartError>`, will be `AutostartError::FailedToUpdate` if the login item couldn't be changed.
+pub fn set_enabled(enabled: bool) -> Result<(), AutostartError> {
+  let auto: AutoLaunch = auto_launch()?;
+
+  // Disabling an item that isn't registered fails on some platforms, so nothing is done when it's already as asked.
+  if auto.is_enabled().is_ok_and(|current| current == enabled) {
+    return Ok(());
+  }
+
+  let result = if enabled { auto.enable() } else { auto.disable() };
+
+  result.map_err(|err| AutostartError::FailedToUpdate(err.to_string()))
+}
\ No newline at end of file
@@ -0,0 +1,323 @@
+use crate::{error::BackupError, schema::{up_migrations, MIGRATOR}, utils::system_util::{format_timestamp, get_timestamp}};
+
+use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection, Pool, Sqlite, SqliteConnection};
+use std::{fs, path::{Path, PathBuf}, time::Duration};
+use tokio::sync::watch;
+use tracing::{error, info};
+
+/// Every backup is named `shortcut-<timestamp>.db`, so sorting the names sorts them by age.
+const BACKUP_PREFIX: &str = "shortcut-";
+const BACKUP_EXTENSION: &str = ".db";
+
+/// Where, how often and how many backups are kept, see `Config::backup`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackupConfig {
+  pub directory: PathBuf,
+  pub interval: Duration,
+  pub keep: usize,
+}
+
+/// Writes a consistent snapshot of the database into the directory with `VACUUM INTO`, which is safe while the server is running.
+///
+/// # Parameters
+/// - `pool`: The connection pool of the database to back up.
+/// - `directory`: The directory to write the snapshot into, created if missing.
+///
+/// # Returns
+/// - `Result<PathBuf, BackupError>`, will be the path of the new snapshot, or `BackupError::FailedToBackup` if it couldn't be written.
+pub async fn create_backup(pool: &Pool<Sqlite>, directory: &Path) -> Result<PathBuf, BackupError> {
+  fs::create_dir_all(directory).map_err(|err| BackupError::FailedToBackup(err.to_string()))?;
+
+  // A second backup within the same second, e.g. one taken before a migration, gets a suffix that still sorts it as newer.
+  let timestamp: String = format_timestamp(get_timestamp());
+  let path: PathBuf = (0..)
+    .map(|attempt| match attempt {
+      0 => directory.join(format!("{}{}{}", BACKUP_PREFIX, timestamp, BACKUP_EXTENSION)),
+      attempt => directory.join(format!("{}{}_{}{}", BACKUP_PREFIX, timestamp, attempt, BACKUP_EXTENSION)),
+    })
+    .find(|path| !path.exists())
+    .unwrap_or_default();
+  let target: &str = path.to_str().ok_or(BackupError::FailedToBackup(format!("invalid path {}", path.display())))?;
+
+  sqlx::query("VACUUM INTO ?1")
+    .bind(target)
+    .execute(pool)
+    .await
+    .map_err(|err| BackupError::FailedToBackup(err.to_string()))?;
+
+  Ok(path)
+}
+
+/// Lists the backups in the directory, newest first.
+pub fn list_backups(directory: &Path) -> Result<Vec<PathBuf>, BackupError> {
+  let entries = match fs::read_dir(directory) {
+    Ok(entries) => entries,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+    Err(err) => return Err(BackupError::FailedToList(err.to_string())),
+  };
+
+  let mut backups: Vec<PathBuf> = entries
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| {
+      let name: &str = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+
+      path.is_file() && name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_EXTENSION)
+    })
+    .collect();
+  backups.sort_by(|a, b| b.cmp(a));
+
+  Ok(backups)
+}
+
+/// Deletes all but the `keep` most recent backups in the directory.
+///
+/// # Returns
+/// - `Result<Vec<PathBuf>, BackupError>`, will be the deleted backups, or `BackupError::FailedToList` if they couldn't be read.
+pub fn rotate_backups(directory: &Path, keep: usize) -> Result<Vec<PathBuf>, BackupError> {
+  let expired: Vec<PathBuf> = list_backups(directory)?.into_iter().skip(keep).collect();
+
+  for backup in &expired {
+    if let Err(err) = fs::remove_file(backup) {
+      error!("Failed to remove expired backup ({}): {}", backup.display(), err);
+    }
+  }
+
+  Ok(expired)
+}
+
+/// Backs up the database every `config.interval`, rotating out old backups, until the server shuts down.
+pub async fn schedule_backups(pool: Pool<Sqlite>, config: BackupConfig, mut shutdown: watch::Receiver<bool>) {
+  let mut interval = tokio::time::interval(config.interval);
+
+  loop {
+    // Only waiting is cut short by a shutdown, a backup that has started is always finished.
+    tokio::select! {
+      _ = interval.tick() => {},
+      _ = shutdown.wait_for(|stopping| *stopping) => return,
+    }
+
+    match create_backup(&pool, &config.directory).await {
+      Ok(path) => info!("Backed up database to {}.", path.display()),
+      Err(err) => error!("{}", err),
+    }
+
+    match rotate_backups(&config.directory, config.keep) {
+      Ok(expired) => expired.iter().for_each(|backup| info!("Removed expired backup {}.", backup.display())),
+      Err(err) => error!("{}", err),
+    }
+  }
+}
+
+/// Checks the snapshot is a sound shortcut database that this binary can migrate.
+///
+/// Every migration applied to the snapshot must be one embedded in this binary, with the same checksum.
+/// Older snapshots are fine, as they're migrated when the server next starts.
+///
+/// # Parameters
+/// - `snapshot`: The path of the snapshot.
+///
+/// # Returns
+/// - `Result<i64, BackupError>`, will be the snapshot's latest migration version,
+///   or `BackupError::InvalidSnapshot`/`BackupError::IncompatibleSnapshot` explaining why it can't be restored.
+pub async fn validate_backup(snapshot: &Path) -> Result<i64, BackupError> {
+  if !snapshot.is_file() {
+    return Err(BackupError::InvalidSnapshot(format!("{} does not exist", snapshot.display())));
+  }
+
+  let mut connection: SqliteConnection = SqliteConnectOptions::new()
+    .filename(snapshot)
+    .read_only(true)
+    .connect()
+    .await
+    .map_err(|err| BackupError::InvalidSnapshot(err.to_string()))?;
+
+  let integrity: String = sqlx::query_scalar("PRAGMA integrity_check")
+    .fetch_one(&mut connection)
+    .await
+    .map_err(|err| BackupError::InvalidSnapshot(err.to_string()))?;
+  if integrity != "ok" {
+    return Err(BackupError::InvalidSnapshot(format!("integrity check failed: {}", integrity)));
+  }
+
+  let applied: Vec<(i64, Vec<u8>)> = sqlx::query_as("SELECT version, checksum FROM _sqlx_migrations WHERE success = 1 ORDER BY version")
+    .fetch_all(&mut connection)
+    .await
+    .map_err(|err| BackupError::InvalidSnapshot(err.to_string()))?;
+  let _ = connection.close().await;
+
+  for (version, checksum) in &applied {
+    match up_migrations(&MIGRATOR).find(|migration| migration.version == *version) {
+      None => return Err(BackupError::IncompatibleSnapshot(format!("unknown migration {}", version))),
+      Some(migration) if migration.checksum.as_ref() != checksum.as_slice() => {
+        return Err(BackupError::IncompatibleSnapshot(format!("migration {} has been changed", version)));
+      },
+      Some(_) => {},
+    }
+  }
+
+  applied.last()
+    .map(|(version, _)| *version)
+    .ok_or(BackupError::InvalidSnapshot("no migrations have been applied".to_owned()))
+}
+
+/// Validates the snapshot and swaps it in as the database. The server must be stopped first.
+///
+/// The current database is kept beside it as `<database>.before-restore`, and the snapshot is copied in
+/// under a temporary name first so the database is only ever replaced by a complete file.
+///
+/// # Parameters
+/// - `snapshot`: The path of the snapshot to restore.
+/// - `database`: The path of the database to replace.
+///
+/// # Returns
+/// - `Result<i64, BackupError>`, will be the restored migration version, or the `BackupError` explaining why it wasn't restored.
+pub async fn restore_backup(snapshot: &Path, database: &Path) -> Result<i64, BackupError> {
+  let version: i64 = validate_backup(snapshot).await?;
+  let with_suffix = |suffix: &str| PathBuf::from(format!("{}{}", database.display(), suffix));
+  let restore_error = |err: std::io::Error| BackupError::FailedToRestore(err.to_string());
+
+  if database.exists() {
+    fs::copy(database, with_suffix(".before-restore")).map_err(restore_error)?;
+  }
+
+  let restoring: PathBuf = with_suffix(".restoring");
+  fs::copy(snapshot, &restoring).map_err(restore_error)?;
+  fs::rename(&restoring, database).map_err(restore_error)?;
+
+  // A journal left by the replaced database would otherwise be replayed onto the snapshot.
+  for suffix in ["-wal", "-shm", "-journal"] {
+    let _ = fs::remove_file(with_suffix(suffix));
+  }
+
+  Ok(version)
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod backup_tests {
+  use sqlx::{sqlite::SqliteConnectOptions, Pool, Sqlite, SqlitePool};
+  use std::{fs, path::PathBuf};
+
+  use crate::schema::MIGRATOR;
+
+  /// An empty directory of its own for each test.
+  fn test_directory(name: &str) -> PathBuf {
+    let directory: PathBuf = std::env::temp_dir().join(format!("shortcut-backup-tests-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    directory
+  }
+
+  /// A migrated database with a single shortcut in it.
+  async fn migrated_database(path: &PathBuf) -> Pool<Sqlite> {
+    let pool: Pool<Sqlite> = SqlitePool::connect_with(SqliteConnectOptions::new().filename(path).create_if_missing(true)).await.unwrap();
+    MIGRATOR.run(&pool).await.unwrap();
+    sqlx::query("INSERT INTO shortcut (created, updated, keyword, url) VALUES (1, 1, 'google', 'https://google.co.uk')").execute(&pool).await.unwrap();
+
+    pool
+  }
+
+  mod rotate_backups_tests {
+    use std::fs;
+
+    use crate::backup::{list_backups, rotate_backups};
+
+    use super::test_directory;
+
+    #[test]
+    fn keeps_most_recent_backups() {
+      let directory = test_directory("rotate");
+      for name in ["shortcut-20240101T000000Z.db", "shortcut-20240301T000000Z.db", "shortcut-20240201T000000Z.db", "notes.txt"] {
+        fs::write(directory.join(name), "").unwrap();
+      }
+
+      let expired = rotate_backups(&directory, 2).unwrap();
+
+      assert_eq!(expired, vec![directory.join("shortcut-20240101T000000Z.db")]);
+      assert_eq!(list_backups(&directory).unwrap(), vec![
+        directory.join("shortcut-20240301T000000Z.db"),
+        directory.join("shortcut-20240201T000000Z.db"),
+      ]);
+      assert!(directory.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn returns_nothing_when_directory_missing() {
+      let directory = test_directory("missing").join("missing");
+
+      assert!(list_backups(&directory).unwrap().is_empty());
+    }
+  }
+
+  mod create_backup_tests {
+    use crate::backup::{create_backup, list_backups};
+
+    use super::{migrated_database, test_directory};
+
+    #[tokio::test]
+    async fn returns_newer_backup_when_taken_within_same_second() {
+      let directory = test_directory("create");
+      let pool = migrated_database(&directory.join("shortcut.db")).await;
+
+      let first = create_backup(&pool, &directory.join("backups")).await.unwrap();
+      let second = create_backup(&pool, &directory.join("backups")).await.unwrap();
+
+      assert_ne!(first, second);
+      assert_eq!(list_backups(&directory.join("backups")).unwrap()[0], second);
+    }
+  }
+
+  mod restore_backup_tests {
+    use std::fs;
+
+    use crate::{backup::{create_backup, restore_backup, validate_backup}, error::BackupError, schema::MIGRATOR};
+
+    use super::{migrated_database, test_directory};
+
+    #[tokio::test]
+    async fn restores_backup_over_database() {
+      let directory = test_directory("restore");
+      let database = directory.join("shortcut.db");
+      let pool = migrated_database(&database).await;
+
+      let backup = create_backup(&pool, &directory.join("backups")).await.unwrap();
+      sqlx::query("DELETE FROM shortcut").execute(&pool).await.unwrap();
+      pool.close().await;
+
+      let version = restore_backup(&backup, &database).await.unwrap();
+      let pool = sqlx::SqlitePool::connect_with(sqlx::sqlite::SqliteConnectOptions::new().filename(&database)).await.unwrap();
+      let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM shortcut").fetch_one(&pool).await.unwrap();
+
+      assert_eq!(version, MIGRATOR.iter().map(|migration| migration.version).max().unwrap());
+      assert_eq!(count, 1);
+      assert!(directory.join("shortcut.db.before-restore").exists());
+    }
+
+    #[tokio::test]
+    async fn returns_exception_when_snapshot_has_unknown_migration() {
+      let directory = test_directory("unknown");
+      let pool = migrated_database(&directory.join("shortcut.db")).await;
+      sqlx::query("INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES (999999, 'future', 1, x'00', 0)")
+        .execute(&pool).await.unwrap();
+
+      let backup = create_backup(&pool, &directory).await.unwrap();
+      let result = validate_backup(&backup).await;
+
+      assert!(matches!(result, Err(BackupError::IncompatibleSnapshot(_))));
+    }
+
+    #[tokio::test]
+    async fn returns_exception_without_touching_database_when_snapshot_invalid() {
+      let directory = test_directory("invalid");
+      let (snapshot, database) = (directory.join("snapshot.db"), directory.join("shortcut.db"));
+      fs::write(&snapshot, "not a database").unwrap();
+      fs::write(&database, "current").unwrap();
+
+      let result = restore_backup(&snapshot, &database).await;
+
+      assert!(matches!(result, Err(BackupError::InvalidSnapshot(_))));
+      assert_eq!(fs::read_to_string(&database).unwrap(), "current");
+    }
+  }
+}
\ No newline at end of file
@@ -0,0 +1,8 @@
+#![forbid(unsafe_code)]
+
+/// The same as `shortcut`, but always a console program, so the commands can print on Windows,
+/// where the release build of `shortcut` has no console.
+#[tokio::main]
+async fn main() {
+    shortcut::cli::main().await;
+}
\ No newline at end of file
@@ -0,0 +1,539 @@
+use crate::{
+  autostart,
+  backup::restore_backup,
+  config::{Config, ConfigArgs},
+  error::ShortcutError,
+  instance::{instance_dir, is_running, probe, read_instance},
+  models::{migration::MigrationStatus, settings::Settings, shortcut::Shortcut, tls::CertificateFiles, transfer::{ConflictPolicy, FileFormat, ImportFormat, ImportSummary}},
+  repository::{settings::{SettingsRepository, SettingsRepositoryTrait}, shortcut::{ShortcutRepository, ShortcutRepositoryTrait}},
+  schema::{connect, connect_db, is_postgres, migrate_down, migrate_up, migration_status, MEMORY_URL},
+  service::shortcut::ShortcutService,
+  tls::{generate, tls_dir},
+  utils::system_util::format_timestamp,
+  run,
+};
+
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use dotenv::dotenv;
+use futures::{StreamExt, TryStreamExt};
+use serde::Serialize;
+use sqlx::sqlite::SqliteConnectOptions;
+use std::{fs, io::{self, Write}, path::{Path, PathBuf}, process::exit, str::FromStr};
+
+/// The command line of the `shortcut` binary, which serves the UI when no command is given.
+#[derive(Parser, Debug)]
+#[command(name = "shortcut", version, about = "A fast, simple, browser shortcut tool.")]
+pub struct Cli {
+  /// Start without opening the UI, as used when launched on login.
+  #[arg(long, global = true)]
+  pub minimized: bool,
+
+  /// Print results as JSON instead of a table.
+  #[arg(long, global = true)]
+  pub json: bool,
+
+  #[command(flatten)]
+  pub config: ConfigArgs,
+
+  #[command(subcommand)]
+  pub command: Option<Command>,
+}
+
+/// The commands other than `serve` work directly on the configured database.
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum Command {
+  /// Start the server (the default).
+  Serve,
+  /// Add a shortcut.
+  Add { keyword: String, url: String },
+  /// Remove a shortcut.
+  Rm { keyword: String },
+  /// List every shortcut.
+  Ls,
+  /// Show a single shortcut.
+  Get { keyword: String },
+  /// Fuzzy search the shortcuts.
+  Search { query: String },
+  /// Import shortcuts from a file.
+  Import {
+    file: PathBuf,
+    /// The format of the file, guessed from its name when not given.
+    #[arg(long, value_enum)]
+    format: Option<ImportFormat>,
+    /// What to do with keywords that already exist.
+    #[arg(long, value_enum, default_value_t)]
+    policy: ConflictPolicy,
+    /// Only print what would be imported.
+    #[arg(long)]
+    dry_run: bool,
+  },
+  /// Export every shortcut.
+  Export {
+    #[arg(long, value_enum, default_value_t)]
+    format: FileFormat,
+    /// The file to write, printed when not given.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+  },
+  /// Show whether the server is running, and where.
+  Status,
+  /// Restore a backup over the database, the server must be stopped first.
+  Restore { snapshot: PathBuf },
+  /// Print the shell completion script.
+  Completions { shell: Shell },
+  /// Manage starting shortcut on login.
+  Autostart {
+    #[command(subcommand)]
+    action: AutostartAction,
+  },
+  /// Show, apply or revert the database migrations.
+  Migrate {
+    #[command(subcommand)]
+    action: MigrateAction,
+  },
+  /// Generate certificates to serve HTTPS with.
+  Tls {
+    #[command(subcommand)]
+    action: TlsAction,
+  },
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum AutostartAction {
+  /// Start shortcut (minimized) on login.
+  Enable,
+  /// Stop starting shortcut on login.
+  Disable,
+  /// Show whether shortcut starts on login.
+  Status,
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum TlsAction {
+  /// Generate a certificate for the hostnames, signed by a local CA that is created the first time.
+  Generate {
+    /// The hostnames or IP addresses the server is reached at, e.g. `go.internal`.
+    #[arg(default_value = "localhost")]
+    hostnames: Vec<String>,
+    /// The directory to write the files to, instead of the one in the platform data directory.
+    #[arg(long)]
+    dir: Option<PathBuf>,
+  },
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum MigrateAction {
+  /// List every migration and whether it has been applied.
+  Status,
+  /// Apply the pending migrations, backing up the database first.
+  Up,
+  /// Revert the latest migration, backing up the database first.
+  Down {
+    /// Revert every migration after this version instead, 0 reverts them all.
+    #[arg(long)]
+    to: Option<i64>,
+  },
+}
+
+/// The path of the database file at `database_url`.
+fn database_path(database_url: &str) -> Result<PathBuf, String> {
+  if is_postgres(database_url) || database_url == MEMORY_URL {
+    return Err("Backups can only be restored to a SQLite database, use pg_restore for PostgreSQL.".to_owned());
+  }
+  let options: SqliteConnectOptions = SqliteConnectOptions::from_str(database_url).map_err(|err| err.to_string())?;
+
+  Ok(options.get_filename().to_path_buf())
+}
+
+/// Lays the rows out as a table under the header row, each column as wide as its widest value.
+fn table<const N: usize>(header: [&str; N], rows: Vec<[String; N]>) -> String {
+  let header: [String; N] = header.map(String::from);
+
+  let mut widths: [usize; N] = header.clone().map(|title| title.len());
+  for row in &rows {
+    for (width, value) in widths.iter_mut().zip(row) {
+      *width = (*width).max(value.chars().count());
+    }
+  }
+
+  std::iter::once(&header).chain(&rows)
+    .map(|row| {
+      let line: Vec<String> = row.iter().zip(widths).map(|(value, width)| format!("{:<width$}", value, width = width)).collect();
+
+      format!("{}\n", line.join("  ").trim_end())
+    })
+    .collect()
+}
+
+/// Lays the shortcuts out as a table, see `table`.
+pub fn format_table(shortcuts: &[Shortcut]) -> String {
+  let rows: Vec<[String; 4]> = shortcuts.iter()
+    .map(|shortcut| [
+      shortcut.keyword.clone(),
+      shortcut.url.clone(),
+      shortcut.updated.map(format_timestamp).unwrap_or_default(),
+      shortcut.version.map(|version| version.to_string()).unwrap_or_default(),
+    ])
+    .collect();
+
+  table(["KEYWORD", "URL", "UPDATED", "VERSION"], rows)
+}
+
+/// Lays the migrations out as a table, see `table`.
+fn format_migrations(statuses: &[MigrationStatus]) -> String {
+  let rows: Vec<[String; 4]> = statuses.iter()
+    .map(|status| [
+      status.version.to_string(),
+      status.description.clone(),
+      format!("{:?}", status.state).to_lowercase(),
+      status.installed_on.clone().unwrap_or_default(),
+    ])
+    .collect();
+
+  table(["VERSION", "DESCRIPTION", "STATE", "INSTALLED"], rows)
+}
+
+/// Describes the migrations applied or reverted, e.g. `Applied migrations 3, 4.`.
+fn format_versions(action: &str, versions: &[i64]) -> String {
+  if versions.is_empty() {
+    return format!("{} no migrations.\n", action);
+  }
+
+  let versions: Vec<String> = versions.iter().map(i64::to_string).collect();
+
+  format!("{} migrations {}.\n", action, versions.join(", "))
+}
+
+/// Prints the value as pretty JSON, or with the given table formatter.
+fn print<T: Serialize + ?Sized>(value: &T, json: bool, table: impl FnOnce(&T) -> String) {
+  if json {
+    println!("{}", serde_json::to_string_pretty(value).unwrap_or_default());
+  } else {
+    print!("{}", table(value));
+  }
+}
+
+/// Describes the generated certificate, and how to use it.
+fn format_certificate(files: &CertificateFiles) -> String {
+  let mut output: String = String::new();
+  if files.ca_created {
+    output.push_str(&format!("Created a local CA at {}, trust it on every machine that opens shortcut.\n", files.ca.display()));
+  } else {
+    output.push_str(&format!("Signed with the local CA at {}.\n", files.ca.display()));
+  }
+
+  output.push_str(&format!("Generated a certificate for {}, valid for {} days. Serve it with:\n", files.hostnames.join(", "), files.valid_days));
+  output.push_str(&format!("tls_cert = {:?}\ntls_key = {:?}\n", files.cert.display().to_string(), files.key.display().to_string()));
+
+  output
+}
+
+/// Lays an import summary out as a table of each shortcut, followed by the totals.
+fn format_import(summary: &ImportSummary) -> String {
+  let mut output: String = summary.items.iter()
+    .map(|item| format!("{:<10} {} {}\n", format!("{:?}", item.action).to_lowercase(), item.imported_as, item.url))
+    .collect();
+
+  output.push_str(&format!(
+    "{} {} created, {} updated, {} renamed, {} skipped, {} unchanged and {} failed.\n",
+    if summary.committed { "Imported:" } else { "Not imported:" },
+    summary.created, summary.updated, summary.renamed, summary.skipped, summary.unchanged, summary.failed,
+  ));
+
+  output
+}
+
+/// Parses the command line and runs the command, starting the server when none (or `serve`) is given.
+///
+/// Shared by the `shortcut` and `shortcut-cli` binaries, which only differ in whether they have a console on Windows.
+pub async fn main() {
+  // `.env` is loaded first, so `SHORTCUT_CONFIG` and the config overrides can be kept in it.
+  dotenv().ok();
+
+  let cli = Cli::parse();
+  let config = match Config::load(&cli.config) {
+    Ok(config) => config,
+    Err(err) => {
+      eprintln!("{}", err);
+      exit(1);
+    }
+  };
+
+  if let Some(command) = cli.command.filter(|command| *command != Command::Serve) {
+    if let Err(err) = execute(command, cli.json, &config).await {
+      eprintln!("{}", err);
+      exit(1);
+    }
+    return;
+  }
+
+  run(config, cli.minimized).await;
+}
+
+/// Runs any command other than `serve`.
+///
+/// # Parameters
+/// - `command`: The `Command` to run.
+/// - `json`: Whether to print results as JSON instead of a table.
+/// - `config`: The loaded `Config`.
+///
+/// # Returns
+/// - `Result<(), String>` with the message to print if the command failed.
+pub async fn execute(command: Command, json: bool, config: &Config) -> Result<(), String> {
+  match command {
+    Command::Serve => return Err("serve is run by main".to_owned()),
+    Command::Completions { shell } => {
+      clap_complete::generate(shell, &mut Cli::command(), "shortcut", &mut io::stdout());
+      return Ok(());
+    },
+    Command::Status => {
+      let directory: Option<PathBuf> = instance_dir(&config.database_url);
+      let running: bool = directory.as_deref().is_some_and(is_running);
+      let instance = directory.as_deref().filter(|_| running).and_then(read_instance);
+      let answering: bool = match &instance {
+        Some(instance) => probe(&instance.address).await,
+        None => false,
+      };
+
+      print(&serde_json::json!({ "running": running, "answering": answering, "instance": instance }), json, |_| match &instance {
+        Some(instance) if answering => format!("Running (pid {}) at {}, listening on {}.\n", instance.pid, instance.ui_url, instance.address),
+        Some(instance) => format!("Running (pid {}) but not answering on {}.\n", instance.pid, instance.address),
+        None if running => "Running, but hasn't recorded where yet.\n".to_owned(),
+        None => "Not running.\n".to_owned(),
+      });
+      return Ok(());
+    },
+    Command::Restore { snapshot } => {
+      if instance_dir(&config.database_url).as_deref().is_some_and(is_running) {
+        return Err("shortcut is running, stop it before restoring a backup.".to_owned());
+      }
+      let database: PathBuf = database_path(&config.database_url)?;
+      let version: i64 = restore_backup(&snapshot, &database).await.map_err(|err| err.to_string())?;
+
+      println!("Restored {} (migration {}) to {}.", snapshot.display(), version, database.display());
+      return Ok(());
+    },
+    Command::Autostart { action } => {
+      match action {
+        AutostartAction::Enable => autostart::set_enabled(true),
+        AutostartAction::Disable => autostart::set_enabled(false),
+        AutostartAction::Status => Ok(()),
+      }.map_err(|err| err.to_string())?;
+
+      let enabled: bool = autostart::is_enabled().map_err(|err| err.to_string())?;
+      print(&serde_json::json!({ "enabled": enabled }), json, |_| {
+        format!("Start on login is {}.\n", if enabled { "enabled" } else { "disabled" })
+      });
+      return Ok(());
+    },
+    Command::Tls { action: TlsAction::Generate { hostnames, dir } } => {
+      let directory: PathBuf = dir.or_else(tls_dir).ok_or("No data directory to write the certificates to, use --dir.")?;
+      let files: CertificateFiles = generate(&directory, &hostnames).map_err(|err| err.to_string())?;
+
+      print(&files, json, format_certificate);
+      return Ok(());
+    },
+    Command::Migrate { action } => {
+      let database = connect(&config.database_url, &config.pool()).await.map_err(|err| err.to_string())?;
+      let backup_dir = config.backup_dir.as_deref();
+
+      match action {
+        MigrateAction::Status => {
+          let statuses: Vec<MigrationStatus> = migration_status(&database).await.map_err(|err| err.to_string())?;
+
+          print(statuses.as_slice(), json, format_migrations);
+        },
+        MigrateAction::Up => {
+          let applied: Vec<i64> = migrate_up(&database, backup_dir).await.map_err(|err| err.to_string())?;
+
+          print(&serde_json::json!({ "applied": applied }), json, |_| format_versions("Applied", &applied));
+        },
+        MigrateAction::Down { to } => {
+          let reverted: Vec<i64> = migrate_down(&database, backup_dir, to).await.map_err(|err| err.to_string())?;
+
+          print(&serde_json::json!({ "reverted": reverted }), json, |_| format_versions("Reverted", &reverted));
+        },
+      }
+      return Ok(());
+    },
+    _ => {},
+  }
+
+  let database = connect_db(&config.database_url, &config.pool(), config.backup_dir.as_deref()).await.map_err(|err| err.to_string())?;
+  let repository: ShortcutRepository = ShortcutRepository::new(database);
+  let error = |err: ShortcutError| err.to_string();
+
+  match command {
+    Command::Add { keyword, url } => {
+      repository.create(&Shortcut::new(keyword.to_lowercase(), url)).await.map_err(error)?;
+      println!("Added {}.", keyword.to_lowercase());
+    },
+    Command::Rm { keyword } => {
+      // Only the version that was read is deleted, so a change made meanwhile, e.g. from the UI, fails rather than being lost.
+      let shortcut: Shortcut = repository.get(&keyword.to_lowercase()).await.map_err(error)?;
+      if !repository.delete(&shortcut.keyword, shortcut.version).await.map_err(error)? {
+        return Err(ShortcutError::NotFound.to_string());
+      }
+      println!("Removed {}.", shortcut.keyword);
+    },
+    Command::Ls => {
+      let shortcuts: Vec<Shortcut> = repository.stream_all().try_collect().await.map_err(error)?;
+
+      print(shortcuts.as_slice(), json, format_table);
+    },
+    Command::Get { keyword } => {
+      let shortcut: Shortcut = repository.get(&keyword.to_lowercase()).await.map_err(error)?;
+
+      print(&shortcut, json, |shortcut| format_table(std::slice::from_ref(shortcut)));
+    },
+    Command::Search { query } => {
+      let settings: Settings = SettingsRepository::new(repository.database.clone()).load().await.unwrap_or_default();
+      let shortcuts: Vec<Shortcut> = match repository.fuzzy_search(&query, settings.search_min_score as f32).await {
+        Err(ShortcutError::NoMatches) => vec![],
+        result => result.map_err(error)?,
+      };
+
+      print(shortcuts.as_slice(), json, format_table);
+    },
+    Command::Import { file, format, policy, dry_run } => {
+      let format: ImportFormat = format
+        .or_else(|| file.file_name().and_then(|name| name.to_str()).and_then(ImportFormat::from_file_name))
+        .ok_or(format!("Can't tell the format of {}, please give --format.", file.display()))?;
+      let contents: Vec<u8> = fs::read(&file).map_err(|err| format!("Failed to read {}: {}", file.display(), err))?;
+
+      let summary: ImportSummary = ShortcutService::new(repository)
+        .import_file(&contents, format, policy, dry_run).await
+        .map_err(error)?;
+
+      print(&summary, json, format_import);
+    },
+    Command::Export { format, output } => {
+      let mut writer: Box<dyn Write> = match &output {
+        Some(path) => Box::new(fs::File::create(path).map_err(|err| format!("Failed to create {}: {}", path.display(), err))?),
+        None => Box::new(io::stdout()),
+      };
+      let mut chunks = Box::pin(ShortcutService::new(repository).export(format));
+
+      while let Some(chunk) = chunks.next().await {
+        writer.write_all(chunk.map_err(error)?.as_bytes()).map_err(|err| err.to_string())?;
+      }
+      writer.flush().map_err(|err| err.to_string())?;
+
+      if let Some(path) = output.as_deref().map(Path::display) {
+        eprintln!("Exported to {}.", path);
+      }
+    },
+    Command::Serve | Command::Status | Command::Completions { .. } | Command::Restore { .. } | Command::Autostart { .. } | Command::Migrate { .. } | Command::Tls { .. } => {},
+  }
+
+  Ok(())
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod cli_tests {
+  mod parse_tests {
+    use clap::Parser;
+    use std::path::PathBuf;
+
+    use crate::{cli::{AutostartAction, Cli, Command, MigrateAction, TlsAction}, models::transfer::{ConflictPolicy, ImportFormat}};
+
+    #[test]
+    fn returns_no_command_when_launched_minimized() {
+      let cli = Cli::try_parse_from(["shortcut", "--minimized"]).unwrap();
+
+      assert!(cli.minimized);
+      assert_eq!(cli.command, None);
+    }
+
+    #[test]
+    fn returns_import_with_options() {
+      let cli = Cli::try_parse_from(["shortcut", "import", "places.sqlite", "--format", "firefox", "--policy", "rename", "--json", "--port", "8035"]).unwrap();
+
+      assert!(cli.json);
+      assert_eq!(cli.config.port, Some(8035));
+      assert_eq!(cli.command, Some(Command::Import {
+        file: PathBuf::from("places.sqlite"),
+        format: Some(ImportFormat::Firefox),
+        policy: ConflictPolicy::Rename,
+        dry_run: false,
+      }));
+    }
+
+    #[test]
+    fn returns_autostart_action() {
+      let cli = Cli::try_parse_from(["shortcut", "autostart", "status"]).unwrap();
+
+      assert_eq!(cli.command, Some(Command::Autostart { action: AutostartAction::Status }));
+    }
+
+    #[test]
+    fn returns_migrate_down_target() {
+      let cli = Cli::try_parse_from(["shortcut", "migrate", "down", "--to", "2"]).unwrap();
+
+      assert_eq!(cli.command, Some(Command::Migrate { action: MigrateAction::Down { to: Some(2) } }));
+    }
+
+    #[test]
+    fn returns_tls_hostnames_defaulting_to_localhost() {
+      let cli = Cli::try_parse_from(["shortcut", "tls", "generate"]).unwrap();
+      let hostnames = Cli::try_parse_from(["shortcut", "tls", "generate", "go.internal", "10.0.0.2"]).unwrap();
+
+      assert_eq!(cli.command, Some(Command::Tls { action: TlsAction::Generate { hostnames: vec!["localhost".to_owned()], dir: None } }));
+      assert_eq!(hostnames.command, Some(Command::Tls {
+        action: TlsAction::Generate { hostnames: vec!["go.internal".to_owned(), "10.0.0.2".to_owned()], dir: None },
+      }));
+    }
+
+    #[test]
+    fn returns_status() {
+      let cli = Cli::try_parse_from(["shortcut", "status", "--json"]).unwrap();
+
+      assert!(cli.json);
+      assert_eq!(cli.command, Some(Command::Status));
+    }
+
+    #[test]
+    fn returns_error_when_argument_missing() {
+      assert!(Cli::try_parse_from(["shortcut", "add", "gh"]).is_err());
+    }
+  }
+
+  mod execute_tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::{cli::{execute, Command}, config::Config};
+
+    #[tokio::test]
+    async fn returns_ok_when_keyword_differs_in_case() {
+      let directory: PathBuf = std::env::temp_dir().join(format!("shortcut-cli-tests-{}-case", std::process::id()));
+      let _ = fs::remove_dir_all(&directory);
+      fs::create_dir_all(&directory).unwrap();
+      let config = Config { database_url: format!("sqlite://{}?mode=rwc", directory.join("shortcut.db").display()), ..Config::default() };
+
+      execute(Command::Add { keyword: "Google".to_owned(), url: "https://google.com".to_owned() }, false, &config).await.unwrap();
+
+      assert!(execute(Command::Get { keyword: "GOOGLE".to_owned() }, false, &config).await.is_ok());
+      assert!(execute(Command::Rm { keyword: "Google".to_owned() }, false, &config).await.is_ok());
+      assert!(execute(Command::Get { keyword: "google".to_owned() }, false, &config).await.is_err());
+    }
+  }
+
+  mod format_table_tests {
+    use crate::{cli::format_table, models::shortcut::Shortcut};
+
+    #[test]
+    fn returns_aligned_columns() {
+      let shortcuts = vec![
+        Shortcut { id: Some(1), created: Some(0), updated: Some(0), keyword: "gh".to_owned(), url: "https://github.com".to_owned(), version: Some(2), managed: false },
+        Shortcut { id: Some(2), created: Some(0), updated: Some(0), keyword: "google".to_owned(), url: "https://google.com".to_owned(), version: Some(1), managed: false },
+      ];
+
+      assert_eq!(format_table(&shortcuts), "\
+        KEYWORD  URL                 UPDATED           VERSION\n\
+        gh       https://github.com  19700101T000000Z  2\n\
+        google   https://google.com  19700101T000000Z  1\n");
+    }
+  }
+}
\ No newline at end of file
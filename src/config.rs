@@ -0,0 +1,580 @@
+use crate::{backup::BackupConfig, error::ConfigError, schema::{check_url, PoolConfig, MEMORY_URL}, ui::load_templates, utils::{sync_util::shortcuts_directory, url_util::normalize_base_path}};
+
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
+use std::{env, fmt::Display, fs, io::ErrorKind, net::{IpAddr, Ipv4Addr}, path::PathBuf, str::FromStr, time::Duration};
+use url::Url;
+
+/// The name of the config file within the platform config directory, e.g. `~/.config/shortcut/config.toml` on Linux.
+const CONFIG_FILE: &str = "shortcut/config.toml";
+
+/// The database within the platform data directory, e.g. `~/.local/share/shortcut/shortcut.db` on Linux.
+const DATABASE_FILE: &str = "shortcut/shortcut.db";
+
+/// The prefix of every environment variable, so a generic one such as `HOST` set by the shell or container is left alone.
+const ENV_PREFIX: &str = "SHORTCUT_";
+
+/// The environment variables that were read without `ENV_PREFIX` before it was added, still read when the prefixed one isn't set.
+const ENV_ALIASES: [&str; 2] = ["PORT", "DATABASE_URL"];
+
+/// The settings of the server, layered from lowest to highest precedence:
+/// 1. The defaults below.
+/// 2. The TOML config file, see `Config::path`.
+/// 3. Environment variables (including `.env`), named after each setting in upper case with `ENV_PREFIX`, e.g. `SHORTCUT_DATABASE_URL`.
+/// 4. The command line flags in `ConfigArgs`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// The address the server listens on, only this machine by default. Use `0.0.0.0` (or `::`) to share it with the network.
+  pub host: IpAddr,
+  pub port: u16,
+  /// Listens on the next free port when `port` is in use, instead of failing to start.
+  pub port_fallback: bool,
+  /// A Unix domain socket to listen on instead of `host` and `port`, e.g. for a reverse proxy.
+  pub unix_socket: Option<PathBuf>,
+  /// The path the UI and API are served under, e.g. `/go` behind a reverse proxy that forwards `https://tools.internal/go/`.
+  pub base_path: String,
+  /// Reads `X-Forwarded-Prefix`, `X-Forwarded-Host` and `X-Forwarded-Proto`, which only a reverse proxy in front of the server
+  /// should be able to set, as otherwise any client could change the links and redirects it's sent.
+  pub trust_forwarded_headers: bool,
+  /// The PEM certificate (chain) to serve HTTPS with, see `shortcut tls generate`.
+  pub tls_cert: Option<PathBuf>,
+  /// The PEM private key of `tls_cert`.
+  pub tls_key: Option<PathBuf>,
+  pub database_url: String,
+  /// The SQLite journal mode, e.g. `wal` or `delete`.
+  pub sqlite_journal_mode: String,
+  /// The SQLite synchronous level, e.g. `normal` or `full`.
+  pub sqlite_synchronous: String,
+  pub sqlite_busy_timeout_ms: u64,
+  pub sqlite_foreign_keys: bool,
+  pub database_min_connections: u32,
+  pub database_max_connections: u32,
+  pub database_statement_cache: usize,
+  /// Where to send the user when a shortcut can't be fetched, defaulting to the UI on `port`.
+  pub ui_url: Option<String>,
+  /// Origins, besides the one of `ui_url`, whose pages may call the API from the browser, e.g. `https://dashboard.internal`.
+  pub cors_origins: Vec<String>,
+  /// A custom theme, whose `templates` and `assets` directories override the built-in files with the same path.
+  pub theme_dir: Option<PathBuf>,
+  /// The declarative shortcuts file to sync, see `ShortcutService::sync`.
+  pub shortcuts_file: Option<PathBuf>,
+  pub shortcuts_dry_run: bool,
+  /// The directory to write backups to, backups are only scheduled when it's set.
+  pub backup_dir: Option<PathBuf>,
+  pub backup_interval_minutes: u64,
+  pub backup_keep: usize,
+  /// How long to wait for requests in flight when shutting down, and then again for the background tasks.
+  pub shutdown_timeout_secs: u64,
+  /// Registers (`true`) or unregisters (`false`) shortcut to start on login each time the server starts, left as it is when unset.
+  pub autostart: Option<bool>,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+      port: 3000,
+      port_fallback: false,
+      unix_socket: None,
+      base_path: String::new(),
+      trust_forwarded_headers: false,
+      tls_cert: None,
+      tls_key: None,
+      database_url: default_database_url(),
+      sqlite_journal_mode: "wal".to_owned(),
+      sqlite_synchronous: "normal".to_owned(),
+      sqlite_busy_timeout_ms: 5000,
+      sqlite_foreign_keys: true,
+      database_min_connections: 1,
+      database_max_connections: 10,
+      database_statement_cache: 100,
+      ui_url: None,
+      cors_origins: Vec::new(),
+      theme_dir: None,
+      shortcuts_file: None,
+      shortcuts_dry_run: false,
+      backup_dir: None,
+      backup_interval_minutes: 24 * 60,
+      backup_keep: 7,
+      shutdown_timeout_secs: 30,
+      autostart: None,
+    }
+  }
+}
+
+/// The SQLite database in the platform data directory, so the same one is opened whichever directory the server starts in,
+/// e.g. on login. Falls back to `shortcut.db` in the current directory when the platform has no data directory.
+fn default_database_url() -> String {
+  let path: String = dirs::data_dir()
+    .map(|directory| directory.join(DATABASE_FILE).display().to_string())
+    .unwrap_or_else(|| "shortcut.db".to_owned());
+
+  // `%`, `?` and `#` are percent-encoded, as they'd otherwise be read as part of the url rather than the path.
+  format!("sqlite://{}?mode=rwc", path.replace('%', "%25").replace('?', "%3F").replace('#', "%23"))
+}
+
+/// The command line flags that override the config, shared by every command.
+#[derive(Args, Clone, Debug, Default, PartialEq)]
+pub struct ConfigArgs {
+  /// The config file to read, instead of the one in the platform config directory.
+  #[arg(long, global = true, env = "SHORTCUT_CONFIG")]
+  pub config: Option<PathBuf>,
+  /// The address to listen on, e.g. `127.0.0.1`, `0.0.0.0` or `::1`.
+  #[arg(long, global = true)]
+  pub host: Option<IpAddr>,
+  /// The port to listen on.
+  #[arg(long, global = true)]
+  pub port: Option<u16>,
+  /// The Unix domain socket to listen on, instead of the address and port.
+  #[arg(long, global = true)]
+  pub unix_socket: Option<PathBuf>,
+  /// The database to use, e.g. `sqlite://shortcut.db?mode=rwc` or `postgres://user@localhost/shortcut`.
+  #[arg(long, global = true)]
+  pub database_url: Option<String>,
+  /// Keep everything in memory instead of the database, for demos, same as `--database-url memory:`.
+  #[arg(long, global = true, conflicts_with = "database_url")]
+  pub ephemeral: bool,
+}
+
+/// Parses an environment variable, naming it in the error.
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, ConfigError> where T::Err: Display {
+  value.trim().parse().map_err(|err: T::Err| ConfigError::InvalidValue(key.to_owned(), err.to_string()))
+}
+
+/// Parses a boolean environment variable, accepting `1`/`0` as well as `true`/`false`.
+fn parse_bool(key: &str, value: &str) -> Result<bool, ConfigError> {
+  match value.trim() {
+    "1" => Ok(true),
+    "0" => Ok(false),
+    value => parse(key, value),
+  }
+}
+
+/// An optional setting, where an empty value unsets it.
+fn optional(value: String) -> Option<String> {
+  Some(value).filter(|value| !value.trim().is_empty())
+}
+
+impl Config {
+  /// Loads the config from every layer and validates it, see `Config::from_layers`.
+  ///
+  /// # Parameters
+  /// - `args`: The command line flags.
+  ///
+  /// # Returns
+  /// - `Result<Config, ConfigError>`, will be the config, or a `ConfigError` naming the file or setting that is wrong.
+  pub fn load(args: &ConfigArgs) -> Result<Self, ConfigError> {
+    let path: Option<PathBuf> = Self::path(args);
+
+    let contents: Option<String> = match &path {
+      None => None,
+      Some(path) => match fs::read_to_string(path) {
+        Ok(contents) => Some(contents),
+        // Only a missing default file is fine, a file that was asked for has to exist.
+        Err(err) if err.kind() == ErrorKind::NotFound && args.config.is_none() => None,
+        Err(err) => return Err(ConfigError::FailedToRead(path.display().to_string(), err.to_string())),
+      },
+    };
+
+    Self::from_layers(contents.as_deref(), |key| env::var(key).ok(), args)
+  }
+
+  /// The config file to read, `--config` (or `SHORTCUT_CONFIG`) if given, otherwise `config.toml` in the platform config directory.
+  pub fn path(args: &ConfigArgs) -> Option<PathBuf> {
+    args.config.clone().or_else(|| dirs::config_dir().map(|directory| directory.join(CONFIG_FILE)))
+  }
+
+  /// Builds the config from the defaults, the contents of the config file, the environment and the command line flags, in that order.
+  ///
+  /// # Parameters
+  /// - `file`: The contents of the config file, if there is one.
+  /// - `var`: Looks up an environment variable.
+  /// - `args`: The command line flags.
+  ///
+  /// # Returns
+  /// - `Result<Config, ConfigError>`, will be the validated config, or a `ConfigError` naming the file or setting that is wrong.
+  pub fn from_layers(file: Option<&str>, var: impl Fn(&str) -> Option<String>, args: &ConfigArgs) -> Result<Self, ConfigError> {
+    let mut config: Config = match file {
+      Some(contents) => toml::from_str(contents).map_err(|err| ConfigError::InvalidFile(err.to_string()))?,
+      None => Config::default(),
+    };
+
+    config.apply_env(var)?;
+
+    if let Some(host) = args.host {
+      config.host = host;
+    }
+    if let Some(port) = args.port {
+      config.port = port;
+    }
+    if let Some(unix_socket) = &args.unix_socket {
+      config.unix_socket = Some(unix_socket.clone());
+    }
+    if let Some(database_url) = &args.database_url {
+      config.database_url = database_url.clone();
+    }
+    if args.ephemeral {
+      config.database_url = MEMORY_URL.to_owned();
+    }
+
+    config.validate()?;
+    config.base_path = normalize_base_path(&config.base_path).unwrap_or_default();
+
+    Ok(config)
+  }
+
+  /// Overrides the settings that have an environment variable set, see `ENV_PREFIX` and `ENV_ALIASES`.
+  fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+    let var = |key: &str| var(key).or_else(|| {
+      let name: &str = key.strip_prefix(ENV_PREFIX)?;
+      ENV_ALIASES.contains(&name).then(|| var(name)).flatten()
+    });
+
+    if let Some(value) = var("SHORTCUT_HOST") {
+      self.host = parse("SHORTCUT_HOST", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_PORT") {
+      self.port = parse("SHORTCUT_PORT", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_PORT_FALLBACK") {
+      self.port_fallback = parse_bool("SHORTCUT_PORT_FALLBACK", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_UNIX_SOCKET") {
+      self.unix_socket = optional(value).map(PathBuf::from);
+    }
+    if let Some(value) = var("SHORTCUT_BASE_PATH") {
+      self.base_path = value;
+    }
+    if let Some(value) = var("SHORTCUT_TRUST_FORWARDED_HEADERS") {
+      self.trust_forwarded_headers = parse_bool("SHORTCUT_TRUST_FORWARDED_HEADERS", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_TLS_CERT") {
+      self.tls_cert = optional(value).map(PathBuf::from);
+    }
+    if let Some(value) = var("SHORTCUT_TLS_KEY") {
+      self.tls_key = optional(value).map(PathBuf::from);
+    }
+    if let Some(value) = var("SHORTCUT_DATABASE_URL") {
+      self.database_url = value;
+    }
+    if let Some(value) = var("SHORTCUT_SQLITE_JOURNAL_MODE") {
+      self.sqlite_journal_mode = value;
+    }
+    if let Some(value) = var("SHORTCUT_SQLITE_SYNCHRONOUS") {
+      self.sqlite_synchronous = value;
+    }
+    if let Some(value) = var("SHORTCUT_SQLITE_BUSY_TIMEOUT_MS") {
+      self.sqlite_busy_timeout_ms = parse("SHORTCUT_SQLITE_BUSY_TIMEOUT_MS", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_SQLITE_FOREIGN_KEYS") {
+      self.sqlite_foreign_keys = parse_bool("SHORTCUT_SQLITE_FOREIGN_KEYS", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_DATABASE_MIN_CONNECTIONS") {
+      self.database_min_connections = parse("SHORTCUT_DATABASE_MIN_CONNECTIONS", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_DATABASE_MAX_CONNECTIONS") {
+      self.database_max_connections = parse("SHORTCUT_DATABASE_MAX_CONNECTIONS", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_DATABASE_STATEMENT_CACHE") {
+      self.database_statement_cache = parse("SHORTCUT_DATABASE_STATEMENT_CACHE", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_UI_URL") {
+      self.ui_url = optional(value);
+    }
+    if let Some(value) = var("SHORTCUT_CORS_ORIGINS") {
+      self.cors_origins = value.split(',').filter_map(|origin| optional(origin.trim().to_owned())).collect();
+    }
+    if let Some(value) = var("SHORTCUT_THEME_DIR") {
+      self.theme_dir = optional(value).map(PathBuf::from);
+    }
+    if let Some(value) = var("SHORTCUT_SHORTCUTS_FILE") {
+      self.shortcuts_file = optional(value).map(PathBuf::from);
+    }
+    if let Some(value) = var("SHORTCUT_SHORTCUTS_DRY_RUN") {
+      self.shortcuts_dry_run = parse_bool("SHORTCUT_SHORTCUTS_DRY_RUN", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_BACKUP_DIR") {
+      self.backup_dir = optional(value).map(PathBuf::from);
+    }
+    if let Some(value) = var("SHORTCUT_BACKUP_INTERVAL_MINUTES") {
+      self.backup_interval_minutes = parse("SHORTCUT_BACKUP_INTERVAL_MINUTES", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_BACKUP_KEEP") {
+      self.backup_keep = parse("SHORTCUT_BACKUP_KEEP", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_SHUTDOWN_TIMEOUT_SECS") {
+      self.shutdown_timeout_secs = parse("SHORTCUT_SHUTDOWN_TIMEOUT_SECS", &value)?;
+    }
+    if let Some(value) = var("SHORTCUT_AUTOSTART") {
+      self.autostart = optional(value).map(|value| parse_bool("SHORTCUT_AUTOSTART", &value)).transpose()?;
+    }
+
+    Ok(())
+  }
+
+  /// Checks every setting can be used, so a bad one stops the server at startup rather than mid-request.
+  fn validate(&self) -> Result<(), ConfigError> {
+    let invalid = |key: &str, message: String| ConfigError::InvalidValue(key.to_owned(), message);
+
+    check_url(&self.database_url).map_err(|err| invalid("database_url", err))?;
+    SqliteJournalMode::from_str(&self.sqlite_journal_mode).map_err(|err| invalid("sqlite_journal_mode", err.to_string()))?;
+    SqliteSynchronous::from_str(&self.sqlite_synchronous).map_err(|err| invalid("sqlite_synchronous", err.to_string()))?;
+    if self.database_max_connections == 0 {
+      return Err(invalid("database_max_connections", "must be at least 1".to_owned()));
+    }
+    if self.database_min_connections > self.database_max_connections {
+      return Err(invalid("database_min_connections", "must be at most database_max_connections".to_owned()));
+    }
+    if normalize_base_path(&self.base_path).is_none() {
+      return Err(invalid("base_path", format!("{:?} must start with / and only have the characters of a path", self.base_path)));
+    }
+    Url::parse(&self.ui_url()).map_err(|err| invalid("ui_url", err.to_string()))?;
+    for origin in &self.cors_origins {
+      if !Url::parse(origin).is_ok_and(|url| url.origin().is_tuple()) {
+        return Err(invalid("cors_origins", format!("{:?} must be an origin, e.g. https://dashboard.internal", origin)));
+      }
+    }
+
+    #[cfg(not(unix))]
+    if self.unix_socket.is_some() {
+      return Err(invalid("unix_socket", "Unix domain sockets are only supported on Unix".to_owned()));
+    }
+
+    match (&self.tls_cert, &self.tls_key) {
+      (Some(_), _) if self.unix_socket.is_some() => {
+        return Err(invalid("tls_cert", "can't be used with unix_socket, let the reverse proxy serve HTTPS".to_owned()));
+      },
+      (Some(_), None) => return Err(invalid("tls_key", "must be set with tls_cert".to_owned())),
+      (None, Some(_)) => return Err(invalid("tls_cert", "must be set with tls_key".to_owned())),
+      // The files are only read when the server starts listening, so every other command works without them, see `listener::bind`.
+      (Some(_), Some(_)) | (None, None) => {},
+    }
+
+    if let Some(theme_dir) = &self.theme_dir {
+      if !theme_dir.is_dir() {
+        return Err(invalid("theme_dir", format!("{} is not a directory", theme_dir.display())));
+      }
+      load_templates(Some(theme_dir)).map_err(|err| invalid("theme_dir", format!("{:?}", err)))?;
+    }
+
+    if let Some(shortcuts_file) = &self.shortcuts_file {
+      if shortcuts_file.file_name().is_none() || !shortcuts_directory(shortcuts_file).is_dir() {
+        return Err(invalid("shortcuts_file", format!("{} is not a file in a directory that exists", shortcuts_file.display())));
+      }
+    }
+
+    if self.backup_interval_minutes == 0 {
+      return Err(invalid("backup_interval_minutes", "must be at least 1".to_owned()));
+    }
+    if self.backup_keep == 0 {
+      return Err(invalid("backup_keep", "must be at least 1".to_owned()));
+    }
+
+    Ok(())
+  }
+
+  /// Where to send the user when a shortcut can't be fetched.
+  pub fn ui_url(&self) -> String {
+    let scheme: &str = if self.tls_cert.is_some() { "https" } else { "http" };
+
+    self.ui_url.clone().unwrap_or(format!("{}://localhost:{}{}/", scheme, self.port, self.base_path))
+  }
+
+  /// The origins whose pages may call the API from the browser, the one of `ui_url` and `cors_origins`, checked by `validate`.
+  ///
+  /// Any other page the user visits is refused by the browser, as otherwise it could change or delete the shortcuts on the server.
+  pub fn cors_origins(&self) -> Vec<String> {
+    let urls = std::iter::once(self.ui_url()).chain(self.cors_origins.iter().cloned());
+
+    let mut origins: Vec<String> = Vec::new();
+    for origin in urls.filter_map(|url| Url::parse(&url).ok()).map(|url| url.origin().ascii_serialization()) {
+      if !origins.contains(&origin) {
+        origins.push(origin);
+      }
+    }
+
+    origins
+  }
+
+  /// How to open the connection pool, the journal mode and synchronous level were checked by `validate`.
+  pub fn pool(&self) -> PoolConfig {
+    PoolConfig {
+      journal_mode: self.sqlite_journal_mode.parse().unwrap_or_default(),
+      synchronous: self.sqlite_synchronous.parse().unwrap_or_default(),
+      busy_timeout: Duration::from_millis(self.sqlite_busy_timeout_ms),
+      foreign_keys: self.sqlite_foreign_keys,
+      min_connections: self.database_min_connections,
+      max_connections: self.database_max_connections,
+      statement_cache_capacity: self.database_statement_cache,
+    }
+  }
+
+  /// The backup schedule, `None` if `backup_dir` isn't set.
+  pub fn backup(&self) -> Option<BackupConfig> {
+    Some(BackupConfig {
+      directory: self.backup_dir.clone()?,
+      interval: Duration::from_secs(self.backup_interval_minutes * 60),
+      keep: self.backup_keep,
+    })
+  }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod config_tests {
+  mod from_layers_tests {
+    use std::{collections::HashMap, net::{IpAddr, Ipv6Addr}, path::PathBuf};
+
+    use crate::{config::{Config, ConfigArgs}, error::ConfigError, schema::PoolConfig};
+
+    fn no_env(_: &str) -> Option<String> {
+      None
+    }
+
+    #[test]
+    fn returns_defaults_when_nothing_set() {
+      let result = Config::from_layers(None, no_env, &ConfigArgs::default()).unwrap();
+
+      assert_eq!(result, Config::default());
+      assert_eq!(result.pool(), PoolConfig::default());
+      assert_eq!(result.ui_url(), "http://localhost:3000/");
+      assert_eq!(result.backup(), None);
+      assert!(result.database_url.ends_with("shortcut/shortcut.db?mode=rwc"));
+    }
+
+    #[test]
+    fn returns_config_with_later_layers_taking_precedence() {
+      let file = "port = 8035\nhost = \"::1\"\nbackup_dir = \"backups\"\nbackup_keep = 3\n";
+      let env = HashMap::from([("SHORTCUT_PORT", "9000"), ("SHORTCUT_BACKUP_KEEP", "5"), ("SHORTCUT_SHORTCUTS_DRY_RUN", "1"), ("SHORTCUT_AUTOSTART", "false")]);
+      let args = ConfigArgs { port: Some(9001), ..ConfigArgs::default() };
+
+      let result = Config::from_layers(Some(file), |key| env.get(key).map(|value| value.to_string()), &args).unwrap();
+
+      assert_eq!(result.host, IpAddr::V6(Ipv6Addr::LOCALHOST));
+      assert_eq!(result.port, 9001);
+      assert_eq!(result.backup_dir, Some(PathBuf::from("backups")));
+      assert_eq!(result.backup_keep, 5);
+      assert!(result.shortcuts_dry_run);
+      assert_eq!(result.autostart, Some(false));
+    }
+
+    #[test]
+    fn returns_exception_when_file_has_unknown_setting() {
+      let result = Config::from_layers(Some("prot = 8035\n"), no_env, &ConfigArgs::default());
+
+      assert!(matches!(result, Err(ConfigError::InvalidFile(_))));
+    }
+
+    #[test]
+    fn returns_exception_naming_invalid_variable() {
+      let result = Config::from_layers(None, |key| (key == "SHORTCUT_PORT").then(|| "eighty".to_owned()), &ConfigArgs::default());
+
+      assert!(matches!(result, Err(ConfigError::InvalidValue(key, _)) if key == "SHORTCUT_PORT"));
+    }
+
+    #[test]
+    fn returns_config_from_prefixed_variables_and_aliases() {
+      let env = HashMap::from([("HOST", "0.0.0.0"), ("PORT", "9000"), ("UI_URL", "http://elsewhere/"), ("DATABASE_URL", "sqlite://old.db")]);
+      let prefixed = HashMap::from([("PORT", "9000"), ("SHORTCUT_PORT", "9001")]);
+
+      let result = Config::from_layers(None, |key| env.get(key).map(|value| value.to_string()), &ConfigArgs::default()).unwrap();
+      let prefixed = Config::from_layers(None, |key| prefixed.get(key).map(|value| value.to_string()), &ConfigArgs::default()).unwrap();
+
+      assert_eq!(result.host, Config::default().host);
+      assert_eq!(result.port, 9000);
+      assert_eq!(result.ui_url, None);
+      assert_eq!(result.database_url, "sqlite://old.db");
+      assert_eq!(prefixed.port, 9001);
+    }
+
+    #[test]
+    fn returns_exception_when_theme_dir_missing() {
+      let result = Config::from_layers(Some("theme_dir = \"missing\"\n"), no_env, &ConfigArgs::default());
+
+      assert!(matches!(result, Err(ConfigError::InvalidValue(key, _)) if key == "theme_dir"));
+    }
+
+    #[test]
+    fn returns_exception_when_shortcuts_file_directory_missing() {
+      let result = Config::from_layers(Some("shortcuts_file = \"missing/shortcuts.yaml\"\n"), no_env, &ConfigArgs::default());
+      let bare = Config::from_layers(Some("shortcuts_file = \"shortcuts.yaml\"\n"), no_env, &ConfigArgs::default());
+
+      assert!(matches!(result, Err(ConfigError::InvalidValue(key, _)) if key == "shortcuts_file"));
+      assert!(bare.is_ok());
+    }
+
+    #[test]
+    fn returns_exception_when_sqlite_pragma_unknown() {
+      let result = Config::from_layers(None, |key| (key == "SHORTCUT_SQLITE_JOURNAL_MODE").then(|| "fast".to_owned()), &ConfigArgs::default());
+
+      assert!(matches!(result, Err(ConfigError::InvalidValue(key, _)) if key == "sqlite_journal_mode"));
+    }
+
+    #[test]
+    fn returns_exception_when_min_connections_above_max() {
+      let result = Config::from_layers(Some("database_min_connections = 4\ndatabase_max_connections = 2\n"), no_env, &ConfigArgs::default());
+
+      assert!(matches!(result, Err(ConfigError::InvalidValue(key, _)) if key == "database_min_connections"));
+    }
+
+    #[test]
+    fn returns_normalized_base_path() {
+      let result = Config::from_layers(Some("base_path = \"/go/\"\n"), no_env, &ConfigArgs::default()).unwrap();
+      let invalid = Config::from_layers(None, |key| (key == "SHORTCUT_BASE_PATH").then(|| "//evil.example".to_owned()), &ConfigArgs::default());
+
+      assert_eq!(result.base_path, "/go");
+      assert_eq!(result.ui_url(), "http://localhost:3000/go/");
+      assert!(matches!(invalid, Err(ConfigError::InvalidValue(key, _)) if key == "base_path"));
+    }
+
+    #[test]
+    fn returns_cors_origins_of_ui_url_and_extra_origins() {
+      let result = Config::from_layers(None, |key| match key {
+        "SHORTCUT_UI_URL" => Some("https://go.internal/go/".to_owned()),
+        "SHORTCUT_CORS_ORIGINS" => Some("https://dashboard.internal/, https://go.internal".to_owned()),
+        _ => None,
+      }, &ConfigArgs::default()).unwrap();
+      let invalid = Config::from_layers(Some("cors_origins = [\"*\"]\n"), no_env, &ConfigArgs::default());
+
+      assert_eq!(result.cors_origins(), vec!["https://go.internal", "https://dashboard.internal"]);
+      assert_eq!(Config::default().cors_origins(), vec!["http://localhost:3000"]);
+      assert!(matches!(invalid, Err(ConfigError::InvalidValue(key, _)) if key == "cors_origins"));
+    }
+
+    #[test]
+    fn returns_exception_when_tls_key_missing() {
+      let result = Config::from_layers(None, |key| (key == "SHORTCUT_TLS_CERT").then(|| "cert.pem".to_owned()), &ConfigArgs::default());
+
+      assert!(matches!(result, Err(ConfigError::InvalidValue(key, _)) if key == "tls_key"));
+    }
+
+    #[test]
+    fn returns_config_when_tls_files_missing() {
+      let result = Config::from_layers(Some("tls_cert = \"missing.pem\"\ntls_key = \"missing-key.pem\"\n"), no_env, &ConfigArgs::default());
+
+      assert_eq!(result.unwrap().tls_cert, Some(PathBuf::from("missing.pem")));
+    }
+
+    #[test]
+    fn returns_memory_database_when_ephemeral() {
+      let env = HashMap::from([("DATABASE_URL", "sqlite://other.db")]);
+      let args = ConfigArgs { ephemeral: true, ..ConfigArgs::default() };
+
+      let result = Config::from_layers(None, |key| env.get(key).map(|value| value.to_string()), &args).unwrap();
+
+      assert_eq!(result.database_url, "memory:");
+    }
+
+    #[test]
+    #[cfg(not(feature = "postgres"))]
+    fn returns_exception_when_postgres_without_feature() {
+      let args = ConfigArgs { database_url: Some("postgres://localhost/shortcut".to_owned()), ..ConfigArgs::default() };
+
+      let result = Config::from_layers(None, no_env, &args);
+
+      assert!(matches!(result, Err(ConfigError::InvalidValue(key, message)) if key == "database_url" && message.contains("postgres")));
+    }
+  }
+}
\ No newline at end of file
@@ -1,4 +1,8 @@
+use crate::models::instance::Instance;
+
+use axum::{http::StatusCode, response::{IntoResponse, Response}};
 use thiserror::Error;
+use tracing::error;
 
 #[derive(Error, Debug)]
 pub enum ShortcutError {
@@ -17,5 +21,137 @@ pub enum ShortcutError {
     #[error("Failed to search shortcut.")]
     FailedToSearch,
     #[error("Failed to delete shortcut.")]
-    FailedToDelete
+    FailedToDelete,
+    #[error("Failed to apply bulk operations.")]
+    FailedToBulk,
+    #[error("Shortcut has been changed since it was loaded. Please reload and try again.")]
+    VersionConflict,
+    #[error("Shortcut version is required to make changes. Please reload and try again.")]
+    VersionRequired,
+    #[error("Failed to export shortcuts.")]
+    FailedToExport,
+    #[error("Failed to read import file. Please check the file and its format.")]
+    InvalidImport,
+    #[error("Failed to import shortcuts.")]
+    FailedToImport,
+    #[error("Shortcut is managed by the shortcuts file, please change it there instead.")]
+    ReadOnly,
+    #[error("Failed to read shortcuts file. Please check the file and its format.")]
+    InvalidShortcutsFile,
+    #[error("Failed to sync shortcuts file.")]
+    FailedToSync,
+}
+
+impl ShortcutError {
+    /// The HTTP status code that best describes the error.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ShortcutError::NotFound | ShortcutError::NoMatches => StatusCode::NOT_FOUND,
+            ShortcutError::UniqueConstraintError => StatusCode::CONFLICT,
+            ShortcutError::VersionConflict => StatusCode::PRECONDITION_FAILED,
+            ShortcutError::VersionRequired => StatusCode::PRECONDITION_REQUIRED,
+            ShortcutError::InvalidImport => StatusCode::BAD_REQUEST,
+            ShortcutError::ReadOnly => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Used by the JSON endpoints to turn a `ShortcutError` into a response with a matching status code.
+impl IntoResponse for ShortcutError {
+    fn into_response(self) -> Response {
+        (self.status_code(), self.to_string()).into_response()
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("Failed to write backup: {0}")]
+    FailedToBackup(String),
+    #[error("Failed to read backups: {0}")]
+    FailedToList(String),
+    #[error("Snapshot is not a valid shortcut database: {0}")]
+    InvalidSnapshot(String),
+    #[error("Snapshot was made by a different version of shortcut: {0}")]
+    IncompatibleSnapshot(String),
+    #[error("Failed to restore snapshot: {0}")]
+    FailedToRestore(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file {0}: {1}")]
+    FailedToRead(String, String),
+    #[error("Invalid config file: {0}")]
+    InvalidFile(String),
+    #[error("Invalid config value for {0}: {1}")]
+    InvalidValue(String, String),
+}
+
+#[derive(Error, Debug)]
+pub enum InstanceError {
+    #[error("shortcut is already running.")]
+    AlreadyRunning(Option<Instance>),
+    #[error("Failed to lock {0}: {1}")]
+    FailedToLock(String, String),
+    #[error("Failed to record the running server in {0}: {1}")]
+    FailedToRecord(String, String),
+}
+
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("Invalid hostname for a certificate: {0:?}")]
+    InvalidHostname(String),
+    #[error("Failed to generate the certificate: {0}")]
+    FailedToGenerate(String),
+    #[error("Failed to read {0}: {1}")]
+    FailedToRead(String, String),
+    #[error("Failed to write {0}: {1}")]
+    FailedToWrite(String, String),
+}
+
+#[derive(Error, Debug)]
+pub enum AutostartError {
+    #[error("Start on login is not available: {0}")]
+    Unavailable(String),
+    #[error("Failed to check start on login: {0}")]
+    FailedToRead(String),
+    #[error("Failed to change start on login: {0}")]
+    FailedToUpdate(String),
+}
+
+#[derive(Error, Debug)]
+pub enum SettingsError {
+    #[error("Failed to load settings.")]
+    FailedToLoad,
+    #[error("Failed to save settings. Please try again.")]
+    FailedToSave,
+    #[error("Invalid setting: {0}")]
+    Invalid(String),
+}
+
+#[derive(Error, Debug)]
+pub enum MigrationError {
+    #[error("Failed to connect to the database: {0}")]
+    Unavailable(String),
+    #[error("Failed to read the applied migrations: {0}")]
+    FailedToRead(String),
+    #[error("Failed to back up the database, so it wasn't migrated: {0}")]
+    FailedToBackup(String),
+    #[error("Failed to migrate the database: {0}")]
+    FailedToMigrate(String),
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum TemplateError {
+    #[error("Failed to render {0}: {1}")]
+    FailedToRender(String, String),
+}
+
+/// Logs the cause and responds with a `500`, without the details that only matter to whoever runs the server.
+impl IntoResponse for TemplateError {
+    fn into_response(self) -> Response {
+        error!("{}", self);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to render the page.").into_response()
+    }
 }
\ No newline at end of file
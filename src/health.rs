@@ -0,0 +1,143 @@
+use crate::{
+  macros::renderable::Renderable,
+  models::{health::{ComponentStatus, Readiness}, migration::{MigrationState, MigrationStatus}},
+  schema::{migration_status, Database},
+  templates::pages::{CreatePageTemplate, ImportPageTemplate, SearchPageTemplate, SettingsPageTemplate},
+  TERA,
+};
+
+use std::{future::Future, time::Instant};
+
+/// Runs a single check, timing how long it took.
+///
+/// # Parameters
+/// - `name`: The name of the component, as shown by `GET /readyz`.
+/// - `check`: The check, giving what it found or why the component isn't ready.
+///
+/// # Returns
+/// - `ComponentStatus` of the component.
+async fn timed(name: &'static str, check: impl Future<Output = Result<Option<String>, String>>) -> ComponentStatus {
+  let start: Instant = Instant::now();
+  let result: Result<Option<String>, String> = check.await;
+  let duration_ms: f64 = start.elapsed().as_secs_f64() * 1000.0;
+
+  match result {
+    Ok(detail) => ComponentStatus { name, ready: true, duration_ms, detail },
+    Err(err) => ComponentStatus { name, ready: false, duration_ms, detail: Some(err) },
+  }
+}
+
+/// Runs the cheapest query there is, to check the database can still be reached.
+async fn check_database(database: &Database) -> Result<Option<String>, String> {
+  dispatch!(database, pool => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()), _ => Ok(()))
+    .map(|_| None)
+    .map_err(|err| err.to_string())
+}
+
+/// Checks every migration embedded in the binary has been applied, giving the latest version.
+async fn check_migrations(database: &Database) -> Result<Option<String>, String> {
+  let statuses: Vec<MigrationStatus> = migration_status(database).await.map_err(|err| err.to_string())?;
+
+  let unapplied: Vec<i64> = statuses.iter()
+    .filter(|status| matches!(status.state, MigrationState::Pending | MigrationState::Failed))
+    .map(|status| status.version)
+    .collect();
+  if !unapplied.is_empty() {
+    return Err(format!("migrations {:?} haven't been applied", unapplied));
+  }
+
+  Ok(statuses.iter()
+    .filter(|status| status.state != MigrationState::Pending)
+    .map(|status| status.version)
+    .max()
+    .map(|version| format!("version {}", version)))
+}
+
+/// Renders every page, as a theme template can fail to render once it's reloaded.
+async fn check_templates() -> Result<Option<String>, String> {
+  let tera = TERA.load();
+
+  SearchPageTemplate{}.get_html(&tera, "")
+    .and(CreatePageTemplate{}.get_html(&tera, ""))
+    .and(ImportPageTemplate{}.get_html(&tera, ""))
+    .and(SettingsPageTemplate{}.get_html(&tera, ""))
+    .map(|_| None)
+    .map_err(|err| format!("{:?}", err))
+}
+
+/// Checks whether the server can serve requests, see `GET /readyz`.
+///
+/// # Parameters
+/// - `database`: The database the server is using.
+///
+/// # Returns
+/// - `Readiness` of the database, its migrations and the templates, with how long each took to check.
+pub async fn readiness(database: &Database) -> Readiness {
+  let components: Vec<ComponentStatus> = vec![
+    timed("database", check_database(database)).await,
+    timed("migrations", check_migrations(database)).await,
+    timed("templates", check_templates()).await,
+  ];
+
+  Readiness { ready: components.iter().all(|component| component.ready), components }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod health_tests {
+  mod readiness_tests {
+    use sqlx::SqlitePool;
+
+    use crate::{
+      health::readiness,
+      models::health::Readiness,
+      repository::memory::MemoryStore,
+      schema::{migrate_up, up_migrations, Database, MIGRATOR},
+    };
+
+    /// The readiness of each component, leaving out the timings.
+    fn components(readiness: &Readiness) -> Vec<(&'static str, bool)> {
+      readiness.components.iter().map(|component| (component.name, component.ready)).collect()
+    }
+
+    #[tokio::test]
+    async fn returns_ready_when_database_migrated() {
+      let database = Database::Sqlite(SqlitePool::connect("sqlite::memory:").await.unwrap());
+      migrate_up(&database, None).await.unwrap();
+
+      let result = readiness(&database).await;
+
+      assert!(result.ready);
+      let latest = up_migrations(&MIGRATOR).map(|migration| migration.version).max().unwrap();
+      assert_eq!(result.components[1].detail, Some(format!("version {}", latest)));
+    }
+
+    #[tokio::test]
+    async fn returns_ready_when_in_memory() {
+      let result = readiness(&Database::Memory(MemoryStore::default())).await;
+
+      assert!(result.ready);
+    }
+
+    #[tokio::test]
+    async fn returns_not_ready_when_migrations_pending() {
+      let database = Database::Sqlite(SqlitePool::connect("sqlite::memory:").await.unwrap());
+
+      let result = readiness(&database).await;
+
+      assert!(!result.ready);
+      assert_eq!(components(&result), vec![("database", true), ("migrations", false), ("templates", true)]);
+    }
+
+    #[tokio::test]
+    async fn returns_not_ready_when_database_gone() {
+      let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+      pool.close().await;
+
+      let result = readiness(&Database::Sqlite(pool)).await;
+
+      assert!(!result.ready);
+      assert_eq!(components(&result), vec![("database", false), ("migrations", false), ("templates", true)]);
+    }
+  }
+}
\ No newline at end of file
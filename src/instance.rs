@@ -0,0 +1,289 @@
+use crate::{error::InstanceError, models::instance::Instance, schema::{is_postgres, MEMORY_URL}, tls::connect_unverified};
+
+use sqlx::sqlite::SqliteConnectOptions;
+use std::{fs::{self, File, OpenOptions, TryLockError}, net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr}, path::{self, Path, PathBuf}, str::FromStr, time::Duration};
+use tokio::{io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt}, net::TcpStream};
+
+/// The directory within the platform data directory, e.g. `~/.local/share/shortcut/instances` on Linux.
+const INSTANCE_DIR: &str = "shortcut/instances";
+/// Locked for as long as a server is running, the lock is released by the OS even if the server crashes.
+const LOCK_FILE: &str = "shortcut.lock";
+/// The running server, see `Instance`.
+const INSTANCE_FILE: &str = "instance.json";
+/// How long a probe waits for the server to answer.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The database a `database_url` opens, so the same SQLite file is found however its url is written, e.g. relative to another directory.
+fn database_key(database_url: &str) -> String {
+  let path: Option<PathBuf> = (!is_postgres(database_url))
+    .then(|| SqliteConnectOptions::from_str(database_url).ok())
+    .flatten()
+    .and_then(|options| path::absolute(options.get_filename()).ok());
+
+  path.map_or_else(|| database_url.to_owned(), |path| path.display().to_string())
+}
+
+/// A 64-bit FNV-1a hash, which unlike `DefaultHasher` is the same in every build, as it names directories on disk.
+fn fnv1a(value: &str) -> u64 {
+  value.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3))
+}
+
+/// Where the lock and the running server are kept, one directory for each database, so servers of different databases
+/// (or configs) run side by side and only a second server of the same one finds it running.
+///
+/// # Parameters
+/// - `database_url`: The database of the server.
+///
+/// # Returns
+/// - `Option<PathBuf>`, will be the directory, or `None` for an ephemeral server, which shares nothing with any other,
+///   or if the platform has no data directory.
+pub fn instance_dir(database_url: &str) -> Option<PathBuf> {
+  if database_url == MEMORY_URL {
+    return None;
+  }
+
+  dirs::data_dir().map(|directory| directory.join(INSTANCE_DIR).join(format!("{:016x}", fnv1a(&database_key(database_url)))))
+}
+
+/// Opens the lock file, without taking the lock.
+fn open_lock(directory: &Path) -> Result<File, InstanceError> {
+  let path: PathBuf = directory.join(LOCK_FILE);
+  let failed = |err: std::io::Error| InstanceError::FailedToLock(path.display().to_string(), err.to_string());
+
+  fs::create_dir_all(directory).map_err(failed)?;
+  OpenOptions::new().create(true).truncate(false).write(true).open(&path).map_err(failed)
+}
+
+/// The server recorded in the directory, `None` if there isn't one or it can't be read.
+pub fn read_instance(directory: &Path) -> Option<Instance> {
+  serde_json::from_str(&fs::read_to_string(directory.join(INSTANCE_FILE)).ok()?).ok()
+}
+
+/// Whether a server holds the lock in the directory, i.e. is running.
+pub fn is_running(directory: &Path) -> bool {
+  let Ok(file) = open_lock(directory) else {
+    return false;
+  };
+
+  match file.try_lock() {
+    Ok(()) => {
+      let _ = file.unlock();
+      false
+    },
+    Err(TryLockError::WouldBlock) => true,
+    Err(TryLockError::Error(_)) => false,
+  }
+}
+
+/// Held by the running server, so a second server started e.g. by logging in twice finds it instead of failing to bind.
+///
+/// Dropping it removes the recorded `Instance` and releases the lock.
+pub struct InstanceLock {
+  file: File,
+  directory: PathBuf,
+}
+
+impl InstanceLock {
+  /// Takes the lock in the directory.
+  ///
+  /// # Parameters
+  /// - `directory`: Where the lock is kept, see `instance_dir`.
+  ///
+  /// # Returns
+  /// - `Result<InstanceLock, InstanceError>`, will be the lock, `InstanceError::AlreadyRunning` with the server holding it,
+  ///   or `InstanceError::FailedToLock`.
+  pub fn acquire(directory: &Path) -> Result<Self, InstanceError> {
+    let file: File = open_lock(directory)?;
+
+    match file.try_lock() {
+      Ok(()) => Ok(Self { file, directory: directory.to_path_buf() }),
+      Err(TryLockError::WouldBlock) => Err(InstanceError::AlreadyRunning(read_instance(directory))),
+      Err(TryLockError::Error(err)) => Err(InstanceError::FailedToLock(directory.join(LOCK_FILE).display().to_string(), err.to_string())),
+    }
+  }
+
+  /// Records where the server is listening, for the CLI and for a second server to find it.
+  pub fn record(&self, instance: &Instance) -> Result<(), InstanceError> {
+    let path: PathBuf = self.directory.join(INSTANCE_FILE);
+    let contents: String = serde_json::to_string_pretty(instance).unwrap_or_default();
+
+    fs::write(&path, contents).map_err(|err| InstanceError::FailedToRecord(path.display().to_string(), err.to_string()))
+  }
+}
+
+impl Drop for InstanceLock {
+  fn drop(&mut self) {
+    let _ = fs::remove_file(self.directory.join(INSTANCE_FILE));
+    let _ = self.file.unlock();
+  }
+}
+
+/// The address to probe a server listening on `host` at, which is the loopback address when it listens on every address.
+fn probe_address(host: IpAddr, port: u16) -> SocketAddr {
+  match host {
+    IpAddr::V4(address) if address.is_unspecified() => SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+    IpAddr::V6(address) if address.is_unspecified() => SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), port),
+    host => SocketAddr::new(host, port),
+  }
+}
+
+/// Sends `GET /readyz` to the `host`, checking the answer is the readiness of a shortcut server.
+///
+/// The probes stay at the root when the UI is served under a `base_path`, so the server is found whatever its config.
+async fn request_health<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, host: &str) -> std::io::Result<bool> {
+  stream.write_all(format!("GET /readyz HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", host).as_bytes()).await?;
+  let mut response: Vec<u8> = Vec::new();
+  stream.read_to_end(&mut response).await?;
+  let response = String::from_utf8_lossy(&response);
+
+  // A server that isn't ready yet answers `503`, but is still shortcut.
+  Ok(response.starts_with("HTTP/1.") && response.contains("\"components\""))
+}
+
+/// Whether a shortcut server is answering at the address.
+///
+/// # Parameters
+/// - `address`: Where the server is listening, as recorded in `Instance::address`, e.g. `0.0.0.0:3000` is probed on `127.0.0.1:3000`,
+///   and `https://127.0.0.1:3000` over TLS.
+///
+/// # Returns
+/// - `bool`, `false` if nothing answered within a couple of seconds, or something other than shortcut did.
+pub async fn probe(address: &str) -> bool {
+  let check = async {
+    #[cfg(unix)]
+    if let Some(path) = address.strip_prefix("unix:") {
+      return request_health(tokio::net::UnixStream::connect(path).await?, "localhost").await;
+    }
+
+    let (tls, address): (bool, &str) = match address.strip_prefix("https://") {
+      Some(address) => (true, address),
+      None => (false, address),
+    };
+    let address: String = match address.parse::<SocketAddr>() {
+      Ok(address) => probe_address(address.ip(), address.port()).to_string(),
+      Err(_) => address.to_owned(),
+    };
+
+    let stream: TcpStream = TcpStream::connect(&address).await?;
+    if tls {
+      request_health(connect_unverified(stream).await?, &address).await
+    } else {
+      request_health(stream, &address).await
+    }
+  };
+
+  matches!(tokio::time::timeout(PROBE_TIMEOUT, check).await, Ok(Ok(true)))
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod instance_tests {
+  use std::{fs, path::PathBuf};
+
+  /// An empty directory of its own for each test.
+  fn test_directory(name: &str) -> PathBuf {
+    let directory: PathBuf = std::env::temp_dir().join(format!("shortcut-instance-tests-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+
+    directory
+  }
+
+  mod instance_dir_tests {
+    use crate::instance::instance_dir;
+
+    #[test]
+    fn returns_directory_for_each_database() {
+      let current = std::env::current_dir().unwrap().join("shortcut.db");
+
+      assert_eq!(instance_dir("sqlite://shortcut.db?mode=rwc"), instance_dir(&format!("sqlite://{}", current.display())));
+      assert_ne!(instance_dir("sqlite://shortcut.db"), instance_dir("sqlite://other.db"));
+      assert_ne!(instance_dir("postgres://localhost/shortcut"), instance_dir("postgres://localhost/other"));
+      assert_eq!(instance_dir("memory:"), None);
+    }
+  }
+
+  mod acquire_tests {
+    use crate::{error::InstanceError, instance::{is_running, read_instance, InstanceLock}, models::instance::Instance};
+
+    use super::test_directory;
+
+    #[test]
+    fn returns_running_instance_until_lock_dropped() {
+      let directory = test_directory("acquire");
+      let instance = Instance { pid: 42, address: "127.0.0.1:3000".to_owned(), ui_url: "http://localhost:3000/".to_owned() };
+      assert!(!is_running(&directory));
+
+      let lock = InstanceLock::acquire(&directory).unwrap();
+      lock.record(&instance).unwrap();
+      let result = InstanceLock::acquire(&directory);
+
+      assert!(matches!(result, Err(InstanceError::AlreadyRunning(Some(ref running))) if *running == instance));
+      assert!(is_running(&directory));
+
+      drop(lock);
+
+      assert!(!is_running(&directory));
+      assert_eq!(read_instance(&directory), None);
+      assert!(InstanceLock::acquire(&directory).is_ok());
+    }
+  }
+
+  mod probe_tests {
+    use std::{future, net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr}, time::Duration};
+    use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpListener};
+
+    use crate::{app, config::Config, instance::{probe, probe_address}, listener::Listener, schema::MEMORY_URL};
+
+    /// Answers a single request with the response, returning the address it listens on.
+    async fn answer(response: &'static str) -> String {
+      let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+      let address = listener.local_addr().unwrap().to_string();
+      tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let _ = stream.read(&mut [0; 1024]).await;
+        stream.write_all(response.as_bytes()).await.unwrap();
+      });
+
+      address
+    }
+
+    #[tokio::test]
+    async fn returns_true_when_shortcut_answers() {
+      let address = answer("HTTP/1.1 503 Service Unavailable\r\n\r\n{\"ready\":false,\"components\":[]}").await;
+
+      assert!(probe(&address).await);
+    }
+
+    #[tokio::test]
+    async fn returns_true_when_served_under_base_path() {
+      let config = Config { database_url: MEMORY_URL.to_owned(), base_path: "/go".to_owned(), ..Config::default() };
+      let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+      let address = listener.local_addr().unwrap().to_string();
+      let app = app(config).await.unwrap();
+      tokio::spawn(app.serve(Listener::Tcp(listener), future::pending(), Duration::from_secs(1)));
+
+      assert!(probe(&address).await);
+    }
+
+    #[tokio::test]
+    async fn returns_false_when_something_else_answers() {
+      let address = answer("HTTP/1.1 404 Not Found\r\n\r\nNot Found").await;
+
+      assert!(!probe(&address).await);
+    }
+
+    #[tokio::test]
+    async fn returns_false_when_nothing_listening() {
+      let address = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().to_string();
+
+      assert!(!probe(&address).await);
+    }
+
+    #[test]
+    fn returns_loopback_when_listening_on_every_address() {
+      assert_eq!(probe_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 80), SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80));
+      assert_eq!(probe_address(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 80), SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 80));
+      assert_eq!(probe_address(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 80), SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 80));
+    }
+  }
+}
\ No newline at end of file
@@ -1,6 +1,16 @@
+#![forbid(unsafe_code)]
+mod autostart;
+mod backup;
+pub mod cli;
+pub mod config;
 mod error;
+mod instance;
+mod listener;
 #[macro_use]
 mod macros;
+// After `macros`, so they can use `dispatch!`.
+mod health;
+mod metrics;
 mod models;
 mod repository;
 mod routes;
@@ -8,75 +18,297 @@ mod schema;
 mod service;
 mod state;
 mod templates;
+mod tls;
+mod ui;
 mod utils;
 
-use axum::{http::{header::CONTENT_TYPE, Method}, Extension, Router};
-use dotenv::dotenv;
-use repository::shortcut::{ShortcutRepository, ShortcutRepositoryTrait};
-use routes::create_api_routes;
-use sqlx::{Pool, Sqlite};
-use templates::create_ui_routes;
-use std::{sync::{LazyLock, RwLock}, time::Duration};
+use arc_swap::ArcSwap;
+use axum::{http::{header::{CONTENT_TYPE, ETAG, IF_MATCH}, HeaderValue, Method}, middleware::from_fn, routing::get, Extension, Router};
+use backup::schedule_backups;
+use config::Config;
+use error::InstanceError;
+use instance::{instance_dir, probe, InstanceLock};
+use listener::{bind, Listener};
+use metrics::track_metrics;
+use models::{instance::Instance, settings::Settings};
+use repository::{settings::{SettingsRepository, SettingsRepositoryTrait}, shortcut::ShortcutRepository};
+use routes::{create_api_routes, create_health_routes, middleware};
+use templates::{create_ui_routes, redirect_to_search};
+use ui::{load_templates, serve_asset};
+#[cfg(debug_assertions)]
+use ui::{theme_templates, TEMPLATES_SOURCE};
+use std::{future::Future, io, net::SocketAddr, path::Path, sync::{Arc, LazyLock}, time::Duration};
+#[cfg(debug_assertions)]
+use std::path::PathBuf;
 use tera::Tera;
+#[cfg(debug_assertions)]
 use tera_hot_reload::{watch, LiveReloadLayer};
-use tokio::net::TcpListener;
-use tower_http::{compression::CompressionLayer, cors::{Any, CorsLayer}, services::ServeDir, trace::TraceLayer};
-use tracing::info;
+use tokio::{sync::{mpsc, watch as channel, Notify}, task::JoinSet};
+use tower_http::{compression::CompressionLayer, cors::CorsLayer, trace::TraceLayer};
+use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use schema::connect_db;
-use service::shortcut::ShortcutService;
+use schema::{connect_db, Database, MEMORY_URL};
+use service::{settings::SettingsService, shortcut::ShortcutService};
 use state::AppState;
+use utils::{sync_util::{load_shortcuts_file, watch_shortcuts_file}, system_util::open_in_browser};
 
-pub static TERA: LazyLock<RwLock<Tera>> = LazyLock::new(|| {
-    RwLock::new(tera::Tera::new("ui/templates/**/*").expect("Failed to create Tera instance"))
+/// The templates, the built-in ones until `app` loads them with the configured theme.
+///
+/// Requests borrow the current templates with `TERA.load()`, and a reload swaps in new ones without blocking them.
+pub static TERA: LazyLock<ArcSwap<Tera>> = LazyLock::new(|| {
+    ArcSwap::from_pointee(load_templates(None).expect("Failed to create Tera instance"))
 });
 
-pub async fn app() -> Result<Router, ()> {
+/// Syncs the declarative shortcuts file into the database, printing every planned (or applied) change.
+async fn sync_shortcuts_file(shortcut_service: &ShortcutService<ShortcutRepository>, path: &Path, dry_run: bool) {
+    // Errors reading the file are logged where they happen, and the database is left as it is.
+    let Ok(declared) = load_shortcuts_file(path) else {
+        return;
+    };
+
+    match shortcut_service.sync(&declared, dry_run).await {
+        Ok(plan) if plan.is_empty() => info!("Shortcuts are in sync with {}.", path.display()),
+        Ok(plan) => {
+            let (creates, updates, deletes) = plan.counts();
+            let action = if dry_run { "Planned (dry run)" } else { "Applied" };
+
+            warn!("Shortcuts have drifted from {}. {}: {} creates, {} updates and {} deletes.", path.display(), action, creates, updates, deletes);
+            plan.changes.iter().for_each(|change| info!("  {}", change));
+        },
+        Err(err) => error!("Failed to sync shortcuts with {}: {}", path.display(), err),
+    }
+}
+
+/// Reloads the templates whenever one changes, and refreshes the open pages through the returned layer.
+///
+/// Only debug builds watch for changes, release builds embed the templates so there is nothing to watch.
+#[cfg(debug_assertions)]
+fn hot_reload(theme_dir: Option<PathBuf>, tasks: &mut JoinSet<()>, shutdown: channel::Receiver<bool>) -> LiveReloadLayer {
     let livereload: LiveReloadLayer = LiveReloadLayer::new();
     let reloader = livereload.reloader();
 
-    let connection: Pool<Sqlite> = connect_db().await;
+    // Only directories on disk can be watched, the built-in templates are only on disk in debug builds.
+    let watched: Vec<PathBuf> = [Some(PathBuf::from(TEMPLATES_SOURCE)), theme_templates(theme_dir.as_deref())]
+        .into_iter()
+        .flatten()
+        .filter(|directory| directory.is_dir())
+        .collect();
+
+    let debouncer = watch(
+        move || {
+            info!("Reloading...");
+            match load_templates(theme_dir.as_deref()) {
+                Ok(tera) => TERA.store(Arc::new(tera)),
+                Err(err) => error!("Failed to reload templates: {:?}", err),
+            }
+            reloader.reload();
+        },
+        Duration::from_millis(10), // if you have tailwindcss and your machine is slow, you can increase this value
+        watched
+    );
+
+    // The watcher stops when dropped, so it is kept alive until the server shuts down.
+    tasks.spawn(async move {
+        let _debouncer = debouncer;
+        stopped(shutdown).await
+    });
+    info!("Hot reload set-up complete.");
+
+    livereload
+}
+
+/// Waits for the server to start shutting down, or for the `App` to be dropped.
+async fn stopped(mut shutdown: channel::Receiver<bool>) {
+    let _ = shutdown.wait_for(|stopping| *stopping).await;
+}
+
+/// Waits for `SIGINT` (Ctrl+C) or, on Unix, `SIGTERM`.
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {}", err);
+            std::future::pending::<()>().await
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => { terminate.recv().await; },
+            Err(err) => {
+                error!("Failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await
+            },
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => info!("Received Ctrl+C, shutting down."),
+        _ = terminate => info!("Received SIGTERM, shutting down."),
+    }
+}
+
+/// The app, along with what has to be stopped cleanly when the server shuts down.
+pub struct App {
+    pub router: Router,
+    database: Database,
+    /// Set to `true` to tell the background tasks to stop, which they also do when it's dropped.
+    shutdown: channel::Sender<bool>,
+    /// The background tasks, e.g. scheduled backups and the file watchers.
+    tasks: JoinSet<()>,
+}
+
+impl App {
+    /// Serves requests until `signal` completes, then shuts down gracefully.
+    ///
+    /// New connections are refused and the requests in flight are given up to `drain_timeout` to finish.
+    /// Then the background tasks are stopped, letting a backup that has started finish, and the database is closed.
+    ///
+    /// # Parameters
+    /// - `listener`: Where to accept connections.
+    /// - `signal`: Completes when the server should shut down, e.g. `shutdown_signal`.
+    /// - `drain_timeout`: How long to wait for the requests in flight, and then again for the background tasks.
+    ///
+    /// # Returns
+    /// - `io::Result<()>`, will be the error if the server stopped for any reason other than the signal.
+    pub async fn serve(self, listener: Listener, signal: impl Future<Output = ()> + Send + 'static, drain_timeout: Duration) -> io::Result<()> {
+        let App { router, database, shutdown, mut tasks } = self;
+        let signalled: Arc<Notify> = Arc::new(Notify::new());
+        let notify: Arc<Notify> = signalled.clone();
+
+        let served = listener.serve(router, async move {
+            signal.await;
+            notify.notify_one();
+        });
+        let draining = async {
+            signalled.notified().await;
+            tokio::time::sleep(drain_timeout).await;
+        };
+
+        let result: io::Result<()> = tokio::select! {
+            result = served => result,
+            _ = draining => {
+                warn!("Requests didn't finish within {:?}, shutting down anyway.", drain_timeout);
+                Ok(())
+            },
+        };
+
+        let _ = shutdown.send(true);
+        let finished = tokio::time::timeout(drain_timeout, async {
+            while tasks.join_next().await.is_some() {}
+        });
+        if finished.await.is_err() {
+            warn!("Background tasks didn't finish within {:?}, stopping them.", drain_timeout);
+            tasks.shutdown().await;
+        }
+
+        database.close().await;
+        info!("Shut down cleanly.");
+
+        result
+    }
+}
+
+pub async fn app(config: Config) -> Result<App, ()> {
+    TERA.store(Arc::new(load_templates(config.theme_dir.as_deref()).map_err(|err| error!("Failed to load templates: {:?}", err))?));
+
+    let database: Database = connect_db(&config.database_url, &config.pool(), config.backup_dir.as_deref()).await.map_err(|err| error!("{}", err))?;
     info!("connected to database");
+
+    let (shutdown, _) = channel::channel(false);
+    let mut tasks: JoinSet<()> = JoinSet::new();
+
+    if let Some(backup) = config.backup() {
+        if let Some(pool) = database.sqlite() {
+            info!("Backing up database to {} every {:?}, keeping {}.", backup.directory.display(), backup.interval, backup.keep);
+            tasks.spawn(schedule_backups(pool.clone(), backup, shutdown.subscribe()));
+        } else {
+            warn!("Scheduled backups are only for SQLite databases, so none will be made.");
+        }
+    }
     
-    let shortcut_repository: ShortcutRepository = ShortcutRepository::new(connection);
+    let settings_repository: SettingsRepository = SettingsRepository::new(database.clone());
+    let settings: Settings = settings_repository.load().await.unwrap_or_else(|err| {
+        error!("{}, using the defaults.", err);
+        Settings::default()
+    });
+    let settings_service = SettingsService::new(settings_repository, settings);
+
+    let shortcut_repository: ShortcutRepository = ShortcutRepository::new(database.clone());
 
     let shortcut_service = ShortcutService::new(shortcut_repository);
 
-    let state: AppState = AppState::new(shortcut_service);
+    // GitOps mode, where the shortcuts declared in `shortcuts_file` are synced at startup and on every change.
+    if let Some(path) = config.shortcuts_file.clone() {
+        let dry_run: bool = config.shortcuts_dry_run;
+
+        sync_shortcuts_file(&shortcut_service, &path, dry_run).await;
+
+        // The watcher only queues a sync, so one runs at a time, and a change made while it runs is synced once it's done.
+        let (changed, mut changes) = mpsc::channel::<()>(1);
+        let sync_debouncer = watch_shortcuts_file(&path, Duration::from_millis(500), move || { let _ = changed.try_send(()); })
+            .map_err(|err| error!("Failed to watch {}: {}", path.display(), err))?;
+
+        let (service, stopping) = (shortcut_service.clone(), stopped(shutdown.subscribe()));
+        tasks.spawn(async move {
+            // The watcher stops when dropped, so it is kept alive until the server shuts down.
+            let _sync_debouncer = sync_debouncer;
+            tokio::pin!(stopping);
+            loop {
+                tokio::select! {
+                    Some(()) = changes.recv() => sync_shortcuts_file(&service, &path, dry_run).await,
+                    _ = &mut stopping => break,
+                }
+            }
+        });
+        info!("Shortcuts file sync set-up complete.");
+    }
+
+    #[cfg(debug_assertions)]
+    let theme_dir: Option<PathBuf> = config.theme_dir.clone();
+    let base_path: String = config.base_path.clone();
+    let origins: Vec<HeaderValue> = config.cors_origins().iter().filter_map(|origin| HeaderValue::from_str(origin).ok()).collect();
+
+    let state: AppState = AppState::new(shortcut_service, settings_service, Arc::new(config), database.clone());
     info!("done intializing appstate");
 
     let cors = CorsLayer::new()
         .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::PUT])
-        .allow_origin(Any)
-        .allow_headers([CONTENT_TYPE]);
+        .allow_origin(origins)
+        .allow_headers([CONTENT_TYPE, IF_MATCH])
+        .expose_headers([ETAG]);
     
-    let app: Router = Router::new()
-        .merge(create_ui_routes())
-        .nest_service("/assets", ServeDir::new("ui/assets"))
-        .nest("/api", create_api_routes())
-        .layer(livereload)
+    let ui: Router = Router::new()
+        .merge(create_ui_routes(&base_path))
+        .route("/assets/{*path}", get(serve_asset))
+        .nest("/api", create_api_routes());
+
+    // The probes and metrics stay at the root, for whatever checks the server directly rather than through the proxy.
+    let app: Router = match base_path.as_str() {
+        "" => ui,
+        base_path => Router::new()
+            .route("/", get(redirect_to_search))
+            .route(&format!("{}/", base_path), get(redirect_to_search))
+            .nest(base_path, ui),
+    }.merge(create_health_routes());
+
+    #[cfg(debug_assertions)]
+    let app: Router = app.layer(hot_reload(theme_dir, &mut tasks, shutdown.subscribe()));
+
+    let app: Router = app
         .layer(cors)
         .layer(TraceLayer::new_for_http())
+        .layer(from_fn(track_metrics))
+        .layer(from_fn(middleware::base_path))
         .layer(CompressionLayer::new())
         .layer(Extension(state));
 
-    let _debouncer = watch(
-        move || {
-            info!("Reloading...");
-            let _ = TERA.write().unwrap().full_reload();
-            reloader.reload();
-        },
-        Duration::from_millis(10), // if you have tailwindcss and your machine is slow, you can increase this value
-        vec!["./ui/templates"] // this is now listening for changes in the templates folder add any other folders you want to watch this can be your folder that holds your JS files or CSS or whatever you are serving in your app
-    );
-    info!("Hot reload set-up complete.");
-    
-    Ok(app)
+    Ok(App { router: app, database, shutdown, tasks })
 }
 
-pub async fn run() {
-    dotenv().ok();
-
+fn init_tracing() {
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
@@ -87,15 +319,170 @@ pub async fn run() {
         )
         .with(tracing_subscriber::fmt::layer())
         .init();
+}
+
+/// Leaves the server that is already running at `ui_url` to it, opening its UI unless `minimized`, and exits.
+fn hand_over(ui_url: &str, minimized: bool) -> ! {
+    info!("shortcut is already running at {}", ui_url);
+    if !minimized {
+        if let Err(err) = open_in_browser(ui_url) {
+            warn!("Failed to open {} in the browser: {}", ui_url, err);
+        }
+    }
+
+    std::process::exit(0);
+}
+
+/// Takes the lock that only one server of the database holds at a time, handing over to the server holding it, if there is one.
+///
+/// Runs without the lock when it can't be taken, e.g. without a data directory, as the port still can't be shared.
+/// An ephemeral server never takes it, as it shares no database with another.
+async fn lock_instance(database_url: &str, minimized: bool) -> Option<InstanceLock> {
+    if database_url == MEMORY_URL {
+        return None;
+    }
+    let Some(directory) = instance_dir(database_url) else {
+        warn!("No data directory to keep the lock in, so another running shortcut won't be found.");
+        return None;
+    };
+
+    match InstanceLock::acquire(&directory) {
+        Ok(lock) => Some(lock),
+        Err(InstanceError::AlreadyRunning(Some(instance))) => {
+            if probe(&instance.address).await {
+                hand_over(&instance.ui_url, minimized);
+            }
+            error!("shortcut is already running (pid {}), but isn't answering on {}.", instance.pid, instance.address);
+            std::process::exit(1);
+        },
+        Err(InstanceError::AlreadyRunning(None)) => {
+            error!("shortcut is already running, but is still starting. Try again in a moment.");
+            std::process::exit(1);
+        },
+        Err(err) => {
+            warn!("{}", err);
+            None
+        },
+    }
+}
+
+/// Starts the server, opening the UI in the browser unless `minimized`, as it is when started on login.
+///
+/// Hands over to a server that's already running, see `lock_instance`.
+pub async fn run(mut config: Config, minimized: bool) {
+    init_tracing();
+
+    // Held until the server has shut down.
+    let lock: Option<InstanceLock> = lock_instance(&config.database_url, minimized).await;
+
+    if let Some(enabled) = config.autostart {
+        match autostart::set_enabled(enabled) {
+            Ok(()) => info!("Start on login {}.", if enabled { "enabled" } else { "disabled" }),
+            Err(err) => warn!("{}", err),
+        }
+    }
+
+    let listener: Listener = match bind(&config).await {
+        Ok(listener) => listener,
+        // The lock is per user, so this can still be shortcut run by someone else, or with another data directory.
+        Err(err) if err.kind() == io::ErrorKind::AddrInUse => {
+            let scheme: &str = if config.tls_cert.is_some() { "https://" } else { "" };
+            if probe(&format!("{}{}", scheme, SocketAddr::new(config.host, config.port))).await {
+                hand_over(&config.ui_url(), minimized);
+            }
+            error!(
+                "Port {} is in use by another program. Use another with --port, or set port_fallback = true to use the next free one.",
+                config.port,
+            );
+            std::process::exit(1);
+        },
+        Err(err) => {
+            error!("Failed to listen on {}:{}: {}", config.host, config.port, err);
+            std::process::exit(1);
+        },
+    };
+    info!("listening on {}", listener);
+    if let Some(port) = listener.port() {
+        config.port = port;
+    }
+
+    // Behind a Unix socket the UI is only reachable through the reverse proxy, at the configured `ui_url`.
+    let browsable: bool = matches!(listener, Listener::Tcp(_) | Listener::Tls(_)) || config.ui_url.is_some();
+    let ui_url = config.ui_url();
+    let drain_timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    // The cause has already been logged, e.g. a failed migration.
+    let Ok(app) = app(config).await else {
+        std::process::exit(1);
+    };
+    // Recorded once the database is ready, so a second server finds this one answering.
+    if let Some(lock) = &lock {
+        let instance = Instance { pid: std::process::id(), address: listener.to_string(), ui_url: ui_url.clone() };
+        if let Err(err) = lock.record(&instance) {
+            warn!("{}", err);
+        }
+    }
+
+    if !minimized && browsable {
+        if let Err(err) = open_in_browser(&ui_url) {
+            warn!("Failed to open {} in the browser: {}", ui_url, err);
+        }
+    }
+
+    if let Err(err) = app.serve(listener, shutdown_signal(), drain_timeout).await {
+        error!("Server stopped: {}", err);
+        std::process::exit(1);
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod lib_tests {
+    mod serve_tests {
+        use sqlx::SqlitePool;
+        use std::{fs, path::PathBuf, time::Duration};
+        use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream}, sync::oneshot};
+
+        use crate::{app, config::Config, listener::Listener};
+
+        #[tokio::test]
+        async fn finishes_requests_and_closes_database_when_signalled() {
+            let directory: PathBuf = std::env::temp_dir().join(format!("shortcut-lib-tests-{}-serve", std::process::id()));
+            let _ = fs::remove_dir_all(&directory);
+            fs::create_dir_all(&directory).unwrap();
+            let database_url = format!("sqlite://{}?mode=rwc", directory.join("shortcut.db").display());
 
-    let port = std::env::var("PORT").unwrap_or(String::from("3000"));
+            let app = app(Config { database_url: database_url.clone(), ..Config::default() }).await.unwrap();
+            let database = app.database.clone();
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let listener = Listener::Tcp(listener);
+            let (trigger, signal) = oneshot::channel::<()>();
+            let server = tokio::spawn(app.serve(listener, async { let _ = signal.await; }, Duration::from_secs(5)));
 
-    let addr = format!("0.0.0.0:{}", port);
-    info!("listening on {}", addr);
+            // The connection is kept alive after the response, so shutting down has to close it
+            let body = "keyword=gh&url=https://github.com";
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            stream.write_all(format!(
+                "POST /api/post HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(), body,
+            ).as_bytes()).await.unwrap();
+            let mut response = [0; 12];
+            stream.read_exact(&mut response).await.unwrap();
+            assert_eq!(&response, b"HTTP/1.1 200");
 
-    let listener = TcpListener::bind(addr).await.unwrap();
+            trigger.send(()).unwrap();
+            let result = tokio::time::timeout(Duration::from_secs(10), server).await.unwrap().unwrap();
 
-    let app = app().await.unwrap();
+            assert!(result.is_ok());
+            assert!(database.sqlite().unwrap().is_closed());
+            // The rest of the response, up to the server closing the connection
+            let mut rest = Vec::new();
+            stream.read_to_end(&mut rest).await.unwrap();
+            assert!(String::from_utf8_lossy(&rest).contains("Successfully created shortcut!"));
 
-    axum::serve(listener, app).await.unwrap();
+            let reopened = SqlitePool::connect(&database_url).await.unwrap();
+            let (url,): (String,) = sqlx::query_as("SELECT url FROM shortcut WHERE keyword = 'gh'").fetch_one(&reopened).await.unwrap();
+            assert_eq!(url, "https://github.com");
+        }
+    }
 }
\ No newline at end of file
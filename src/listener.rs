@@ -0,0 +1,227 @@
+use crate::{config::Config, tls::{acceptor, TlsListener}};
+
+use axum::Router;
+#[cfg(unix)]
+use listenfd::ListenFd;
+use std::{fmt, future::Future, io, net::SocketAddr};
+#[cfg(unix)]
+use std::{fs, os::unix::fs::FileTypeExt, path::{Path, PathBuf}};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tracing::warn;
+#[cfg(unix)]
+use tracing::info;
+
+/// How many ports, from `port` on, are tried when `port_fallback` is set.
+const FALLBACK_PORTS: usize = 10;
+
+/// Where the server accepts connections.
+pub enum Listener {
+  Tcp(TcpListener),
+  /// HTTPS, when `tls_cert` and `tls_key` are set.
+  Tls(TlsListener),
+  /// A Unix domain socket, e.g. for a reverse proxy on the same machine.
+  #[cfg(unix)]
+  Unix(UnixListener, PathBuf),
+}
+
+/// Used to log where the server is listening, e.g. `127.0.0.1:3000`, `https://127.0.0.1:3000` or `unix:/run/shortcut.sock`.
+impl fmt::Display for Listener {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Listener::Tcp(listener) => match listener.local_addr() {
+        Ok(address) => write!(f, "{}", address),
+        Err(_) => write!(f, "a TCP socket"),
+      },
+      Listener::Tls(listener) => write!(f, "https://{}", listener.local_addr()),
+      #[cfg(unix)]
+      Listener::Unix(_, path) => write!(f, "unix:{}", path.display()),
+    }
+  }
+}
+
+impl Listener {
+  /// The port of a TCP listener, which can differ from the configured one after falling back or when given by systemd.
+  pub fn port(&self) -> Option<u16> {
+    match self {
+      Listener::Tcp(listener) => listener.local_addr().ok().map(|address| address.port()),
+      Listener::Tls(listener) => Some(listener.local_addr().port()),
+      #[cfg(unix)]
+      Listener::Unix(..) => None,
+    }
+  }
+
+  /// Serves the app until `signal` completes, then waits for the connections to finish, see `App::serve`.
+  pub async fn serve(self, router: Router, signal: impl Future<Output = ()> + Send + 'static) -> io::Result<()> {
+    match self {
+      Listener::Tcp(listener) => axum::serve(listener, router).with_graceful_shutdown(signal).await,
+      Listener::Tls(listener) => axum::serve(listener, router).with_graceful_shutdown(signal).await,
+      #[cfg(unix)]
+      Listener::Unix(listener, _) => axum::serve(listener, router).with_graceful_shutdown(signal).await,
+    }
+  }
+}
+
+/// Takes over the first socket passed by systemd socket activation (`LISTEN_FDS`), which can be either a TCP or a Unix domain socket.
+///
+/// # Parameters
+/// - `fds`: The sockets passed to this process, see `ListenFd::from_env`.
+///
+/// # Returns
+/// - `io::Result<Option<Listener>>`, will be `None` when no socket was passed, only the first one is used.
+#[cfg(unix)]
+fn take_activated(fds: &mut ListenFd) -> io::Result<Option<Listener>> {
+  // A socket that isn't TCP is left in place by `take_tcp_listener`, to be taken as a Unix domain socket instead.
+  if let Ok(Some(listener)) = fds.take_tcp_listener(0) {
+    listener.set_nonblocking(true)?;
+    return Ok(Some(Listener::Tcp(TcpListener::from_std(listener)?)));
+  }
+
+  let Some(listener) = fds.take_unix_listener(0)? else {
+    return Ok(None);
+  };
+  listener.set_nonblocking(true)?;
+  let path: PathBuf = listener.local_addr()?.as_pathname().map(Path::to_path_buf).unwrap_or_default();
+
+  Ok(Some(Listener::Unix(UnixListener::from_std(listener)?, path)))
+}
+
+/// Listens on a Unix domain socket, replacing the socket left behind by a server that didn't shut down.
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> io::Result<Listener> {
+  if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+    fs::remove_file(path)?;
+  }
+
+  Ok(Listener::Unix(UnixListener::bind(path)?, path.to_path_buf()))
+}
+
+/// Opens where the server accepts connections, in order of precedence:
+/// 1. The socket passed by systemd socket activation (`LISTEN_FDS`), on Unix.
+/// 2. The Unix domain socket at `unix_socket`, on Unix.
+/// 3. `host` and `port`, or the next free port after it when `port_fallback` is set.
+///
+/// A TCP socket serves HTTPS when `tls_cert` and `tls_key` are set. They're loaded before anything is opened,
+/// so a missing or invalid certificate stops the server without taking the port.
+///
+/// # Parameters
+/// - `config`: The config of the server.
+///
+/// # Returns
+/// - `io::Result<Listener>`, will be the listener, or the error if it couldn't be opened e.g. the port is in use
+///   or the certificate can't be read.
+pub async fn bind(config: &Config) -> io::Result<Listener> {
+  let acceptor = match (&config.tls_cert, &config.tls_key) {
+    (Some(cert), Some(key)) => Some(acceptor(cert, key).map_err(io::Error::other)?),
+    _ => None,
+  };
+
+  match (open(config).await?, acceptor) {
+    (Listener::Tcp(listener), Some(acceptor)) => Ok(Listener::Tls(TlsListener::new(listener, acceptor)?)),
+    (listener, _) => Ok(listener),
+  }
+}
+
+/// Opens the socket to listen on, see `bind`.
+async fn open(config: &Config) -> io::Result<Listener> {
+  #[cfg(unix)]
+  {
+    if let Some(listener) = take_activated(&mut ListenFd::from_env())? {
+      info!("Using the socket passed by systemd.");
+      return Ok(listener);
+    }
+
+    if let Some(path) = &config.unix_socket {
+      return bind_unix(path);
+    }
+  }
+
+  let attempts: usize = if config.port_fallback { FALLBACK_PORTS } else { 1 };
+  let mut ports = (config.port..=u16::MAX).take(attempts).peekable();
+  while let Some(port) = ports.next() {
+    match TcpListener::bind(SocketAddr::new(config.host, port)).await {
+      Err(err) if err.kind() == io::ErrorKind::AddrInUse && ports.peek().is_some() => warn!("Port {} is in use, trying {}.", port, port + 1),
+      result => return result.map(Listener::Tcp),
+    }
+  }
+
+  Err(io::Error::new(io::ErrorKind::AddrNotAvailable, "no port to listen on"))
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(all(test, unix))]
+mod listener_tests {
+  mod bind_tests {
+    use listenfd::ListenFd;
+    use std::{env, fs, os::unix::io::IntoRawFd, path::PathBuf};
+    use tokio::sync::Mutex;
+
+    use crate::{config::Config, listener::{bind, take_activated, Listener}};
+
+    /// Held by every test that binds, as `bind` would otherwise take the sockets passed in `LISTEN_FDS` by another test.
+    static LISTEN_FDS: Mutex<()> = Mutex::const_new(());
+
+    /// The sockets passed to this process, as systemd passes them, starting from the given one.
+    fn activated(fd: i32) -> ListenFd {
+      env::set_var("LISTEN_FDS_FIRST_FD", fd.to_string());
+      env::set_var("LISTEN_FDS", "1");
+      env::set_var("LISTEN_PID", std::process::id().to_string());
+      let fds = ListenFd::from_env();
+      env::remove_var("LISTEN_FDS_FIRST_FD");
+
+      fds
+    }
+
+    fn socket_path(name: &str) -> PathBuf {
+      std::env::temp_dir().join(format!("shortcut-listener-tests-{}-{}.sock", std::process::id(), name))
+    }
+
+    #[tokio::test]
+    async fn binds_unix_socket_replacing_stale_one() {
+      let _listen_fds = LISTEN_FDS.lock().await;
+      let path = socket_path("stale");
+      let _ = fs::remove_file(&path);
+      drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+      let config = Config { unix_socket: Some(path.clone()), ..Config::default() };
+
+      let result = bind(&config).await.unwrap();
+
+      assert_eq!(result.to_string(), format!("unix:{}", path.display()));
+      fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn binds_next_port_when_in_use_and_falling_back() {
+      let _listen_fds = LISTEN_FDS.lock().await;
+      let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+      let port = taken.local_addr().unwrap().port();
+      let config = Config { host: "127.0.0.1".parse().unwrap(), port, ..Config::default() };
+
+      let without_fallback = bind(&config).await;
+      let result = bind(&Config { port_fallback: true, ..config }).await.unwrap();
+
+      assert!(matches!(without_fallback, Err(err) if err.kind() == std::io::ErrorKind::AddrInUse));
+      assert!(result.port().is_some_and(|chosen| chosen > port));
+    }
+
+    #[tokio::test]
+    async fn takes_over_activated_tcp_and_unix_sockets() {
+      let _listen_fds = LISTEN_FDS.lock().await;
+      let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+      let address = tcp.local_addr().unwrap();
+      let path = socket_path("activated");
+      let _ = fs::remove_file(&path);
+      let unix = std::os::unix::net::UnixListener::bind(&path).unwrap();
+
+      let tcp_result = take_activated(&mut activated(tcp.into_raw_fd())).unwrap().unwrap();
+      let unix_result = take_activated(&mut activated(unix.into_raw_fd())).unwrap().unwrap();
+
+      assert!(matches!(tcp_result, Listener::Tcp(_)));
+      assert_eq!(tcp_result.to_string(), address.to_string());
+      assert!(matches!(unix_result, Listener::Unix(_, ref activated) if *activated == path));
+      assert!(take_activated(&mut ListenFd::empty()).unwrap().is_none());
+      fs::remove_file(&path).unwrap();
+    }
+  }
+}
\ No newline at end of file
@@ -0,0 +1,15 @@
+/// A macro to run the same code against whichever SQL backend a `Database` holds, with its pool bound to the given name,
+/// and the given code for the in-memory `MemoryStore` of an ephemeral server.
+///
+/// The code is repeated for each backend, so it can call backend-specific functions as long as both have one with the same name.
+#[macro_export]
+macro_rules! dispatch {
+  ($database:expr, $pool:ident => $body:expr, $memory:pat => $in_memory:expr) => {
+    match $database {
+      $crate::schema::Database::Sqlite($pool) => $body,
+      #[cfg(feature = "postgres")]
+      $crate::schema::Database::Postgres($pool) => $body,
+      $crate::schema::Database::Memory($memory) => $in_memory,
+    }
+  };
+}
\ No newline at end of file
@@ -1,2 +1,4 @@
 #[macro_use]
+pub mod database;
+#[macro_use]
 pub mod renderable;
\ No newline at end of file
@@ -1,19 +1,86 @@
+use crate::{error::TemplateError, metrics::METRICS};
+
 use axum::response::Html;
-use tera::Tera;
+use serde::Serialize;
+use std::{error::Error, time::Instant};
+use tera::{Context, Tera};
+
+/// The error and each of its sources, as `tera` keeps the cause of a failed render (e.g. the missing variable) in the sources.
+fn describe(err: &(dyn Error + 'static)) -> String {
+  std::iter::successors(Some(err), |&err| err.source())
+    .map(|err| err.to_string())
+    .collect::<Vec<String>>()
+    .join(": ")
+}
 
 /// `trait` for templates that require rendering from other files e.g. shared templates.
-pub trait Renderable {
-  fn get_html(&self, tera: Tera) -> Html<String>;
+pub trait Renderable: Serialize {
+  /// The path of the template, relative to the templates directory.
+  const TEMPLATE: &'static str;
+
+  /// Renders the template with `self` as its context, along with `base_path` that every link in the UI starts with.
+  ///
+  /// # Parameters
+  /// - `tera`: The loaded templates, borrowed from `TERA` so nothing is copied per render.
+  /// - `base_path`: The path the UI is served under for the request, see `BasePath`.
+  ///
+  /// # Returns
+  /// - `Result<Html<String>, TemplateError>`, will be the rendered template, or a `TemplateError` if it fails to render,
+  ///   e.g. a theme template using a variable that doesn't exist.
+  fn get_html(&self, tera: &Tera, base_path: &str) -> Result<Html<String>, TemplateError> {
+    let start: Instant = Instant::now();
+    let result = Context::from_serialize(self)
+      .and_then(|mut context| {
+        context.insert("base_path", base_path);
+        tera.render(Self::TEMPLATE, &context)
+      })
+      .map(Html)
+      .map_err(|err| TemplateError::FailedToRender(Self::TEMPLATE.to_owned(), describe(&err)));
+    METRICS.render_duration.observe(&[Self::TEMPLATE], start.elapsed());
+
+    result
+  }
 }
 
-/// A macro to make a `renderable` implementation for the given struct to give pub access to rendering the template.
+/// A macro to make a `renderable` implementation for each given struct and the path of its template.
 #[macro_export]
 macro_rules! impl_renderable {
-  ($($t:ty),*) => {
+  ($($t:ty => $path:literal),* $(,)?) => {
     $(impl Renderable for $t {
-      fn get_html(&self, tera: Tera) -> Html<String> {
-        Html(self.render(&tera))
-      }
+      const TEMPLATE: &'static str = $path;
     })*
   };
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod renderable_tests {
+  mod get_html_tests {
+    use tera::Tera;
+
+    use crate::{error::TemplateError, macros::renderable::Renderable, templates::components::ErrorAlert};
+
+    fn tera(template: &str) -> Tera {
+      let mut tera = Tera::default();
+      tera.add_raw_template("components/alert/error.html", template).unwrap();
+
+      tera
+    }
+
+    #[test]
+    fn returns_rendered_template() {
+      let context = ErrorAlert { error: "Oops".to_owned(), successful: false };
+
+      assert_eq!(context.get_html(&tera("{% if base_path == '/go' %}{{ error }}{% endif %}"), "/go").unwrap().0, "Oops");
+    }
+
+    #[test]
+    fn returns_exception_naming_template_and_cause() {
+      let context = ErrorAlert { error: "Oops".to_owned(), successful: false };
+
+      let result = context.get_html(&tera("{{ missing }}"), "");
+
+      assert!(matches!(result, Err(TemplateError::FailedToRender(template, cause)) if template == "components/alert/error.html" && cause.contains("missing")));
+    }
+  }
 }
\ No newline at end of file
@@ -0,0 +1,247 @@
+use crate::{error::ShortcutError, schema::Database};
+
+use axum::{extract::{MatchedPath, Request}, middleware::Next, response::Response};
+use std::{collections::BTreeMap, fmt::Write, sync::{LazyLock, Mutex, PoisonError}, time::{Duration, Instant}};
+
+/// The metrics of the server, served in the Prometheus text format by `GET /metrics`.
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// The upper bounds (in seconds) of the latency buckets, from 100µs to 5s.
+const LATENCY_BUCKETS: &[f64] = &[0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Quotes a label value, escaping what the text format requires.
+fn quote(value: &str) -> String {
+  format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// Formats the labels of a sample e.g. `{method="GET",route="/search"}`, nothing when there aren't any.
+fn format_labels(names: &[&str], values: &[String], extra: Option<(&str, String)>) -> String {
+  let pairs: Vec<String> = names.iter().zip(values)
+    .map(|(name, value)| format!("{}={}", name, quote(value)))
+    .chain(extra.map(|(name, value)| format!("{}={}", name, quote(&value))))
+    .collect();
+
+  if pairs.is_empty() { String::new() } else { format!("{{{}}}", pairs.join(",")) }
+}
+
+/// A counter for each set of label values.
+pub struct Counters {
+  name: &'static str,
+  help: &'static str,
+  labels: &'static [&'static str],
+  values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+
+impl Counters {
+  pub fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+    Self { name, help, labels, values: Mutex::new(BTreeMap::new()) }
+  }
+
+  /// Adds one to the counter with the label values, given in the same order as the label names.
+  pub fn increment(&self, values: &[&str]) {
+    let mut counters = self.values.lock().unwrap_or_else(PoisonError::into_inner);
+    *counters.entry(values.iter().map(|value| value.to_string()).collect()).or_default() += 1;
+  }
+
+  /// Writes every counter in the text format.
+  fn render(&self, out: &mut String) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter", self.name, self.help, self.name);
+    for (values, count) in self.values.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+      let _ = writeln!(out, "{}{} {}", self.name, format_labels(self.labels, values, None), count);
+    }
+  }
+}
+
+/// The observations of a single histogram, counted into the bucket of each upper bound they're under.
+#[derive(Default)]
+struct Histogram {
+  buckets: Vec<u64>,
+  sum: f64,
+  count: u64,
+}
+
+/// A histogram of durations for each set of label values.
+pub struct Histograms {
+  name: &'static str,
+  help: &'static str,
+  labels: &'static [&'static str],
+  values: Mutex<BTreeMap<Vec<String>, Histogram>>,
+}
+
+impl Histograms {
+  pub fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+    Self { name, help, labels, values: Mutex::new(BTreeMap::new()) }
+  }
+
+  /// Records a duration in the histogram with the label values, given in the same order as the label names.
+  pub fn observe(&self, values: &[&str], duration: Duration) {
+    let seconds: f64 = duration.as_secs_f64();
+    let mut histograms = self.values.lock().unwrap_or_else(PoisonError::into_inner);
+    let histogram: &mut Histogram = histograms.entry(values.iter().map(|value| value.to_string()).collect()).or_default();
+
+    histogram.buckets.resize(LATENCY_BUCKETS.len(), 0);
+    if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+      histogram.buckets[bucket] += 1;
+    }
+    histogram.sum += seconds;
+    histogram.count += 1;
+  }
+
+  /// Writes every histogram in the text format, where each bucket also counts the buckets below it.
+  fn render(&self, out: &mut String) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} histogram", self.name, self.help, self.name);
+    for (values, histogram) in self.values.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+      let mut cumulative: u64 = 0;
+      for (bound, count) in LATENCY_BUCKETS.iter().zip(&histogram.buckets) {
+        cumulative += count;
+        let _ = writeln!(out, "{}_bucket{} {}", self.name, format_labels(self.labels, values, Some(("le", bound.to_string()))), cumulative);
+      }
+      let _ = writeln!(out, "{}_bucket{} {}", self.name, format_labels(self.labels, values, Some(("le", "+Inf".to_owned()))), histogram.count);
+      let _ = writeln!(out, "{}_sum{} {}", self.name, format_labels(self.labels, values, None), histogram.sum);
+      let _ = writeln!(out, "{}_count{} {}", self.name, format_labels(self.labels, values, None), histogram.count);
+    }
+  }
+}
+
+/// Every metric the server keeps, the connection pool is read when the metrics are rendered instead.
+pub struct Metrics {
+  pub requests: Counters,
+  pub request_duration: Histograms,
+  pub resolutions: Counters,
+  pub writes: Counters,
+  pub search_duration: Histograms,
+  pub render_duration: Histograms,
+}
+
+impl Default for Metrics {
+  fn default() -> Self {
+    Self {
+      requests: Counters::new("shortcut_http_requests_total", "HTTP requests by route and status.", &["method", "route", "status"]),
+      request_duration: Histograms::new("shortcut_http_request_duration_seconds", "How long HTTP requests took.", &["method", "route"]),
+      resolutions: Counters::new("shortcut_resolutions_total", "Keywords resolved by outcome, hit, miss, fallback or error.", &["outcome"]),
+      writes: Counters::new("shortcut_writes_total", "Creates, updates and deletes by outcome.", &["operation", "outcome"]),
+      search_duration: Histograms::new("shortcut_search_duration_seconds", "How long fuzzy searches took.", &[]),
+      render_duration: Histograms::new("shortcut_template_render_duration_seconds", "How long templates took to render.", &["template"]),
+    }
+  }
+}
+
+impl Metrics {
+  /// Counts a create, update or delete by how it went, e.g. `success`, `not_found` or `conflict`.
+  pub fn record_write(&self, operation: &str, result: &Result<bool, ShortcutError>) {
+    let outcome: &str = match result {
+      Ok(true) => "success",
+      Ok(false) | Err(ShortcutError::NotFound) => "not_found",
+      Err(ShortcutError::UniqueConstraintError) => "duplicate",
+      Err(ShortcutError::VersionConflict | ShortcutError::VersionRequired) => "conflict",
+      Err(ShortcutError::ReadOnly) => "read_only",
+      Err(_) => "error",
+    };
+
+    self.writes.increment(&[operation, outcome]);
+  }
+
+  /// Renders every metric in the Prometheus text format.
+  ///
+  /// # Parameters
+  /// - `database`: The database, whose connection pool is reported as it is now.
+  ///
+  /// # Returns
+  /// - `String` of the metrics.
+  pub fn render(&self, database: &Database) -> String {
+    let mut out: String = String::new();
+
+    self.requests.render(&mut out);
+    self.request_duration.render(&mut out);
+    self.resolutions.render(&mut out);
+    self.writes.render(&mut out);
+    self.search_duration.render(&mut out);
+    self.render_duration.render(&mut out);
+
+    let pool: Option<(u32, usize, u32)> = dispatch!(database,
+      pool => Some((pool.size(), pool.num_idle(), pool.options().get_max_connections())),
+      _ => None
+    );
+    if let Some((size, idle, max)) = pool {
+      let _ = writeln!(out, "# HELP shortcut_db_connections Open database connections by state.\n# TYPE shortcut_db_connections gauge");
+      let _ = writeln!(out, "shortcut_db_connections{{state=\"idle\"}} {}", idle);
+      let _ = writeln!(out, "shortcut_db_connections{{state=\"in_use\"}} {}", (size as usize).saturating_sub(idle));
+      let _ = writeln!(out, "# HELP shortcut_db_max_connections The most connections the pool will open.\n# TYPE shortcut_db_max_connections gauge");
+      let _ = writeln!(out, "shortcut_db_max_connections {}", max);
+    }
+
+    out
+  }
+}
+
+/// Counts and times every request by its route, rather than its path, so each shortcut doesn't get a metric of its own.
+pub async fn track_metrics(request: Request, next: Next) -> Response {
+  let start: Instant = Instant::now();
+  let method: String = request.method().to_string();
+  let route: String = request.extensions().get::<MatchedPath>()
+    .map(|path| path.as_str().to_owned())
+    .unwrap_or_else(|| "unmatched".to_owned());
+
+  let response: Response = next.run(request).await;
+
+  METRICS.requests.increment(&[&method, &route, response.status().as_str()]);
+  METRICS.request_duration.observe(&[&method, &route], start.elapsed());
+
+  response
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod metrics_tests {
+  mod render_tests {
+    use std::time::Duration;
+
+    use crate::{error::ShortcutError, metrics::{Counters, Histograms, Metrics}, repository::memory::MemoryStore, schema::Database};
+
+    #[test]
+    fn returns_counters_with_escaped_labels() {
+      let counters = Counters::new("test_total", "A test.", &["route"]);
+      counters.increment(&["/a"]);
+      counters.increment(&["/a"]);
+      counters.increment(&["/\"b\""]);
+      let mut out = String::new();
+
+      counters.render(&mut out);
+
+      assert_eq!(out, "# HELP test_total A test.\n# TYPE test_total counter\ntest_total{route=\"/\\\"b\\\"\"} 1\ntest_total{route=\"/a\"} 2\n");
+    }
+
+    #[test]
+    fn returns_cumulative_histogram_buckets() {
+      let histograms = Histograms::new("test_seconds", "A test.", &[]);
+      histograms.observe(&[], Duration::from_micros(50));
+      histograms.observe(&[], Duration::from_millis(3));
+      histograms.observe(&[], Duration::from_secs(10));
+      let mut out = String::new();
+
+      histograms.render(&mut out);
+
+      assert!(out.contains("test_seconds_bucket{le=\"0.0001\"} 1\n"));
+      assert!(out.contains("test_seconds_bucket{le=\"0.0025\"} 1\n"));
+      assert!(out.contains("test_seconds_bucket{le=\"0.005\"} 2\n"));
+      assert!(out.contains("test_seconds_bucket{le=\"5\"} 2\n"));
+      assert!(out.contains("test_seconds_bucket{le=\"+Inf\"} 3\n"));
+      assert!(out.contains("test_seconds_count 3\n"));
+    }
+
+    #[test]
+    fn returns_writes_by_outcome() {
+      let metrics = Metrics::default();
+      metrics.record_write("update", &Ok(true));
+      metrics.record_write("update", &Ok(false));
+      metrics.record_write("update", &Err(ShortcutError::VersionConflict));
+
+      let result = metrics.render(&Database::Memory(MemoryStore::default()));
+
+      assert!(result.contains("shortcut_writes_total{operation=\"update\",outcome=\"success\"} 1\n"));
+      assert!(result.contains("shortcut_writes_total{operation=\"update\",outcome=\"not_found\"} 1\n"));
+      assert!(result.contains("shortcut_writes_total{operation=\"update\",outcome=\"conflict\"} 1\n"));
+      assert!(!result.contains("shortcut_db_connections"));
+    }
+  }
+}
\ No newline at end of file
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+/// Decides what happens to a bulk request when one of its operations fails.
+///
+/// - `AllOrNothing` rolls the whole transaction back on the first failure,
+/// - `BestEffort` rolls back only the failing operation and carries on with the rest.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkMode {
+  #[default]
+  AllOrNothing,
+  BestEffort,
+}
+
+/// A single operation within a bulk request, tagged by `op` e.g. `{ "op": "delete", "keyword": "google" }`.
+/// 
+/// Updates and deletes must give the `version` they expect, and fail if the `Shortcut` has since changed,
+/// or with `ShortcutError::VersionRequired` if it's missing, like the single `/api/update` and `/api/delete`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOperation {
+  Create { keyword: String, url: String },
+  Update { keyword: String, url: String, #[serde(default)] version: Option<i64> },
+  Delete { keyword: String, #[serde(default)] version: Option<i64> },
+}
+
+impl BulkOperation {
+  /// The keyword the operation targets.
+  pub fn keyword(&self) -> &str {
+    match self {
+      BulkOperation::Create { keyword, .. }
+        | BulkOperation::Update { keyword, .. }
+        | BulkOperation::Delete { keyword, .. } => keyword,
+    }
+  }
+
+  /// Returns a copy of the operation with the keyword lowercased, matching `Shortcut::from_request`.
+  pub fn normalised(&self) -> Self {
+    match self {
+      BulkOperation::Create { keyword, url } => BulkOperation::Create { keyword: keyword.to_lowercase(), url: url.clone() },
+      BulkOperation::Update { keyword, url, version } => BulkOperation::Update { keyword: keyword.to_lowercase(), url: url.clone(), version: *version },
+      BulkOperation::Delete { keyword, version } => BulkOperation::Delete { keyword: keyword.to_lowercase(), version: *version },
+    }
+  }
+}
+
+/// Required to cast the body of the `/api/bulk` endpoint to object.
+#[derive(Deserialize)]
+pub struct BulkRequest {
+  #[serde(default)]
+  pub mode: BulkMode,
+  pub operations: Vec<BulkOperation>,
+}
+
+/// The outcome of a single operation within a bulk request.
+///
+/// - `Applied` the operation was written (and committed),
+/// - `Failed` the operation itself failed, see `error`,
+/// - `RolledBack` the operation succeeded but was undone as another operation failed,
+/// - `Skipped` the operation was never attempted as an earlier operation failed.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkItemStatus {
+  Applied,
+  Failed,
+  RolledBack,
+  Skipped,
+}
+
+/// The per-item entry of a `BulkReport`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BulkItemResult {
+  pub index: usize,
+  pub keyword: String,
+  pub status: BulkItemStatus,
+  pub error: Option<String>,
+}
+
+/// The report returned by a bulk request, with one `BulkItemResult` per operation in request order.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BulkReport {
+  pub mode: BulkMode,
+  pub committed: bool,
+  pub results: Vec<BulkItemResult>,
+}
+
+/// Required to cast the body of the `/api/bulk/replace-url` endpoint to object.
+///
+/// Every `Shortcut` whose url contains `find` has each occurrence swapped for `replace`.
+#[derive(Deserialize)]
+pub struct ReplaceUrlRequest {
+  pub find: String,
+  pub replace: String,
+  #[serde(default)]
+  pub dry_run: bool,
+}
+
+/// A single planned (or applied) url change from a `ReplaceUrlRequest`.
+/// 
+/// The `version` is the one the `Shortcut` had when planned, so the replacement fails if it has changed since.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct UrlReplacement {
+  pub keyword: String,
+  pub old_url: String,
+  pub new_url: String,
+  pub version: Option<i64>,
+}
+
+/// The result of a `ReplaceUrlRequest`, `report` is only present when the replacements were applied.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ReplaceUrlReport {
+  pub dry_run: bool,
+  pub replacements: Vec<UrlReplacement>,
+  pub report: Option<BulkReport>,
+}
@@ -0,0 +1,19 @@
+use serde::Serialize;
+
+/// Whether a single part of the server is working, as checked by `GET /readyz`.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct ComponentStatus {
+  pub name: &'static str,
+  pub ready: bool,
+  /// How long the check took, in milliseconds.
+  pub duration_ms: f64,
+  /// What was found, e.g. the migration version, or why it isn't ready.
+  pub detail: Option<String>,
+}
+
+/// The result of `GET /readyz`, only `ready` when every component is.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct Readiness {
+  pub ready: bool,
+  pub components: Vec<ComponentStatus>,
+}
\ No newline at end of file
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// The server that's running, recorded in the data directory for the CLI and for a second server to find it.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Instance {
+  pub pid: u32,
+  /// Where it's listening, e.g. `127.0.0.1:3000` or `unix:/run/shortcut.sock`.
+  pub address: String,
+  pub ui_url: String,
+}
\ No newline at end of file
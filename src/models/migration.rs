@@ -0,0 +1,26 @@
+use serde::Serialize;
+
+/// Where a migration is up to in the database.
+///
+/// - `Applied` migrations have been run,
+/// - `Pending` migrations are embedded in the binary but haven't been run yet,
+/// - `Failed` migrations were started but didn't finish, which stops any further migrations,
+/// - `Unknown` migrations were applied by a newer version of shortcut, so aren't embedded in this one.
+#[derive(Clone, Copy, Serialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationState {
+  Applied,
+  Pending,
+  Failed,
+  Unknown,
+}
+
+/// A single migration, as listed by `shortcut migrate status`.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct MigrationStatus {
+  pub version: i64,
+  pub description: String,
+  pub state: MigrationState,
+  /// When it was applied, in the database's `CURRENT_TIMESTAMP` format.
+  pub installed_on: Option<String>,
+}
\ No newline at end of file
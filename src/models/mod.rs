@@ -1 +1,9 @@
-pub mod shortcut;
\ No newline at end of file
+pub mod bulk;
+pub mod health;
+pub mod instance;
+pub mod migration;
+pub mod settings;
+pub mod shortcut;
+pub mod sync;
+pub mod tls;
+pub mod transfer;
\ No newline at end of file
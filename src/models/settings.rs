@@ -0,0 +1,78 @@
+use crate::{error::SettingsError, models::shortcut::QUERY_PLACEHOLDER};
+
+use serde::{Deserialize, Serialize};
+
+/// The redirect status codes a `Shortcut` can be followed with: see other, temporary and permanent.
+pub const REDIRECT_STATUSES: [u16; 3] = [303, 307, 308];
+
+/// Where to send the user when a keyword has no `Shortcut`.
+///
+/// - `Search` shows the search page, offering to create it,
+/// - `Home` goes to the UI,
+/// - `Url` goes to `Settings::fallback_url`, e.g. a web search.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Fallback {
+  #[default]
+  Search,
+  Home,
+  Url,
+}
+
+/// The options that can be changed from the settings page while the server is running, stored in the `settings` table.
+///
+/// There are 6 fields of this structure:
+/// - `fallback` which decides where a keyword without a `Shortcut` goes,
+/// - `fallback_url` which is used by `Fallback::Url`, with `QUERY_PLACEHOLDER` replaced by the keyword,
+/// - `search_min_score` which a keyword has to score above (from 0 to 1) to be a search result,
+/// - `redirect_status` which is the status code a `Shortcut` is followed with, one of `REDIRECT_STATUSES`,
+/// - `trash_retention_days` which is how long deleted shortcuts are kept for by the trash,
+/// - `analytics` which turns the collection of usage analytics on.
+///
+/// `trash_retention_days` and `analytics` are saved ready for the trash and analytics, and are unused until those features land.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+  pub fallback: Fallback,
+  pub fallback_url: String,
+  pub search_min_score: f64,
+  pub redirect_status: u16,
+  pub trash_retention_days: u32,
+  pub analytics: bool,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      fallback: Fallback::Search,
+      fallback_url: format!("https://duckduckgo.com/?q={}", QUERY_PLACEHOLDER),
+      search_min_score: 0.0,
+      redirect_status: 303,
+      trash_retention_days: 30,
+      analytics: false,
+    }
+  }
+}
+
+impl Settings {
+  /// Checks every setting is within range, so a bad value is never published.
+  pub fn validate(&self) -> Result<(), SettingsError> {
+    if self.fallback == Fallback::Url && !self.fallback_url.starts_with("http") {
+      return Err(SettingsError::Invalid("the fallback url has to start with http:// or https://".to_owned()));
+    }
+    if !(0.0..1.0).contains(&self.search_min_score) {
+      return Err(SettingsError::Invalid("the search score has to be at least 0 and below 1".to_owned()));
+    }
+    if !REDIRECT_STATUSES.contains(&self.redirect_status) {
+      return Err(SettingsError::Invalid(format!("the redirect status has to be one of {:?}", REDIRECT_STATUSES)));
+    }
+
+    Ok(())
+  }
+}
+
+/// The body of a request to turn start on login on or off.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct AutostartRequest {
+  pub enabled: bool,
+}
\ No newline at end of file
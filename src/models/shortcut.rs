@@ -1,29 +1,38 @@
 use crate::utils::system_util::get_timestamp;
 
 use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use url::form_urlencoded::byte_serialize;
+
+/// The placeholder in a `Shortcut` url which is replaced with whatever was typed after the keyword, e.g. `https://github.com/search?q={query}`.
+pub const QUERY_PLACEHOLDER: &str = "{query}";
 
 /// A model used for the datamodel of the shortcuts saved.
 /// 
-/// There are 5 fields of this structure:
+/// There are 7 fields of this structure:
 /// - `id` which will be generated on creation in the db,
 /// - `create` which will be generated on first creation of the `Shortcut` object,
 /// - `updated` which will be generated on first creation and further updates of the `Shortcut` object,
 /// - `keyword` which is unique,
-/// - `url` which is unique.
+/// - `url` which is unique,
+/// - `version` which will be generated on creation in the db and incremented on every update, used as the ETag,
+/// - `managed` which marks a `Shortcut` synced from the declarative shortcuts file, read-only everywhere else.
 /// 
 /// The `keyword` and `url` is required by both construcors.
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, FromRow)]
 pub struct Shortcut {
   pub id: Option<i64>,
   pub created: Option<i64>,
   pub updated: Option<i64>,
   pub keyword: String,
   pub url: String,
+  pub version: Option<i64>,
+  #[serde(default)]
+  pub managed: bool,
 }
 
 impl Shortcut { 
   /// A constructor which takes the default fields required to create `Shortcut`.
-  #[allow(dead_code)]
   pub fn new(keyword: String, url: String) -> Self {
     let timestamp = get_timestamp();
     Self {
@@ -32,6 +41,8 @@ impl Shortcut {
       updated: Some(timestamp),
       keyword,
       url,
+      version: None,
+      managed: false,
     }
   }
 
@@ -44,19 +55,39 @@ impl Shortcut {
       updated: Some(timestamp),
       keyword: request.keyword.to_lowercase().clone(),
       url: request.url.clone(),
+      version: request.version,
+      managed: false,
     }
   }
+
+  /// The url to redirect to, with every `QUERY_PLACEHOLDER` replaced by the url encoded `query`.
+  pub fn expand(&self, query: &str) -> String {
+    expand_query(&self.url, query)
+  }
+}
+
+/// Replaces every `QUERY_PLACEHOLDER` in the url with the url encoded `query`.
+pub fn expand_query(url: &str, query: &str) -> String {
+  let encoded: String = byte_serialize(query.as_bytes()).collect();
+
+  url.replace(QUERY_PLACEHOLDER, &encoded)
 }
 
 /// Required to cast request within search endpoints to object.
+/// 
+/// The `version` is only used by the delete endpoint, where it can be given instead of an `If-Match` header.
 #[derive(Deserialize)]
 pub struct KeywordRequest {
   pub keyword: String,
+  pub version: Option<i64>,
 }
 
 /// Required to cast request within post endpoints to object.
+/// 
+/// The `version` is only used by the update endpoint, where it can be given instead of an `If-Match` header.
 #[derive(Deserialize)]
 pub struct PostRequest {
   pub keyword: String,
   pub url: String,
+  pub version: Option<i64>,
 }
\ No newline at end of file
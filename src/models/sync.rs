@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+/// The declarative shortcuts file, mapping each keyword to its url e.g. in YAML:
+/// 
+/// ```yaml
+/// shortcuts:
+///   gh: https://github.com/search?q={query}
+/// ```
+#[derive(Deserialize, Debug, Default, PartialEq)]
+pub struct ShortcutsFile {
+  #[serde(default)]
+  pub shortcuts: BTreeMap<String, String>,
+}
+
+/// A single change needed to bring the database in line with the shortcuts file.
+///
+/// - `Create` a keyword only in the file,
+/// - `Update` a keyword whose url differs from the file, or which exists but isn't managed by the file yet,
+/// - `Delete` a managed keyword which has been removed from the file.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum SyncChange {
+  Create { keyword: String, url: String },
+  Update { keyword: String, old_url: String, url: String },
+  Delete { keyword: String, url: String },
+}
+
+/// Used to print a planned (or applied) change, e.g. `~ g: https://google.co.uk -> https://google.com`.
+impl fmt::Display for SyncChange {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SyncChange::Create { keyword, url } => write!(f, "+ {}: {}", keyword, url),
+      SyncChange::Update { keyword, old_url, url } => write!(f, "~ {}: {} -> {}", keyword, old_url, url),
+      SyncChange::Delete { keyword, url } => write!(f, "- {}: {}", keyword, url),
+    }
+  }
+}
+
+/// Every `SyncChange` between the database and the shortcuts file, empty when they match.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct SyncPlan {
+  pub changes: Vec<SyncChange>,
+}
+
+impl SyncPlan {
+  /// Whether the database already matches the shortcuts file.
+  pub fn is_empty(&self) -> bool {
+    self.changes.is_empty()
+  }
+
+  /// The number of creates, updates and deletes in the plan.
+  pub fn counts(&self) -> (usize, usize, usize) {
+    self.changes.iter().fold((0, 0, 0), |(creates, updates, deletes), change| match change {
+      SyncChange::Create { .. } => (creates + 1, updates, deletes),
+      SyncChange::Update { .. } => (creates, updates + 1, deletes),
+      SyncChange::Delete { .. } => (creates, updates, deletes + 1),
+    })
+  }
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// The files written by `shortcut tls generate`.
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct CertificateFiles {
+  /// The local CA to trust on every machine that opens shortcut.
+  pub ca: PathBuf,
+  /// Whether the CA was created, rather than reused from an earlier run.
+  pub ca_created: bool,
+  pub cert: PathBuf,
+  pub key: PathBuf,
+  /// The hostnames and IP addresses the certificate is valid for.
+  pub hostnames: Vec<String>,
+  pub valid_days: u32,
+}
\ No newline at end of file
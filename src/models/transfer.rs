@@ -0,0 +1,179 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// The file formats shortcuts can be exported in and imported from.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+  #[default]
+  Json,
+  Csv,
+  Yaml,
+}
+
+impl FileFormat {
+  /// The `Content-Type` of a file in this format.
+  pub fn content_type(&self) -> &'static str {
+    match self {
+      FileFormat::Json => "application/json",
+      FileFormat::Csv => "text/csv",
+      FileFormat::Yaml => "application/yaml",
+    }
+  }
+
+  /// The file extension of a file in this format.
+  pub fn extension(&self) -> &'static str {
+    match self {
+      FileFormat::Json => "json",
+      FileFormat::Csv => "csv",
+      FileFormat::Yaml => "yaml",
+    }
+  }
+
+  /// Guesses the format of a file from the extension of its name, e.g. `shortcuts.yml`.
+  pub fn from_file_name(file_name: &str) -> Option<Self> {
+    let (_, extension) = file_name.rsplit_once('.')?;
+
+    match extension.to_lowercase().as_str() {
+      "json" => Some(FileFormat::Json),
+      "csv" => Some(FileFormat::Csv),
+      "yaml" | "yml" => Some(FileFormat::Yaml),
+      _ => None,
+    }
+  }
+}
+
+/// The formats shortcuts can be imported from, every `FileFormat` plus the keyword bookmarks of browsers.
+///
+/// - `Html` a Netscape bookmark file, as exported by every browser, where bookmarks with a `SHORTCUTURL` or `KEYWORD` are imported,
+/// - `Chrome` the `Web Data` SQLite database of a Chrome profile, imported from its `keywords` table,
+/// - `Firefox` the `places.sqlite` database of a Firefox profile, imported from its `moz_keywords` table.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+  Json,
+  Csv,
+  Yaml,
+  Html,
+  Chrome,
+  Firefox,
+}
+
+impl ImportFormat {
+  /// Guesses the format of a file from its name, including the fixed names of the browser databases.
+  pub fn from_file_name(file_name: &str) -> Option<Self> {
+    match file_name {
+      "Web Data" => return Some(ImportFormat::Chrome),
+      "places.sqlite" => return Some(ImportFormat::Firefox),
+      _ => {},
+    }
+
+    if let Some(format) = FileFormat::from_file_name(file_name) {
+      return Some(format.into());
+    }
+
+    let (_, extension) = file_name.rsplit_once('.')?;
+    match extension.to_lowercase().as_str() {
+      "html" | "htm" => Some(ImportFormat::Html),
+      _ => None,
+    }
+  }
+}
+
+impl From<FileFormat> for ImportFormat {
+  fn from(format: FileFormat) -> Self {
+    match format {
+      FileFormat::Json => ImportFormat::Json,
+      FileFormat::Csv => ImportFormat::Csv,
+      FileFormat::Yaml => ImportFormat::Yaml,
+    }
+  }
+}
+
+/// Required to cast request within the export endpoint to object.
+#[derive(Deserialize)]
+pub struct ExportRequest {
+  #[serde(default)]
+  pub format: FileFormat,
+}
+
+/// Decides what an import does with a keyword that already exists with a different url.
+///
+/// - `Skip` leaves the existing `Shortcut` as it is,
+/// - `Overwrite` replaces the existing url with the imported one,
+/// - `Rename` imports the `Shortcut` under the first free keyword of the form `keyword-2`, `keyword-3`...,
+/// - `Fail` rolls back the whole import.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+  #[default]
+  Skip,
+  Overwrite,
+  Rename,
+  Fail,
+}
+
+/// Required to cast the confirmation of a previewed import to object.
+///
+/// The `payload` is the JSON of the already parsed `Shortcut`'s, so the file only has to be uploaded once.
+#[derive(Deserialize)]
+pub struct ImportRequest {
+  pub payload: String,
+  #[serde(default)]
+  pub policy: ConflictPolicy,
+}
+
+/// What happened (or would happen) to a single imported `Shortcut`.
+///
+/// An existing keyword with the same url is always `Unchanged`, whatever the `ConflictPolicy`.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+  Created,
+  Updated,
+  Renamed,
+  Skipped,
+  Unchanged,
+  Failed,
+}
+
+/// The per-row entry of an `ImportSummary`, `old_url` is the url of the existing `Shortcut` if there was one.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ImportItem {
+  pub keyword: String,
+  pub imported_as: String,
+  pub action: ImportAction,
+  pub old_url: Option<String>,
+  pub url: String,
+}
+
+/// The outcome of an import (or a preview of one), with a count of each `ImportAction`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ImportSummary {
+  pub committed: bool,
+  pub created: usize,
+  pub updated: usize,
+  pub renamed: usize,
+  pub skipped: usize,
+  pub unchanged: usize,
+  pub failed: usize,
+  pub items: Vec<ImportItem>,
+}
+
+impl ImportSummary {
+  /// Creates the summary from its items, counting each `ImportAction`.
+  pub fn new(committed: bool, items: Vec<ImportItem>) -> Self {
+    let count = |action: ImportAction| items.iter().filter(|item| item.action == action).count();
+
+    Self {
+      committed,
+      created: count(ImportAction::Created),
+      updated: count(ImportAction::Updated),
+      renamed: count(ImportAction::Renamed),
+      skipped: count(ImportAction::Skipped),
+      unchanged: count(ImportAction::Unchanged),
+      failed: count(ImportAction::Failed),
+      items,
+    }
+  }
+}
\ No newline at end of file
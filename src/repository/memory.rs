@@ -0,0 +1,353 @@
+use crate::{
+  error::{SettingsError, ShortcutError},
+  models::{
+    bulk::{BulkItemResult, BulkItemStatus, BulkMode, BulkOperation, BulkReport},
+    settings::Settings,
+    shortcut::Shortcut,
+    sync::{SyncChange, SyncPlan},
+    transfer::{ConflictPolicy, ImportAction, ImportItem, ImportSummary},
+  },
+  repository::{settings::SettingsRepositoryTrait, shortcut::ShortcutRepositoryTrait},
+  utils::system_util::get_timestamp,
+};
+
+use futures::{stream::{self, BoxStream}, StreamExt};
+use rust_fuzzy_search::fuzzy_compare;
+use std::{collections::BTreeMap, sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard}};
+use tracing::debug;
+
+/// The shortcuts and settings of an ephemeral server, lost when it stops.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+  pub shortcuts: InMemoryShortcutRepository,
+  pub settings: InMemorySettingsRepository,
+}
+
+/// The `Shortcut`'s held by `InMemoryShortcutRepository`, keyed (and so ordered) by keyword.
+#[derive(Clone, Default)]
+struct Shortcuts {
+  by_keyword: BTreeMap<String, Shortcut>,
+  last_id: i64,
+}
+
+/// This repository keeps the `Shortcut`'s in memory instead of a db, for ephemeral servers and tests.
+///
+/// It behaves the same as `ShortcutRepository`, which the conformance tests in `shortcut_repository_tests` check:
+/// - Keywords are unique, and ids and versions are given out the same way,
+/// - Updates and deletes are compare-and-swaps that leave managed `Shortcut`'s alone,
+/// - Bulk operations, imports and syncs are all or nothing, by applying them to a copy that only replaces the `Shortcut`'s once committed.
+///
+/// Clones share the same `Shortcut`'s.
+#[derive(Clone, Default)]
+pub struct InMemoryShortcutRepository {
+  shortcuts: Arc<RwLock<Shortcuts>>,
+}
+
+impl InMemoryShortcutRepository {
+  /// Reads the `Shortcut`'s, a panic while they were held can't have left them half changed so it's ignored.
+  fn read(&self) -> RwLockReadGuard<'_, Shortcuts> {
+    self.shortcuts.read().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// Changes the `Shortcut`'s, see `read`.
+  fn write(&self) -> RwLockWriteGuard<'_, Shortcuts> {
+    self.shortcuts.write().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+impl Shortcuts {
+  /// Inserts the `Shortcut`, with a new id and the first version, as the `INSERT` of `ShortcutRepository` does.
+  fn insert(&mut self, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
+    if self.by_keyword.contains_key(&shortcut.keyword) {
+      return Err(ShortcutError::UniqueConstraintError);
+    }
+    if shortcut.created.is_none() || shortcut.updated.is_none() {
+      return Err(ShortcutError::FailedToCreate);
+    }
+
+    self.last_id += 1;
+    self.by_keyword.insert(shortcut.keyword.clone(), Shortcut {
+      id: Some(self.last_id),
+      version: Some(1),
+      managed: false,
+      ..shortcut.clone()
+    });
+
+    Ok(true)
+  }
+
+  /// Finds the unmanaged `Shortcut` a compare-and-swap can change, `Ok(None)` if the keyword doesn't exist,
+  /// `ShortcutError::ReadOnly` if it's managed or `ShortcutError::VersionConflict` if it has a different version.
+  fn changeable(&mut self, keyword: &str, version: Option<i64>) -> Result<Option<&mut Shortcut>, ShortcutError> {
+    match self.by_keyword.get_mut(keyword) {
+      None => Ok(None),
+      Some(existing) if existing.managed => Err(ShortcutError::ReadOnly),
+      Some(existing) if version.is_some() && existing.version != version => Err(ShortcutError::VersionConflict),
+      Some(existing) => Ok(Some(existing)),
+    }
+  }
+
+  /// Updates the url of the unmanaged `Shortcut` with the same keyword (and version, if given).
+  fn update_url(&mut self, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
+    let Some(existing) = self.changeable(&shortcut.keyword, shortcut.version)? else {
+      return Ok(false);
+    };
+    let Some(updated) = shortcut.updated else {
+      return Err(ShortcutError::FailedToUpdate);
+    };
+
+    existing.url = shortcut.url.clone();
+    existing.updated = Some(updated);
+    existing.version = existing.version.map(|version| version + 1);
+
+    Ok(true)
+  }
+
+  /// Deletes the unmanaged `Shortcut` with the given keyword (and version, if given).
+  fn remove(&mut self, keyword: &str, version: Option<i64>) -> Result<bool, ShortcutError> {
+    if self.changeable(keyword, version)?.is_none() {
+      return Ok(false);
+    }
+
+    Ok(self.by_keyword.remove(keyword).is_some())
+  }
+
+  /// Applies a single `BulkOperation`, treating a missing keyword as `ShortcutError::NotFound`.
+  fn apply(&mut self, operation: &BulkOperation) -> Result<(), ShortcutError> {
+    let applied = match operation {
+      BulkOperation::Update { version: None, .. } | BulkOperation::Delete { version: None, .. } => return Err(ShortcutError::VersionRequired),
+      BulkOperation::Create { keyword, url } => self.insert(&Shortcut::new(keyword.clone(), url.clone()))?,
+      BulkOperation::Update { keyword, url, version } => {
+        self.update_url(&Shortcut { version: *version, ..Shortcut::new(keyword.clone(), url.clone()) })?
+      },
+      BulkOperation::Delete { keyword, version } => self.remove(keyword, *version)?,
+    };
+
+    if applied { Ok(()) } else { Err(ShortcutError::NotFound) }
+  }
+
+  /// Imports a single `Shortcut`, resolving an existing keyword with the `ConflictPolicy`.
+  fn import_one(&mut self, shortcut: &Shortcut, policy: ConflictPolicy) -> Result<ImportItem, ShortcutError> {
+    let mut item = ImportItem {
+      keyword: shortcut.keyword.clone(),
+      imported_as: shortcut.keyword.clone(),
+      action: ImportAction::Created,
+      old_url: None,
+      url: shortcut.url.clone(),
+    };
+
+    match self.insert(shortcut) {
+      Ok(_) => return Ok(item),
+      Err(ShortcutError::UniqueConstraintError) => {},
+      Err(_) => return Err(ShortcutError::FailedToImport),
+    }
+
+    let existing_url: String = self.by_keyword.get(&shortcut.keyword).map(|existing| existing.url.clone()).ok_or(ShortcutError::FailedToImport)?;
+    item.old_url = Some(existing_url.clone());
+
+    item.action = if existing_url == shortcut.url {
+      ImportAction::Unchanged
+    } else {
+      match policy {
+        ConflictPolicy::Skip => ImportAction::Skipped,
+        ConflictPolicy::Fail => ImportAction::Failed,
+        ConflictPolicy::Overwrite => match self.update_url(&Shortcut { version: None, ..shortcut.clone() }) {
+          Ok(_) => ImportAction::Updated,
+          Err(ShortcutError::ReadOnly) => ImportAction::Skipped,
+          Err(_) => return Err(ShortcutError::FailedToImport),
+        },
+        ConflictPolicy::Rename => {
+          item.old_url = None;
+
+          let mut suffix = 2;
+          loop {
+            let renamed = Shortcut { keyword: format!("{}-{}", shortcut.keyword, suffix), ..shortcut.clone() };
+
+            match self.insert(&renamed) {
+              Ok(_) => {
+                item.imported_as = renamed.keyword;
+                break ImportAction::Renamed;
+              },
+              Err(ShortcutError::UniqueConstraintError) => suffix += 1,
+              Err(_) => return Err(ShortcutError::FailedToImport),
+            }
+          }
+        },
+      }
+    };
+
+    Ok(item)
+  }
+
+  /// Applies a single `SyncChange`, the only writes allowed to touch managed `Shortcut`'s.
+  fn apply_change(&mut self, change: &SyncChange, timestamp: i64) -> Result<(), ShortcutError> {
+    match change {
+      SyncChange::Create { keyword, url } => {
+        let shortcut = Shortcut { created: Some(timestamp), updated: Some(timestamp), ..Shortcut::new(keyword.clone(), url.clone()) };
+
+        self.insert(&shortcut)?;
+        if let Some(created) = self.by_keyword.get_mut(keyword) {
+          created.managed = true;
+        }
+      },
+      SyncChange::Update { keyword, url, .. } => {
+        if let Some(existing) = self.by_keyword.get_mut(keyword) {
+          existing.url = url.clone();
+          existing.updated = Some(timestamp);
+          existing.version = existing.version.map(|version| version + 1);
+          existing.managed = true;
+        }
+      },
+      SyncChange::Delete { keyword, .. } => {
+        if self.by_keyword.get(keyword).is_some_and(|existing| existing.managed) {
+          self.by_keyword.remove(keyword);
+        }
+      },
+    }
+
+    Ok(())
+  }
+}
+
+impl ShortcutRepositoryTrait for InMemoryShortcutRepository {
+  async fn fuzzy_search(&self, search: &str, min_score: f32) -> Result<Vec<Shortcut>, ShortcutError> {
+    let mut shortcuts: Vec<Shortcut> = self.read().by_keyword.values().cloned().collect();
+    // Ties keep the order they were created in, as they do when read from the db.
+    shortcuts.sort_by_key(|shortcut| shortcut.id);
+
+    let mut matches: Vec<(f32, Shortcut)> = shortcuts.into_iter()
+      .map(|shortcut| (fuzzy_compare(search, &shortcut.keyword), shortcut))
+      .filter(|(score, _)| *score > min_score)
+      .collect();
+
+    if matches.is_empty() {
+      return Err(ShortcutError::NoMatches);
+    }
+
+    matches.sort_by(|a: &(f32, Shortcut), b| b.0.partial_cmp(&a.0).unwrap());
+
+    debug!("Found {:?} matches.", matches.len());
+    Ok(matches.into_iter().map(|(_, shortcut)| shortcut).collect())
+  }
+
+  async fn get(&self, keyword: &str) -> Result<Shortcut, ShortcutError> {
+    self.read().by_keyword.get(keyword).cloned().ok_or(ShortcutError::NotFound)
+  }
+
+  async fn find_by_url(&self, fragment: &str) -> Result<Vec<Shortcut>, ShortcutError> {
+    Ok(self.read().by_keyword.values().filter(|shortcut| shortcut.url.contains(fragment)).cloned().collect())
+  }
+
+  fn stream_all(&self) -> BoxStream<'static, Result<Shortcut, ShortcutError>> {
+    let shortcuts: Vec<Shortcut> = self.read().by_keyword.values().cloned().collect();
+
+    stream::iter(shortcuts).map(Ok).boxed()
+  }
+
+  async fn create(&self, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
+    self.write().insert(shortcut)
+  }
+
+  async fn update(&self, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
+    self.write().update_url(shortcut)
+  }
+
+  async fn delete(&self, keyword: &str, version: Option<i64>) -> Result<bool, ShortcutError> {
+    self.write().remove(keyword, version)
+  }
+
+  async fn bulk(&self, operations: &[BulkOperation], mode: BulkMode) -> Result<BulkReport, ShortcutError> {
+    let mut shortcuts = self.write();
+    let mut transaction: Shortcuts = shortcuts.clone();
+
+    let mut results: Vec<BulkItemResult> = Vec::with_capacity(operations.len());
+    let mut failed = false;
+
+    for (index, operation) in operations.iter().enumerate() {
+      let keyword = operation.keyword().to_owned();
+
+      if failed && mode == BulkMode::AllOrNothing {
+        results.push(BulkItemResult { index, keyword, status: BulkItemStatus::Skipped, error: None });
+        continue;
+      }
+
+      // A failed operation leaves nothing behind, so best effort needs no savepoints.
+      match transaction.apply(operation) {
+        Ok(()) => results.push(BulkItemResult { index, keyword, status: BulkItemStatus::Applied, error: None }),
+        Err(err) => {
+          failed = true;
+          results.push(BulkItemResult { index, keyword, status: BulkItemStatus::Failed, error: Some(err.to_string()) });
+        }
+      }
+    }
+
+    let committed = !(failed && mode == BulkMode::AllOrNothing);
+    if committed {
+      *shortcuts = transaction;
+    } else {
+      results.iter_mut()
+        .filter(|result| result.status == BulkItemStatus::Applied)
+        .for_each(|result| result.status = BulkItemStatus::RolledBack);
+    }
+
+    debug!("Bulk request of {} operations committed: {}", operations.len(), committed);
+    Ok(BulkReport { mode, committed, results })
+  }
+
+  async fn import(&self, shortcuts: &[Shortcut], policy: ConflictPolicy, dry_run: bool) -> Result<ImportSummary, ShortcutError> {
+    let mut stored = self.write();
+    let mut transaction: Shortcuts = stored.clone();
+
+    let mut items: Vec<ImportItem> = Vec::with_capacity(shortcuts.len());
+    for shortcut in shortcuts {
+      let item = transaction.import_one(shortcut, policy)?;
+      let failed = item.action == ImportAction::Failed;
+
+      items.push(item);
+      if failed {
+        break;
+      }
+    }
+
+    let committed = !dry_run && items.iter().all(|item| item.action != ImportAction::Failed);
+    if committed {
+      *stored = transaction;
+    }
+
+    debug!("Import of {} shortcuts committed: {}", shortcuts.len(), committed);
+    Ok(ImportSummary::new(committed, items))
+  }
+
+  async fn apply_sync(&self, plan: &SyncPlan) -> Result<(), ShortcutError> {
+    let timestamp: i64 = get_timestamp();
+    let mut shortcuts = self.write();
+    let mut transaction: Shortcuts = shortcuts.clone();
+
+    for change in &plan.changes {
+      transaction.apply_change(change, timestamp).map_err(|_| ShortcutError::FailedToSync)?;
+    }
+
+    *shortcuts = transaction;
+    Ok(())
+  }
+}
+
+/// This repository keeps the `Settings` in memory instead of a db, alongside `InMemoryShortcutRepository`.
+///
+/// Clones share the same `Settings`.
+#[derive(Clone, Default)]
+pub struct InMemorySettingsRepository {
+  settings: Arc<RwLock<Option<Settings>>>,
+}
+
+impl SettingsRepositoryTrait for InMemorySettingsRepository {
+  async fn load(&self) -> Result<Settings, SettingsError> {
+    Ok(self.settings.read().unwrap_or_else(PoisonError::into_inner).clone().unwrap_or_default())
+  }
+
+  async fn save(&self, settings: &Settings) -> Result<(), SettingsError> {
+    *self.settings.write().unwrap_or_else(PoisonError::into_inner) = Some(settings.clone());
+
+    Ok(())
+  }
+}
\ No newline at end of file
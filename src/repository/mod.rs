@@ -1 +1,6 @@
-pub mod shortcut;
\ No newline at end of file
+pub mod memory;
+pub mod settings;
+pub mod shortcut;
+pub mod sqlite;
+#[cfg(feature = "postgres")]
+pub mod postgres;
\ No newline at end of file
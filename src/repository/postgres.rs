@@ -0,0 +1,203 @@
+use crate::{
+  models::{shortcut::Shortcut, sync::SyncChange},
+  repository::{settings::SettingsStatements, shortcut::ShortcutStatements},
+};
+
+use futures::{stream::BoxStream, StreamExt};
+use sqlx::{Error, PgConnection, Pool, Postgres};
+
+/// The PostgreSQL statements, against the schema in `migrations/postgres`.
+///
+/// These are checked when the tests run rather than at compile time, so building doesn't need a PostgreSQL server.
+impl ShortcutStatements for Postgres {
+  async fn insert(connection: &mut PgConnection, shortcut: &Shortcut) -> Result<(), Error> {
+    sqlx::query(r#"INSERT INTO shortcut (created, updated, keyword, url) VALUES ($1, $2, $3, $4)"#)
+      .bind(shortcut.created)
+      .bind(shortcut.updated)
+      .bind(&shortcut.keyword)
+      .bind(&shortcut.url)
+      .execute(&mut *connection)
+      .await?;
+
+    Ok(())
+  }
+
+  async fn fetch_all(connection: &mut PgConnection) -> Result<Vec<Shortcut>, Error> {
+    sqlx::query_as(r#"SELECT * FROM shortcut;"#)
+      .fetch_all(&mut *connection)
+      .await
+  }
+
+  async fn fetch_by_keyword(connection: &mut PgConnection, keyword: &str) -> Result<Option<Shortcut>, Error> {
+    sqlx::query_as(r#"SELECT * FROM shortcut WHERE keyword = $1;"#)
+      .bind(keyword)
+      .fetch_optional(&mut *connection)
+      .await
+  }
+
+  async fn fetch_by_url(connection: &mut PgConnection, fragment: &str) -> Result<Vec<Shortcut>, Error> {
+    sqlx::query_as(r#"SELECT * FROM shortcut WHERE strpos(url, $1) > 0 ORDER BY keyword;"#)
+      .bind(fragment)
+      .fetch_all(&mut *connection)
+      .await
+  }
+
+  fn stream_all(pool: Pool<Postgres>) -> BoxStream<'static, Result<Shortcut, Error>> {
+    async_stream::stream! {
+      let mut rows = sqlx::query_as(r#"SELECT * FROM shortcut ORDER BY keyword;"#).fetch(&pool);
+
+      while let Some(row) = rows.next().await {
+        yield row;
+      }
+    }.boxed()
+  }
+
+  async fn fetch_managed(connection: &mut PgConnection, keyword: &str) -> Result<Option<bool>, Error> {
+    sqlx::query_scalar(r#"SELECT managed FROM shortcut WHERE keyword = $1"#)
+      .bind(keyword)
+      .fetch_optional(&mut *connection)
+      .await
+  }
+
+  async fn update_url(connection: &mut PgConnection, shortcut: &Shortcut) -> Result<u64, Error> {
+    let result = sqlx::query(
+        r#"UPDATE shortcut SET url = $1, updated = $2, version = version + 1 WHERE keyword = $3 AND ($4::BIGINT IS NULL OR version = $4) AND NOT managed"#
+      )
+      .bind(&shortcut.url)
+      .bind(shortcut.updated)
+      .bind(&shortcut.keyword)
+      .bind(shortcut.version)
+      .execute(&mut *connection)
+      .await?;
+
+    Ok(result.rows_affected())
+  }
+
+  async fn remove(connection: &mut PgConnection, keyword: &str, version: Option<i64>) -> Result<u64, Error> {
+    let result = sqlx::query(r#"DELETE from shortcut WHERE keyword = $1 AND ($2::BIGINT IS NULL OR version = $2) AND NOT managed"#)
+      .bind(keyword)
+      .bind(version)
+      .execute(&mut *connection)
+      .await?;
+
+    Ok(result.rows_affected())
+  }
+
+  async fn apply_change(connection: &mut PgConnection, change: &SyncChange, timestamp: i64) -> Result<(), Error> {
+    match change {
+      SyncChange::Create { keyword, url } => sqlx::query(r#"INSERT INTO shortcut (created, updated, keyword, url, managed) VALUES ($1, $1, $2, $3, TRUE)"#)
+        .bind(timestamp)
+        .bind(keyword)
+        .bind(url)
+        .execute(&mut *connection)
+        .await?,
+      SyncChange::Update { keyword, url, .. } => sqlx::query(r#"UPDATE shortcut SET url = $1, updated = $2, version = version + 1, managed = TRUE WHERE keyword = $3"#)
+        .bind(url)
+        .bind(timestamp)
+        .bind(keyword)
+        .execute(&mut *connection)
+        .await?,
+      SyncChange::Delete { keyword, .. } => sqlx::query(r#"DELETE FROM shortcut WHERE keyword = $1 AND managed"#)
+        .bind(keyword)
+        .execute(&mut *connection)
+        .await?,
+    };
+
+    Ok(())
+  }
+}
+
+impl SettingsStatements for Postgres {
+  async fn fetch_values(connection: &mut PgConnection) -> Result<Vec<(String, String)>, Error> {
+    sqlx::query_as(r#"SELECT key, value FROM settings;"#)
+      .fetch_all(&mut *connection)
+      .await
+  }
+
+  async fn upsert_value(connection: &mut PgConnection, key: &str, value: &str, updated: i64) -> Result<(), Error> {
+    sqlx::query(
+        r#"INSERT INTO settings (key, value, updated) VALUES ($1, $2, $3)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated = excluded.updated;"#
+      )
+      .bind(key)
+      .bind(value)
+      .bind(updated)
+      .execute(&mut *connection)
+      .await?;
+
+    Ok(())
+  }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+pub mod postgres_test_server {
+  use sqlx::{postgres::{PgConnectOptions, PgPoolOptions}, Executor, PgPool};
+  use std::{
+    net::TcpListener,
+    path::PathBuf,
+    process::{Command, Stdio},
+    str::FromStr,
+    sync::{atomic::{AtomicUsize, Ordering}, OnceLock},
+    thread,
+    time::Duration,
+  };
+
+  use crate::schema::POSTGRES_MIGRATOR;
+
+  /// The server every test shares, see `test_database`.
+  static SERVER: OnceLock<String> = OnceLock::new();
+  static SCHEMAS: AtomicUsize = AtomicUsize::new(0);
+
+  /// Starts a throwaway server with `initdb` and `postgres` from the `PATH`, in a temporary directory on a free port.
+  ///
+  /// The server is started from a shell waiting on our stdin, so it's stopped and removed when the tests exit however they exit.
+  fn start_server() -> String {
+    let directory: PathBuf = std::env::temp_dir().join(format!("shortcut-postgres-tests-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+
+    let initdb = Command::new("initdb")
+      .args(["--auth=trust", "--username=postgres", "--pgdata"])
+      .arg(&directory)
+      .stdout(Stdio::null())
+      .status()
+      .expect("PostgreSQL tests need initdb on the PATH, or POSTGRES_TEST_URL set");
+    assert!(initdb.success(), "initdb failed, PostgreSQL refuses to run as root so set POSTGRES_TEST_URL instead");
+
+    let port: u16 = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let server = format!(
+      "postgres -D '{0}' -p {1} -h 127.0.0.1 -k '{0}' & read _; kill $!; wait; rm -rf '{0}'",
+      directory.display(), port
+    );
+    let child = Command::new("sh").args(["-c", &server]).stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null()).spawn().unwrap();
+    // The pipe closes when the tests exit, which stops the server.
+    std::mem::forget(child);
+
+    let url = format!("postgres://postgres@127.0.0.1:{}/postgres", port);
+    for _ in 0..100 {
+      if std::net::TcpStream::connect(("127.0.0.1", port)).is_ok() {
+        thread::sleep(Duration::from_millis(200));
+        return url;
+      }
+      thread::sleep(Duration::from_millis(100));
+    }
+
+    panic!("PostgreSQL didn't start on port {}", port)
+  }
+
+  /// A migrated database of its own for each test, as a schema within the server from `POSTGRES_TEST_URL`, or one started for the tests.
+  pub async fn test_database() -> PgPool {
+    let url: &String = SERVER.get_or_init(|| std::env::var("POSTGRES_TEST_URL").unwrap_or_else(|_| start_server()));
+    let schema = format!("shortcut_test_{}_{}", std::process::id(), SCHEMAS.fetch_add(1, Ordering::Relaxed));
+
+    let admin = PgPool::connect(url).await.unwrap();
+    admin.execute(format!("DROP SCHEMA IF EXISTS {schema} CASCADE; CREATE SCHEMA {schema};").as_str()).await.unwrap();
+    admin.close().await;
+
+    let options = PgConnectOptions::from_str(url).unwrap().options([("search_path", schema.as_str())]);
+    let pool = PgPoolOptions::new().max_connections(4).connect_with(options).await.unwrap();
+    POSTGRES_MIGRATOR.run(&pool).await.unwrap();
+
+    pool
+  }
+}
\ No newline at end of file
@@ -0,0 +1,206 @@
+use crate::{error::SettingsError, models::settings::Settings, schema::Database, utils::system_util::get_timestamp};
+
+#[allow(unused_imports)]
+use mockall::{predicate::*, automock};
+use serde_json::{Map, Value};
+use sqlx::{Error, Pool};
+use tracing::{error, warn};
+
+/// This repository sits between the `settings` table and the settings service.
+///
+/// Every field of `Settings` is a row, keyed by its name with the value stored as JSON, so a setting added later
+/// simply takes its default until it's first saved.
+///
+/// We have 3 total functions:
+/// - A constructor requiring the `Database`,
+/// - A load method to read the `Settings`, falling back to the default of any missing or unreadable setting,
+/// - And a save method to write every setting within a single transaction.
+#[derive(Clone)]
+pub struct SettingsRepository {
+  pub database: Database,
+}
+
+impl SettingsRepository {
+  /// Creates a new instance of `SettingsRepository`.
+  pub fn new(database: Database) -> Self {
+    SettingsRepository { database }
+  }
+}
+
+#[cfg_attr(test, automock)]
+pub trait SettingsRepositoryTrait {
+  /// A function to read the `Settings` from the db.
+  ///
+  /// # Returns
+  /// - `Result<Settings, SettingsError>`, will be the saved `Settings` over the defaults, or `SettingsError::FailedToLoad` if the db couldn't be read.
+  async fn load(&self) -> Result<Settings, SettingsError>;
+
+  /// A function to write the `Settings` to the db.
+  ///
+  /// # Parameters
+  /// - `settings`: The `Settings` to save.
+  ///
+  /// # Returns
+  /// - `Result<(), SettingsError>`, will be `SettingsError::FailedToSave` if any setting couldn't be written.
+  async fn save(&self, settings: &Settings) -> Result<(), SettingsError>;
+}
+
+/// The statements a backend runs against the `settings` table, see `ShortcutStatements`.
+pub(crate) trait SettingsStatements: sqlx::Database {
+  /// Gets every saved setting, as its key and JSON value.
+  async fn fetch_values(connection: &mut Self::Connection) -> Result<Vec<(String, String)>, Error>;
+
+  /// Inserts the setting, or replaces its value if it's already saved.
+  async fn upsert_value(connection: &mut Self::Connection, key: &str, value: &str, updated: i64) -> Result<(), Error>;
+}
+
+impl<DB: SettingsStatements> SettingsRepositoryTrait for Pool<DB> {
+  async fn load(&self) -> Result<Settings, SettingsError> {
+    let rows: Vec<(String, String)> = async { DB::fetch_values(&mut *self.acquire().await?).await }
+      .await
+      .map_err(|err: Error| {
+        error!("Failed to load settings from database: {}", err);
+        SettingsError::FailedToLoad
+      })?;
+
+    let mut values: Map<String, Value> = match serde_json::to_value(Settings::default()) {
+      Ok(Value::Object(values)) => values,
+      _ => return Err(SettingsError::FailedToLoad),
+    };
+
+    // Each setting is applied on its own, so one unreadable value only resets that setting.
+    for (key, value) in rows {
+      let mut candidate: Map<String, Value> = values.clone();
+
+      match serde_json::from_str::<Value>(&value) {
+        Ok(value) if values.contains_key(&key) => { candidate.insert(key.clone(), value); },
+        _ => {
+          warn!("Ignoring unknown or unreadable setting ({}).", key);
+          continue;
+        },
+      }
+
+      if serde_json::from_value::<Settings>(Value::Object(candidate.clone())).is_ok() {
+        values = candidate;
+      } else {
+        warn!("Ignoring invalid value for setting ({}).", key);
+      }
+    }
+
+    serde_json::from_value(Value::Object(values)).map_err(|err| {
+      error!("Failed to read settings: {}", err);
+      SettingsError::FailedToLoad
+    })
+  }
+
+  async fn save(&self, settings: &Settings) -> Result<(), SettingsError> {
+    let failed = |err: &dyn std::fmt::Display| {
+      error!("Failed to save settings to database: {}", err);
+      SettingsError::FailedToSave
+    };
+
+    let values: Map<String, Value> = match serde_json::to_value(settings) {
+      Ok(Value::Object(values)) => values,
+      Ok(_) => return Err(SettingsError::FailedToSave),
+      Err(err) => return Err(failed(&err)),
+    };
+    let updated: i64 = get_timestamp();
+
+    let mut transaction = self.begin().await.map_err(|err| failed(&err))?;
+
+    for (key, value) in values {
+      DB::upsert_value(&mut transaction, &key, &value.to_string(), updated).await.map_err(|err| failed(&err))?;
+    }
+
+    transaction.commit().await.map_err(|err| failed(&err))
+  }
+}
+
+impl SettingsRepositoryTrait for SettingsRepository {
+  async fn load(&self) -> Result<Settings, SettingsError> {
+    dispatch!(&self.database, pool => pool.load().await, memory => memory.settings.load().await)
+  }
+
+  async fn save(&self, settings: &Settings) -> Result<(), SettingsError> {
+    dispatch!(&self.database, pool => pool.save(settings).await, memory => memory.settings.save(settings).await)
+  }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod settings_repository_tests {
+  use sqlx::{Executor, SqlitePool};
+
+  use crate::{repository::{memory::MemoryStore, settings::SettingsRepository}, schema::Database};
+
+  /// Runs the statement against an SQL backend, for test data the repository can't write itself.
+  async fn execute(database: &Database, query: &str) {
+    dispatch!(database, pool => { pool.execute(query).await.unwrap(); }, _ => {});
+  }
+
+  /// The repository on every backend built in, see `shortcut_repository_tests::setup`.
+  async fn setup() -> Vec<SettingsRepository> {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+
+    // Create temporary table within memory
+    pool.execute("CREATE TABLE IF NOT EXISTS settings (
+        key       TEXT  NOT NULL  PRIMARY KEY,
+        value     TEXT  NOT NULL,
+        updated   INTEGER NOT NULL
+      );").await.unwrap();
+
+    #[allow(unused_mut)]
+    let mut databases: Vec<Database> = vec![Database::Sqlite(pool), Database::Memory(MemoryStore::default())];
+    #[cfg(feature = "postgres")]
+    databases.push(Database::Postgres(crate::repository::postgres::postgres_test_server::test_database().await));
+
+    databases.into_iter().map(SettingsRepository::new).collect()
+  }
+
+  mod load_tests {
+    use crate::{models::settings::{Fallback, Settings}, repository::settings::SettingsRepositoryTrait};
+
+    use crate::schema::Database;
+
+    use super::{execute, setup};
+
+    #[tokio::test]
+    async fn returns_defaults_when_nothing_saved() {
+      for settings_repository in setup().await {
+        let result = settings_repository.load().await.unwrap();
+
+        assert_eq!(result, Settings::default());
+      }
+    }
+
+    #[tokio::test]
+    async fn returns_saved_settings() {
+      for settings_repository in setup().await {
+        let settings = Settings { fallback: Fallback::Url, redirect_status: 308, analytics: true, ..Settings::default() };
+
+        settings_repository.save(&settings).await.unwrap();
+        settings_repository.save(&settings).await.unwrap();
+        let result = settings_repository.load().await.unwrap();
+
+        assert_eq!(result, settings);
+      }
+    }
+
+    #[tokio::test]
+    async fn returns_default_for_unreadable_setting() {
+      // Only the SQL backends can hold a value that doesn't deserialize
+      for settings_repository in setup().await.into_iter().filter(|repository| !matches!(repository.database, Database::Memory(_))) {
+        execute(&settings_repository.database, "
+          INSERT INTO settings (key, value, updated) VALUES
+          ('redirect_status', '\"soon\"', 1),
+          ('analytics', 'true', 1),
+          ('removed', '1', 1)
+        ").await;
+
+        let result = settings_repository.load().await.unwrap();
+
+        assert_eq!(result, Settings { analytics: true, ..Settings::default() });
+      }
+    }
+  }
+}
\ No newline at end of file
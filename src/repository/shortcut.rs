@@ -1,63 +1,106 @@
-use crate::{error::ShortcutError, models::shortcut::Shortcut};
+use crate::{
+  error::ShortcutError, 
+  models::{
+    bulk::{BulkItemResult, BulkItemStatus, BulkMode, BulkOperation, BulkReport}, 
+    shortcut::Shortcut,
+    sync::{SyncChange, SyncPlan},
+    transfer::{ConflictPolicy, ImportAction, ImportItem, ImportSummary},
+  },
+  schema::Database,
+  utils::system_util::get_timestamp,
+};
 
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
 #[allow(unused_imports)]
 use mockall::{predicate::*, automock};
 use rust_fuzzy_search::fuzzy_compare;
-use sqlx::{Error, Pool, Sqlite};
+use sqlx::{pool::PoolConnection, Acquire, Connection, Error, Pool};
 use tracing::{debug, error};
 
-/// This repository sits between the SQLite db and the service interactions.
+/// This repository sits between the db and the service interactions, on whichever backend the `Database` is.
 /// 
-/// We have 5 total functions:
-/// - A constructor requiring a SQLite connection pool (`Pool<Sqlite>`),
+/// We have 11 total functions:
+/// - A constructor requiring the `Database`,
 /// - A fuzzy seach funtion to get close results, 
 /// - A get method for exact `Shortcut` match by keyword,
+/// - A find by url method to get every `Shortcut` whose url contains a fragment,
+/// - A stream all method to read every `Shortcut` one row at a time,
 /// - A create method to insert a `Shortcut` into the db, 
-/// - And a update method to change the url of a `Shortcut`.
+/// - A update method to change the url of a `Shortcut`,
+/// - A delete method to remove a `Shortcut`,
+/// - A bulk method to run many creates, updates and deletes within a single transaction,
+/// - An import method to create many `Shortcut`'s within a single transaction, resolving existing keywords with a `ConflictPolicy`,
+/// - And an apply sync method to bring the database in line with the declarative shortcuts file.
+/// 
+/// Each method is written once over the `Pool` of any backend implementing `ShortcutStatements`.
 #[derive(Clone)]
 pub struct ShortcutRepository {
-  pub database: Pool<Sqlite>,
+  pub database: Database,
 }
 
-#[cfg_attr(test, automock)]
-pub trait ShortcutRepositoryTrait {
+impl ShortcutRepository {
   /// Creates a new instance of `ShortcutRepository`.
-  fn new(database: Pool<Sqlite>) -> Self;
+  pub fn new(database: Database) -> Self {
+    ShortcutRepository { database }
+  }
+}
 
+#[cfg_attr(test, automock)]
+pub trait ShortcutRepositoryTrait {
   /// A function to get the closest results for the given input using `rust_fuzzy_search`. 
   /// 
   /// This will return a `Vec` of `Shortcut` objects, sorted by the closest match of keyword to input.
   /// 
-  /// It will remove all the object that did not match the search term at all.
+  /// It will remove all the object that did not score above `min_score`, so `0.0` only removes those that did not match at all.
   /// 
-  /// ## Parameters
+  /// # Parameters
   /// - `search`: The unique `String` keyword to search.
+  /// - `min_score`: The score (from 0 to 1) a keyword has to beat to be a match.
   /// 
-  /// ## Returns
+  /// # Returns
   /// - `Result<Vec<Shortcut>, ShortcutError>`, will a `Vec<Shortcut>` sorted by how similar to the search term if there are matches, `ShortcutError::NotFound`
   ///   if there are no matching `Shortcut`'s, or `ShortcutError::FailedToSearch` if error occurs while retrieving.
-  async fn fuzzy_search(&self, search: &str) -> Result<Vec<Shortcut>, ShortcutError>;
+  async fn fuzzy_search(&self, search: &str, min_score: f32) -> Result<Vec<Shortcut>, ShortcutError>;
 
   /// A function to get the `Shortcut` object given an inputed keyword.
   /// 
-  /// ## Parameters
+  /// # Parameters
   /// - `keyword`: The unique `String` keyword for teh Shortcut required.
   /// 
-  /// ## Returns
+  /// # Returns
   /// - `Result<Shortcut, ShortcutError>`, will be the requested `Shortcut` if exists, `ShortcutError::NotFound` if the `Shortcut` doesn't exists,
   ///   or `ShortcutError::FailedToGet` if error occurs while retrieving.
   async fn get(&self, keyword: &str) -> Result<Shortcut, ShortcutError>;
 
+  /// A function to get every `Shortcut` whose url contains the given fragment, ordered by keyword.
+  /// 
+  /// # Parameters
+  /// - `fragment`: The `String` to look for within each url.
+  /// 
+  /// # Returns
+  /// - `Result<Vec<Shortcut>, ShortcutError>`, will be the matching `Shortcut`'s (empty if there are none),
+  ///   or `ShortcutError::FailedToSearch` if error occurs while retrieving.
+  async fn find_by_url(&self, fragment: &str) -> Result<Vec<Shortcut>, ShortcutError>;
+
+  /// A function to read every `Shortcut`, ordered by keyword, without loading them all into memory at once.
+  /// 
+  /// The stream owns its own handle to the database, so it can outlive the repository e.g. as a response body.
+  /// 
+  /// # Returns
+  /// - `BoxStream<Result<Shortcut, ShortcutError>>`, will yield each `Shortcut` as it is read, 
+  ///   or `ShortcutError::FailedToExport` if an error occurs while reading.
+  fn stream_all(&self) -> BoxStream<'static, Result<Shortcut, ShortcutError>>;
+
   /// A function to create a `Shortcut` given an input `Shortcut` object.
   /// 
   /// This will take the `created`, `updated`, `url` and `keyword` fields from the `Shortcut` object and insert them into the database.
   /// 
   /// You can expect this to return `true` if successfully created.
   /// 
-  /// ## Parameters
+  /// # Parameters
   /// - `shortcut`: The `&Shortcut` to create.
   /// 
-  /// ## Returns
+  /// # Returns
   /// - `Result<bool, ShortcutError>`, will be true if successful, `ShortcutError::UniqueConstraintError` if the `Shortcut` already exists,
   ///   or `ShortcutError::FailedToCreate` if error occurs while creating.
   async fn create(&self, shortcut: &Shortcut) -> Result<bool, ShortcutError>;
@@ -66,47 +109,288 @@ pub trait ShortcutRepositoryTrait {
   /// 
   /// This will take the `created`, and `url` fields from the `Shortcut` object and update the `Shortcut` with the same `keyword`.
   /// 
+  /// This is a compare-and-swap, if the `version` is given the `Shortcut` is only updated while its version still matches,
+  /// and every update increments the stored version.
+  /// 
   /// You can expect this to return `true` if successfully updated.
   /// 
-  /// ## Parameters
+  /// # Parameters
   /// - `shortcut`: The `&Shortcut` to update.
   /// 
-  /// ## Returns
-  /// - `Result<bool, ShortcutError>`, will be true if successful, false if nothing is updated, and `ShortcutError::FailedToUpdare`
-  ///   if an error occurs while updating.
+  /// # Returns
+  /// - `Result<bool, ShortcutError>`, will be true if successful, false if nothing is updated, `ShortcutError::VersionConflict`
+  ///   if the `Shortcut` has a different version, and `ShortcutError::FailedToUpdare` if an error occurs while updating.
   async fn update(&self, shortcut: &Shortcut) -> Result<bool, ShortcutError>;
 
   /// A function to delete a `Shortcut` given an inputed keyword.
   /// 
   /// This will take the `keyword`, from a `Shortcut` object and delete the `Shortcut` with the same `keyword`.
   /// 
+  /// Like `update`, if the `version` is given the `Shortcut` is only deleted while its version still matches.
+  /// 
   /// You can expect this to return `true` if successfully deletes.
   /// 
-  /// ## Parameters
-  /// - `shortcut`: The `&Shortcut` to update.
+  /// # Parameters
+  /// - `keyword`: The keyword of the `Shortcut` to delete.
+  /// - `version`: The version the `Shortcut` is expected to have, if any.
+  /// 
+  /// # Returns
+  /// - `Result<bool, ShortcutError>`, will be true if successful, false if nothing is deleted, `ShortcutError::VersionConflict`
+  ///   if the `Shortcut` has a different version, and `ShortcutError::FailedToUpdare` if an error occurs while updating.
+  async fn delete(&self, keyword: &str, version: Option<i64>) -> Result<bool, ShortcutError>;
+
+  /// A function to apply many create, update and delete operations within a single transaction.
+  /// 
+  /// An update or delete of a keyword that does not exist counts as a failed operation, as does one without the `version` it expects.
+  /// 
+  /// With `BulkMode::AllOrNothing` the first failure rolls back the whole transaction and the remaining operations are skipped,
+  /// with `BulkMode::BestEffort` each operation runs within its own savepoint so only the failing operations are rolled back.
   /// 
-  /// ## Returns
-  /// - `Result<bool, ShortcutError>`, will be true if successful, false if nothing is deleted, and `ShortcutError::FailedToUpdare`
-  ///   if an error occurs while updating.
-  async fn delete(&self, keyword: &str) -> Result<bool, ShortcutError>;
+  /// # Parameters
+  /// - `operations`: The `&[BulkOperation]` to apply, in order.
+  /// - `mode`: The `BulkMode` deciding how failures are handled.
+  /// 
+  /// # Returns
+  /// - `Result<BulkReport, ShortcutError>`, will be the `BulkReport` with the outcome of each operation, 
+  ///   or `ShortcutError::FailedToBulk` if the transaction itself could not be started or committed.
+  async fn bulk(&self, operations: &[BulkOperation], mode: BulkMode) -> Result<BulkReport, ShortcutError>;
+
+  /// A function to import many `Shortcut`'s within a single transaction.
+  /// 
+  /// Each `Shortcut` is created as in `create`, and when that fails with `ShortcutError::UniqueConstraintError` the existing keyword 
+  /// is resolved with the given `ConflictPolicy`. With `ConflictPolicy::Fail` the first conflict rolls back the whole import.
+  /// 
+  /// A dry run applies the import the same way and then rolls it back, so its summary is an exact preview.
+  /// 
+  /// # Parameters
+  /// - `shortcuts`: The `&[Shortcut]` to import, in order.
+  /// - `policy`: The `ConflictPolicy` for keywords that already exist with a different url.
+  /// - `dry_run`: Whether to roll back instead of committing.
+  /// 
+  /// # Returns
+  /// - `Result<ImportSummary, ShortcutError>`, will be the `ImportSummary` with the outcome of each `Shortcut`, 
+  ///   or `ShortcutError::FailedToImport` if an error occurs while importing.
+  async fn import(&self, shortcuts: &[Shortcut], policy: ConflictPolicy, dry_run: bool) -> Result<ImportSummary, ShortcutError>;
+
+  /// A function to apply a `SyncPlan` from the shortcuts file within a single transaction.
+  /// 
+  /// Created and updated `Shortcut`'s are marked as managed, and only managed `Shortcut`'s are deleted.
+  /// 
+  /// # Parameters
+  /// - `plan`: The `SyncPlan` to apply.
+  /// 
+  /// # Returns
+  /// - `Result<(), ShortcutError>`, will be `ShortcutError::FailedToSync` if any change fails, in which case none are applied.
+  async fn apply_sync(&self, plan: &SyncPlan) -> Result<(), ShortcutError>;
 }
 
-impl ShortcutRepositoryTrait for ShortcutRepository {
-  fn new(database: Pool<Sqlite>) -> Self {
-    ShortcutRepository { database }
+/// The statements a backend runs against the `shortcut` table, everything else about the repository is shared between backends.
+/// 
+/// Errors are returned as they are, so they're handled the same way whichever backend raised them.
+pub(crate) trait ShortcutStatements: sqlx::Database {
+  /// Inserts the `created`, `updated`, `keyword` and `url` of the `Shortcut`.
+  async fn insert(connection: &mut Self::Connection, shortcut: &Shortcut) -> Result<(), Error>;
+
+  /// Gets every `Shortcut`, in no particular order.
+  async fn fetch_all(connection: &mut Self::Connection) -> Result<Vec<Shortcut>, Error>;
+
+  /// Gets the `Shortcut` with the given keyword, if there is one.
+  async fn fetch_by_keyword(connection: &mut Self::Connection, keyword: &str) -> Result<Option<Shortcut>, Error>;
+
+  /// Gets every `Shortcut` whose url contains the fragment, ordered by keyword.
+  async fn fetch_by_url(connection: &mut Self::Connection, fragment: &str) -> Result<Vec<Shortcut>, Error>;
+
+  /// Reads every `Shortcut` ordered by keyword, holding its own handle to the pool.
+  fn stream_all(pool: Pool<Self>) -> BoxStream<'static, Result<Shortcut, Error>>;
+
+  /// Gets whether the `Shortcut` with the given keyword is managed, `None` if there isn't one.
+  async fn fetch_managed(connection: &mut Self::Connection, keyword: &str) -> Result<Option<bool>, Error>;
+
+  /// Updates the url of the unmanaged `Shortcut` with the same keyword (and version, if given), incrementing its version.
+  /// 
+  /// Returns the number of rows updated.
+  async fn update_url(connection: &mut Self::Connection, shortcut: &Shortcut) -> Result<u64, Error>;
+
+  /// Deletes the unmanaged `Shortcut` with the given keyword (and version, if given).
+  /// 
+  /// Returns the number of rows deleted.
+  async fn remove(connection: &mut Self::Connection, keyword: &str, version: Option<i64>) -> Result<u64, Error>;
+
+  /// Applies a single `SyncChange`, the only writes allowed to touch managed `Shortcut`'s.
+  async fn apply_change(connection: &mut Self::Connection, change: &SyncChange, timestamp: i64) -> Result<(), Error>;
+}
+
+/// Acquires a connection from the pool, logging and mapping a failure to the given error.
+async fn acquire<DB: ShortcutStatements>(pool: &Pool<DB>, on_error: ShortcutError) -> Result<PoolConnection<DB>, ShortcutError> {
+  pool.acquire().await.map_err(|err| {
+    error!("Failed to acquire database connection: {}", err);
+    on_error
+  })
+}
+
+/// Inserts the `Shortcut` using the given connection, so it can be shared between the pool and transactions.
+/// 
+/// The insert runs within a savepoint, as some backends (e.g. PostgreSQL) abort the whole transaction when it fails.
+async fn insert<DB: ShortcutStatements>(connection: &mut DB::Connection, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
+  let result: Result<(), Error> = async {
+    let mut savepoint = connection.begin().await?;
+
+    match DB::insert(&mut savepoint, shortcut).await {
+      Ok(()) => savepoint.commit().await,
+      Err(err) => {
+        savepoint.rollback().await?;
+        Err(err)
+      }
+    }
+  }.await;
+
+  match result {
+    Ok(()) => Ok(true),
+    Err(Error::Database(db_err)) if db_err.is_unique_violation() => Err(ShortcutError::UniqueConstraintError),
+    Err(err) => {
+      error!("Failed to add shortcut ({}) to database: {}", shortcut.keyword, err);
+      Err(ShortcutError::FailedToCreate)
+    }
+  }
+}
+
+/// Works out why a compare-and-swap changed no rows, `Ok(false)` if the keyword doesn't exist,
+/// `ShortcutError::ReadOnly` if it's managed by the shortcuts file or `ShortcutError::VersionConflict` if it exists with a different version.
+async fn check_unchanged<DB: ShortcutStatements>(connection: &mut DB::Connection, keyword: &str, version: Option<i64>, on_error: ShortcutError) -> Result<bool, ShortcutError> {
+  match DB::fetch_managed(connection, keyword).await {
+    Ok(Some(true)) => Err(ShortcutError::ReadOnly),
+    Ok(Some(false)) if version.is_some() => Err(ShortcutError::VersionConflict),
+    Ok(_) => Ok(false),
+    Err(err) => {
+      error!("Failed to check version of shortcut ({}) in database: {}", keyword, err);
+      Err(on_error)
+    }
   }
+}
 
-  async fn fuzzy_search(&self, search: &str) -> Result<Vec<Shortcut>, ShortcutError> {
-    let result = sqlx::query_as!(Shortcut, r#"SELECT * FROM shortcut;"#)
-      .fetch_all(&self.database)
-      .await;
+/// Updates the url of the unmanaged `Shortcut` with the same keyword (and version, if given) using the given connection.
+async fn update_url<DB: ShortcutStatements>(connection: &mut DB::Connection, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
+  match DB::update_url(connection, shortcut).await {
+    Ok(1) => Ok(true),
+    Ok(0) => check_unchanged::<DB>(connection, &shortcut.keyword, shortcut.version, ShortcutError::FailedToUpdate).await,
+    Ok(rows) => {
+      error!("Failed to update shortcut ({}) in database: updated {} rows", shortcut.keyword, rows);
+
+      Err(ShortcutError::FailedToUpdate)
+    },
+    Err(err) => {
+      error!("Failed to update shortcut ({}) in database: {}", shortcut.keyword, err);
+      Err(ShortcutError::FailedToUpdate)
+    }
+  }
+}
+
+/// Deletes the unmanaged `Shortcut` with the given keyword (and version, if given) using the given connection.
+async fn remove<DB: ShortcutStatements>(connection: &mut DB::Connection, keyword: &str, version: Option<i64>) -> Result<bool, ShortcutError> {
+  match DB::remove(connection, keyword, version).await {
+    Ok(1) => Ok(true),
+    Ok(0) => check_unchanged::<DB>(connection, keyword, version, ShortcutError::FailedToDelete).await,
+    Ok(rows) => {
+      error!("Failed to delete shortcut ({}) in database: updated {} rows", keyword, rows);
+
+      Err(ShortcutError::FailedToDelete)
+    },
+    Err(err) => {
+      error!("Failed to update shortcut ({}) in database: {}", keyword, err);
+      Err(ShortcutError::FailedToDelete)
+    }
+  }
+}
+
+/// Imports a single `Shortcut` using the given connection, resolving an existing keyword with the `ConflictPolicy`.
+async fn import_one<DB: ShortcutStatements>(connection: &mut DB::Connection, shortcut: &Shortcut, policy: ConflictPolicy) -> Result<ImportItem, ShortcutError> {
+  let mut item = ImportItem { 
+    keyword: shortcut.keyword.clone(), 
+    imported_as: shortcut.keyword.clone(), 
+    action: ImportAction::Created, 
+    old_url: None, 
+    url: shortcut.url.clone(),
+  };
+
+  match insert::<DB>(connection, shortcut).await {
+    Ok(_) => return Ok(item),
+    Err(ShortcutError::UniqueConstraintError) => {},
+    Err(_) => return Err(ShortcutError::FailedToImport),
+  }
+
+  let existing = DB::fetch_by_keyword(connection, &shortcut.keyword).await
+    .map_err(|err| {
+      error!("Failed to get existing shortcut ({}) for import: {}", shortcut.keyword, err);
+      ShortcutError::FailedToImport
+    })?
+    .ok_or(ShortcutError::FailedToImport)?;
+  item.old_url = Some(existing.url.clone());
+
+  item.action = if existing.url == shortcut.url {
+    ImportAction::Unchanged
+  } else {
+    match policy {
+      ConflictPolicy::Skip => ImportAction::Skipped,
+      ConflictPolicy::Fail => ImportAction::Failed,
+      ConflictPolicy::Overwrite => {
+        let overwrite = Shortcut { version: None, ..shortcut.clone() };
+
+        match update_url::<DB>(connection, &overwrite).await {
+          Ok(_) => ImportAction::Updated,
+          Err(ShortcutError::ReadOnly) => ImportAction::Skipped,
+          Err(_) => return Err(ShortcutError::FailedToImport),
+        }
+      },
+      ConflictPolicy::Rename => {
+        item.old_url = None;
+
+        let mut suffix = 2;
+        loop {
+          let renamed = Shortcut { keyword: format!("{}-{}", shortcut.keyword, suffix), ..shortcut.clone() };
+
+          match insert::<DB>(connection, &renamed).await {
+            Ok(_) => {
+              item.imported_as = renamed.keyword;
+              break ImportAction::Renamed;
+            },
+            Err(ShortcutError::UniqueConstraintError) => suffix += 1,
+            Err(_) => return Err(ShortcutError::FailedToImport),
+          }
+        }
+      },
+    }
+  };
+
+  Ok(item)
+}
+
+/// Applies a single `BulkOperation` using the given connection, treating a missing keyword as `ShortcutError::NotFound`.
+async fn apply<DB: ShortcutStatements>(connection: &mut DB::Connection, operation: &BulkOperation) -> Result<(), ShortcutError> {
+  let applied = match operation {
+    BulkOperation::Update { version: None, .. } | BulkOperation::Delete { version: None, .. } => return Err(ShortcutError::VersionRequired),
+    BulkOperation::Create { keyword, url } => insert::<DB>(connection, &Shortcut::new(keyword.clone(), url.clone())).await?,
+    BulkOperation::Update { keyword, url, version } => {
+      let shortcut = Shortcut { version: *version, ..Shortcut::new(keyword.clone(), url.clone()) };
+
+      update_url::<DB>(connection, &shortcut).await?
+    },
+    BulkOperation::Delete { keyword, version } => remove::<DB>(connection, keyword, *version).await?,
+  };
+
+  if applied { Ok(()) } else { Err(ShortcutError::NotFound) }
+}
+
+impl<DB: ShortcutStatements> ShortcutRepositoryTrait for Pool<DB> {
+  async fn fuzzy_search(&self, search: &str, min_score: f32) -> Result<Vec<Shortcut>, ShortcutError> {
+    let mut connection = acquire(self, ShortcutError::FailedToSearch).await?;
+    let result = DB::fetch_all(&mut connection).await;
 
     match result {
       Ok(shortcuts) => {
         let mut matches:Vec<(f32, Shortcut)> = shortcuts.into_iter()
           .filter_map(|shortcut| {
             let score = fuzzy_compare(search, &shortcut.keyword);
-            if score > 0.0 {
+            if score > min_score {
               Some((score, shortcut))  // Keep only matching results
             } else {
               None  // Skip non-matching results
@@ -133,12 +417,12 @@ impl ShortcutRepositoryTrait for ShortcutRepository {
   }
 
   async fn get(&self, keyword: &str) -> Result<Shortcut, ShortcutError> {
-    let result = sqlx::query_as!(Shortcut, r#"SELECT * FROM shortcut WHERE keyword = ?1;"#, keyword)
-      .fetch_one(&self.database).await;
+    let mut connection = acquire(self, ShortcutError::FailedToGet).await?;
+    let result = DB::fetch_by_keyword(&mut connection, keyword).await;
 
     match result {
-      Ok(result) => Ok(result),
-      Err(Error::RowNotFound) => Err(ShortcutError::NotFound),
+      Ok(Some(result)) => Ok(result),
+      Ok(None) => Err(ShortcutError::NotFound),
       Err(err) => {
         error!("Failed to get shortcut for ({}) from database: {}", keyword, err);
         Err(ShortcutError::FailedToGet)
@@ -146,81 +430,208 @@ impl ShortcutRepositoryTrait for ShortcutRepository {
     }
   }
 
-  async fn create(&self, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
-    let result = sqlx::query!(
-        r#"INSERT INTO shortcut (created, updated, keyword, url) VALUES (?1, ?2, ?3, ?4)"#, 
-        shortcut.created, 
-        shortcut.updated,
-        shortcut.keyword,
-        shortcut.url
-      )
-      .execute(&self.database)
-      .await;
+  async fn find_by_url(&self, fragment: &str) -> Result<Vec<Shortcut>, ShortcutError> {
+    let mut connection = acquire(self, ShortcutError::FailedToSearch).await?;
+    let result = DB::fetch_by_url(&mut connection, fragment).await;
 
     match result {
-      Ok(_) => Ok(true),
-      Err(Error::Database(db_err)) if db_err.message().contains("UNIQUE constraint failed") => Err(ShortcutError::UniqueConstraintError),
+      Ok(shortcuts) => Ok(shortcuts),
       Err(err) => {
-        error!("Failed to add shortcut ({}) to database: {}", shortcut.keyword, err);
-        Err(ShortcutError::FailedToCreate)
+        error!("Failed to find shortcuts with url containing ({}) from database: {}", fragment, err);
+        Err(ShortcutError::FailedToSearch)
       }
     }
   }
 
+  fn stream_all(&self) -> BoxStream<'static, Result<Shortcut, ShortcutError>> {
+    DB::stream_all(self.clone())
+      .map_err(|err| {
+        error!("Failed to stream shortcuts from database: {}", err);
+        ShortcutError::FailedToExport
+      })
+      .boxed()
+  }
+
+  async fn create(&self, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
+    let mut connection = acquire(self, ShortcutError::FailedToCreate).await?;
+
+    insert::<DB>(&mut connection, shortcut).await
+  }
+
   async fn update(&self, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
-    let result = sqlx::query!(
-      r#"UPDATE shortcut SET url = ?1, updated = ?2 WHERE keyword = ?3"#, 
-      shortcut.url,
-      shortcut.updated,
-      shortcut.keyword
-    )
-    .execute(&self.database)
-    .await;
+    let mut connection = acquire(self, ShortcutError::FailedToUpdate).await?;
 
-    match result {
-      Ok(res) => 
-        if res.rows_affected() == 1 {Ok(true)} 
-        else if res.rows_affected() == 0 {Ok(false)}
-        else {
-          error!("Failed to update shortcut ({}) in database: updated {} rows", shortcut.keyword, res.rows_affected());
-
-          Err(ShortcutError::FailedToUpdate)
-        },
-      Err(err) => {
-        error!("Failed to update shortcut ({}) in database: {}", shortcut.keyword, err);
-        Err(ShortcutError::FailedToUpdate)
+    update_url::<DB>(&mut connection, shortcut).await
+  }
+
+  async fn delete(&self, keyword: &str, version: Option<i64>) -> Result<bool, ShortcutError> {
+    let mut connection = acquire(self, ShortcutError::FailedToDelete).await?;
+
+    remove::<DB>(&mut connection, keyword, version).await
+  }
+
+  async fn bulk(&self, operations: &[BulkOperation], mode: BulkMode) -> Result<BulkReport, ShortcutError> {
+    let mut transaction = self.begin().await.map_err(|err| {
+      error!("Failed to start bulk transaction: {}", err);
+      ShortcutError::FailedToBulk
+    })?;
+
+    let mut results: Vec<BulkItemResult> = Vec::with_capacity(operations.len());
+    let mut failed = false;
+
+    for (index, operation) in operations.iter().enumerate() {
+      let keyword = operation.keyword().to_owned();
+
+      if failed && mode == BulkMode::AllOrNothing {
+        results.push(BulkItemResult { index, keyword, status: BulkItemStatus::Skipped, error: None });
+        continue;
+      }
+
+      let outcome = match mode {
+        BulkMode::AllOrNothing => apply::<DB>(&mut transaction, operation).await,
+        BulkMode::BestEffort => {
+          let mut savepoint = transaction.begin().await.map_err(|err| {
+            error!("Failed to create savepoint for bulk operation ({}): {}", keyword, err);
+            ShortcutError::FailedToBulk
+          })?;
+          let outcome = apply::<DB>(&mut savepoint, operation).await;
+          let closed = if outcome.is_ok() { savepoint.commit().await } else { savepoint.rollback().await };
+
+          closed.map_err(|err| {
+            error!("Failed to release savepoint for bulk operation ({}): {}", keyword, err);
+            ShortcutError::FailedToBulk
+          })?;
+          outcome
+        }
+      };
+
+      match outcome {
+        Ok(()) => results.push(BulkItemResult { index, keyword, status: BulkItemStatus::Applied, error: None }),
+        Err(err) => {
+          failed = true;
+          results.push(BulkItemResult { index, keyword, status: BulkItemStatus::Failed, error: Some(err.to_string()) });
+        }
       }
     }
+
+    let committed = !(failed && mode == BulkMode::AllOrNothing);
+    let closed = if committed { transaction.commit().await } else { transaction.rollback().await };
+
+    if let Err(err) = closed {
+      error!("Failed to close bulk transaction: {}", err);
+      return Err(ShortcutError::FailedToBulk);
+    }
+
+    if !committed {
+      results.iter_mut()
+        .filter(|result| result.status == BulkItemStatus::Applied)
+        .for_each(|result| result.status = BulkItemStatus::RolledBack);
+    }
+
+    debug!("Bulk request of {} operations committed: {}", operations.len(), committed);
+    Ok(BulkReport { mode, committed, results })
   }
 
-  async fn delete(&self, keyword: &str) -> Result<bool, ShortcutError> {
-    let result = sqlx::query!(r#"DELETE from shortcut WHERE keyword = ?1"#, keyword)
-      .execute(&self.database)
-      .await;
+  async fn import(&self, shortcuts: &[Shortcut], policy: ConflictPolicy, dry_run: bool) -> Result<ImportSummary, ShortcutError> {
+    let mut transaction = self.begin().await.map_err(|err| {
+      error!("Failed to start import transaction: {}", err);
+      ShortcutError::FailedToImport
+    })?;
 
-    match result {
-      Ok(res) => 
-        if res.rows_affected() == 1 {Ok(true)} 
-        else if res.rows_affected() == 0 {Ok(false)}
-        else {
-          error!("Failed to delete shortcut ({}) in database: updated {} rows", keyword, res.rows_affected());
-
-          Err(ShortcutError::FailedToDelete)
-        },
-      Err(err) => {
-        error!("Failed to update shortcut ({}) in database: {}", keyword, err);
-        Err(ShortcutError::FailedToDelete)
+    let mut items: Vec<ImportItem> = Vec::with_capacity(shortcuts.len());
+    for shortcut in shortcuts {
+      let item = import_one::<DB>(&mut transaction, shortcut, policy).await?;
+      let failed = item.action == ImportAction::Failed;
+
+      items.push(item);
+      if failed {
+        break;
       }
     }
+
+    let committed = !dry_run && items.iter().all(|item| item.action != ImportAction::Failed);
+    let closed = if committed { transaction.commit().await } else { transaction.rollback().await };
+
+    if let Err(err) = closed {
+      error!("Failed to close import transaction: {}", err);
+      return Err(ShortcutError::FailedToImport);
+    }
+
+    debug!("Import of {} shortcuts committed: {}", shortcuts.len(), committed);
+    Ok(ImportSummary::new(committed, items))
+  }
+
+  async fn apply_sync(&self, plan: &SyncPlan) -> Result<(), ShortcutError> {
+    let timestamp: i64 = get_timestamp();
+    let result: Result<(), Error> = async {
+      let mut transaction = self.begin().await?;
+
+      for change in &plan.changes {
+        DB::apply_change(&mut transaction, change, timestamp).await?;
+      }
+
+      transaction.commit().await
+    }.await;
+
+    result.map_err(|err| {
+      error!("Failed to apply sync of {} changes: {}", plan.changes.len(), err);
+      ShortcutError::FailedToSync
+    })
+  }
+}
+
+impl ShortcutRepositoryTrait for ShortcutRepository {
+  async fn fuzzy_search(&self, search: &str, min_score: f32) -> Result<Vec<Shortcut>, ShortcutError> {
+    dispatch!(&self.database, pool => pool.fuzzy_search(search, min_score).await, memory => memory.shortcuts.fuzzy_search(search, min_score).await)
+  }
+
+  async fn get(&self, keyword: &str) -> Result<Shortcut, ShortcutError> {
+    dispatch!(&self.database, pool => pool.get(keyword).await, memory => memory.shortcuts.get(keyword).await)
+  }
+
+  async fn find_by_url(&self, fragment: &str) -> Result<Vec<Shortcut>, ShortcutError> {
+    dispatch!(&self.database, pool => pool.find_by_url(fragment).await, memory => memory.shortcuts.find_by_url(fragment).await)
+  }
+
+  fn stream_all(&self) -> BoxStream<'static, Result<Shortcut, ShortcutError>> {
+    dispatch!(&self.database, pool => pool.stream_all(), memory => memory.shortcuts.stream_all())
+  }
+
+  async fn create(&self, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
+    dispatch!(&self.database, pool => pool.create(shortcut).await, memory => memory.shortcuts.create(shortcut).await)
+  }
+
+  async fn update(&self, shortcut: &Shortcut) -> Result<bool, ShortcutError> {
+    dispatch!(&self.database, pool => pool.update(shortcut).await, memory => memory.shortcuts.update(shortcut).await)
+  }
+
+  async fn delete(&self, keyword: &str, version: Option<i64>) -> Result<bool, ShortcutError> {
+    dispatch!(&self.database, pool => pool.delete(keyword, version).await, memory => memory.shortcuts.delete(keyword, version).await)
+  }
+
+  async fn bulk(&self, operations: &[BulkOperation], mode: BulkMode) -> Result<BulkReport, ShortcutError> {
+    dispatch!(&self.database, pool => pool.bulk(operations, mode).await, memory => memory.shortcuts.bulk(operations, mode).await)
+  }
+
+  async fn import(&self, shortcuts: &[Shortcut], policy: ConflictPolicy, dry_run: bool) -> Result<ImportSummary, ShortcutError> {
+    dispatch!(&self.database, pool => pool.import(shortcuts, policy, dry_run).await, memory => memory.shortcuts.import(shortcuts, policy, dry_run).await)
+  }
+
+  async fn apply_sync(&self, plan: &SyncPlan) -> Result<(), ShortcutError> {
+    dispatch!(&self.database, pool => pool.apply_sync(plan).await, memory => memory.shortcuts.apply_sync(plan).await)
   }
 }
 
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
 #[cfg(test)]
-mod shortcut_repository_tests {
-  use sqlx::{Executor, Pool, Sqlite, SqlitePool};
+pub(crate) mod shortcut_repository_tests {
+  use sqlx::{Executor, SqlitePool};
 
-  use crate::{models::shortcut::Shortcut, repository::shortcut::{ShortcutRepository, ShortcutRepositoryTrait}};
+  use crate::{
+    models::{shortcut::Shortcut, sync::{SyncChange, SyncPlan}},
+    repository::{memory::MemoryStore, shortcut::{ShortcutRepository, ShortcutRepositoryTrait}},
+    schema::Database,
+  };
   use lazy_static::lazy_static;
 
   lazy_static! {
@@ -230,6 +641,8 @@ mod shortcut_repository_tests {
         updated: Some(4), 
         keyword: "bing".to_owned(),
         url: "https://bing.co.uk".to_owned(),
+        version: Some(1),
+        managed: false,
       };
 
     pub static ref GOOGLE_SHORTCUT: Shortcut = Shortcut { 
@@ -238,6 +651,8 @@ mod shortcut_repository_tests {
         updated: Some(2), 
         keyword: "google".to_owned(),
         url: "https://google.co.uk".to_owned(),
+        version: Some(1),
+        managed: false,
       };
 
     pub static ref G_SHORTCUT: Shortcut = Shortcut { 
@@ -246,6 +661,8 @@ mod shortcut_repository_tests {
         updated: Some(22), 
         keyword: "g".to_owned(),
         url: "https://google.com".to_owned(),
+        version: Some(1),
+        managed: false,
       };
 
     pub static ref GLE_SHORTCUT: Shortcut = Shortcut { 
@@ -254,6 +671,8 @@ mod shortcut_repository_tests {
         updated: Some(2222), 
         keyword: "gle".to_owned(),
         url: "https://greatlightexchange.co.uk".to_owned(),
+        version: Some(1),
+        managed: false,
       };
 
     pub static ref GOOGLE_COM_SHORTCUT: Shortcut = Shortcut { 
@@ -262,6 +681,8 @@ mod shortcut_repository_tests {
         updated: Some(3), 
         keyword: "google".to_owned(),
         url: "https://google.com".to_owned(),
+        version: Some(1),
+        managed: false,
       };
 
     pub static ref TEST_SHORTCUT: Shortcut = Shortcut { 
@@ -270,10 +691,39 @@ mod shortcut_repository_tests {
         updated: Some(222), 
         keyword: "test".to_owned(),
         url: "https://test.co.uk".to_owned(),
+        version: Some(1),
+        managed: false,
       };
   }
   
-  async fn bulk_insert(pool: Pool<Sqlite>) -> Pool<Sqlite> {
+  async fn bulk_insert(database: Database) -> ShortcutRepository {
+    // Created through the repository, so every backend starts from the same ids
+    let shortcut_repository = ShortcutRepository::new(database);
+    let fixtures = [
+      (1, 2, "google", "https://google.co.uk"),
+      (11, 22, "g", "https://google.com"),
+      (111, 222, "test", "https://test.co.uk"),
+      (1111, 2222, "gle", "https://greatlightexchange.co.uk"),
+    ];
+    for (created, updated, keyword, url) in fixtures {
+      let shortcut = Shortcut { created: Some(created), updated: Some(updated), ..Shortcut::new(keyword.to_owned(), url.to_owned()) };
+      shortcut_repository.create(&shortcut).await.unwrap();
+    }
+
+    shortcut_repository
+  }
+
+  /// Marks the `Shortcut` as managed by a shortcuts file, the same way `shortcut sync` does.
+  pub async fn mark_managed(shortcut_repository: &ShortcutRepository, keyword: &str) {
+    let url = shortcut_repository.get(keyword).await.unwrap().url;
+    let plan = SyncPlan { changes: vec![SyncChange::Update { keyword: keyword.to_owned(), old_url: url.clone(), url }] };
+
+    shortcut_repository.apply_sync(&plan).await.unwrap();
+  }
+
+  async fn mock_pool() -> Database {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+
     // Create temporary table within memory
     pool.execute("CREATE TABLE IF NOT EXISTS shortcut (
         id        INTEGER UNIQUE,
@@ -281,168 +731,520 @@ mod shortcut_repository_tests {
         updated   INTEGER NOT NULL,
         keyword   TEXT  NOT NULL  UNIQUE,
         url       TEXT  NOT NULL,
+        version   INTEGER NOT NULL DEFAULT 1,
+        managed   BOOLEAN NOT NULL DEFAULT 0,
         PRIMARY KEY(id ASC)
       );").await.unwrap();
 
-    // Batch insert test data
-    let query = "
-        INSERT INTO shortcut (created, updated, keyword, url) VALUES
-        (1, 2, 'google', 'https://google.co.uk'), 
-        (11, 22, 'g', 'https://google.com'), 
-        (111, 222, 'test', 'https://test.co.uk'), 
-        (1111, 2222, 'gle', 'https://greatlightexchange.co.uk')
-    ";
-    pool.execute(query).await.unwrap();
-
-    // Verify count
-    pool
+    Database::Sqlite(pool)
   }
 
-  async fn mock_pool() -> Pool<Sqlite> {
-    bulk_insert(SqlitePool::connect("sqlite::memory:").await.unwrap()).await
-  }
+  /// The conformance suite: every test runs against each `ShortcutRepositoryTrait` backend built in
+  /// (SQLite, PostgreSQL and `InMemoryShortcutRepository`), which must all behave the same.
+  async fn setup() -> Vec<ShortcutRepository> {
+    #[allow(unused_mut)]
+    let mut databases: Vec<Database> = vec![mock_pool().await, Database::Memory(MemoryStore::default())];
+    #[cfg(feature = "postgres")]
+    databases.push(Database::Postgres(crate::repository::postgres::postgres_test_server::test_database().await));
 
-  async fn setup() -> ShortcutRepository {
-    ShortcutRepository::new(mock_pool().await)
+    let mut repositories: Vec<ShortcutRepository> = vec![];
+    for database in databases {
+      repositories.push(bulk_insert(database).await);
+    }
+
+    repositories
   }
 
   mod get_tests {
-    use crate::{error::ShortcutError, repository::shortcut::{ShortcutRepository, ShortcutRepositoryTrait}};
+    use crate::{error::ShortcutError, repository::shortcut::ShortcutRepositoryTrait};
 
     use super::{setup, GOOGLE_SHORTCUT};
 
     #[tokio::test]
     async fn gets_exact_match_when_exists() {
       let input = "google".to_string();
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.get(&input).await.unwrap();
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.get(&input).await.unwrap();
   
-      assert_eq!(result, GOOGLE_SHORTCUT.to_owned());
+        assert_eq!(result, GOOGLE_SHORTCUT.to_owned());
+      }
     }
   
     #[tokio::test]
     async fn returns_exception_when_none_exists() {
       let input = "bing".to_string();
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.get(&input).await;
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.get(&input).await;
   
-      assert!(matches!(result, Err(ShortcutError::NotFound)));
+        assert!(matches!(result, Err(ShortcutError::NotFound)));
+      }
     }
     
     #[tokio::test]
     async fn returns_exception_when_empty_input() {
       let input = "".to_string();
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.get(&input).await;
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.get(&input).await;
   
-      assert!(matches!(result, Err(ShortcutError::NotFound)));
+        assert!(matches!(result, Err(ShortcutError::NotFound)));
+      }
     }
   }
 
   mod create_tests {
-    use crate::{error::ShortcutError, repository::shortcut::{ShortcutRepository, ShortcutRepositoryTrait}};
+    use crate::{error::ShortcutError, repository::shortcut::ShortcutRepositoryTrait};
 
     use super::{setup, GOOGLE_SHORTCUT, BING_SHORTCUT};
 
     #[tokio::test]
     async fn gets_true_when_unique_entry() {
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.create(&BING_SHORTCUT).await.unwrap();
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.create(&BING_SHORTCUT).await.unwrap();
   
-      assert!(result);
+        assert!(result);
+      }
     }
   
     #[tokio::test]
     async fn returns_exception_when_already_exists() {
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.create(&GOOGLE_SHORTCUT).await;
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.create(&GOOGLE_SHORTCUT).await;
   
-      assert!(matches!(result, Err(ShortcutError::UniqueConstraintError)));
+        assert!(matches!(result, Err(ShortcutError::UniqueConstraintError)));
+      }
     }
   }
 
   mod update_test {
-    use crate::repository::shortcut::{ShortcutRepository, ShortcutRepositoryTrait};
+    use crate::{error::ShortcutError, models::shortcut::Shortcut, repository::shortcut::ShortcutRepositoryTrait};
 
     use super::{setup, GOOGLE_COM_SHORTCUT, BING_SHORTCUT};
 
     #[tokio::test]
     async fn returns_true_when_exists() {
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.update(&GOOGLE_COM_SHORTCUT).await.unwrap();
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.update(&GOOGLE_COM_SHORTCUT).await.unwrap();
       
-      assert!(result);
+        assert!(result);
+      }
+    }
+
+    #[tokio::test]
+    async fn increments_version_when_updated() {
+      for shortcut_repository in setup().await {
+        shortcut_repository.update(&GOOGLE_COM_SHORTCUT).await.unwrap();
+        let result = shortcut_repository.get(&GOOGLE_COM_SHORTCUT.keyword).await.unwrap();
+      
+        assert_eq!(result.version, Some(2));
+      }
+    }
+
+    #[tokio::test]
+    async fn returns_exception_when_version_is_stale() {
+      for shortcut_repository in setup().await {
+        shortcut_repository.update(&GOOGLE_COM_SHORTCUT).await.unwrap();
+        let result = shortcut_repository.update(&GOOGLE_COM_SHORTCUT).await;
+      
+        assert!(matches!(result, Err(ShortcutError::VersionConflict)));
+      }
+    }
+
+    #[tokio::test]
+    async fn returns_true_when_no_version_given() {
+      for shortcut_repository in setup().await {
+        let shortcut = Shortcut { version: None, ..GOOGLE_COM_SHORTCUT.to_owned() };
+        let result = shortcut_repository.update(&shortcut).await.unwrap();
+      
+        assert!(result);
+      }
     }
   
     #[tokio::test]
     async fn returns_false_when_not_exists() {
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.update(&BING_SHORTCUT).await.unwrap();
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.update(&BING_SHORTCUT).await.unwrap();
   
-      assert!(!result);
+        assert!(!result);
+      }
     }
   }
 
   mod delete_test {
-    use crate::repository::shortcut::{ShortcutRepository, ShortcutRepositoryTrait};
+    use crate::{error::ShortcutError, repository::shortcut::ShortcutRepositoryTrait};
 
-    use super::{setup, GOOGLE_COM_SHORTCUT, BING_SHORTCUT};
+    use super::{mark_managed, setup, GOOGLE_COM_SHORTCUT, BING_SHORTCUT};
 
     #[tokio::test]
     async fn returns_true_when_exists() {
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.delete(GOOGLE_COM_SHORTCUT.keyword.as_str()).await.unwrap();
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.delete(GOOGLE_COM_SHORTCUT.keyword.as_str(), GOOGLE_COM_SHORTCUT.version).await.unwrap();
       
-      assert!(result);
+        assert!(result);
+      }
     }
   
     #[tokio::test]
     async fn returns_false_when_not_exists() {
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.delete(BING_SHORTCUT.keyword.as_str()).await.unwrap();
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.delete(BING_SHORTCUT.keyword.as_str(), BING_SHORTCUT.version).await.unwrap();
+  
+        assert!(!result);
+      }
+    }
+
+    #[tokio::test]
+    async fn returns_exception_when_version_is_stale() {
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.delete(GOOGLE_COM_SHORTCUT.keyword.as_str(), Some(2)).await;
+  
+        assert!(matches!(result, Err(ShortcutError::VersionConflict)));
+      }
+    }
+
+    #[tokio::test]
+    async fn returns_exception_when_managed_by_shortcuts_file() {
+      for shortcut_repository in setup().await {
+        mark_managed(&shortcut_repository, "google").await;
+
+        let result = shortcut_repository.delete(GOOGLE_COM_SHORTCUT.keyword.as_str(), None).await;
   
-      assert!(!result);
+        assert!(matches!(result, Err(ShortcutError::ReadOnly)));
+      }
     }
   }
 
   mod fuzzy_search_test {
-    use crate::{error::ShortcutError, repository::shortcut::{ShortcutRepository, ShortcutRepositoryTrait}};
+    use crate::{error::ShortcutError, repository::shortcut::ShortcutRepositoryTrait};
 
     use super::{setup, G_SHORTCUT, GLE_SHORTCUT, GOOGLE_SHORTCUT, TEST_SHORTCUT};
 
     #[tokio::test]
     async fn returns_one_entry_when_exists() {
       let input = "test";
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.fuzzy_search(input).await.unwrap();
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.fuzzy_search(input, 0.0).await.unwrap();
  
-      let expected = vec![TEST_SHORTCUT.to_owned()];
+        let expected = vec![TEST_SHORTCUT.to_owned()];
 
-      assert_eq!(result, expected);
+        assert_eq!(result, expected);
+      }
     }
   
     #[tokio::test]
     async fn returns_ordered_multiple_entry_when_exists() {
       let input = "google";
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.fuzzy_search(input).await.unwrap();
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.fuzzy_search(input, 0.0).await.unwrap();
  
-      let expected = vec![
-        GOOGLE_SHORTCUT.to_owned(), 
-        GLE_SHORTCUT.to_owned(), 
-        G_SHORTCUT.to_owned()
-      ];
+        let expected = vec![
+          GOOGLE_SHORTCUT.to_owned(), 
+          GLE_SHORTCUT.to_owned(), 
+          G_SHORTCUT.to_owned()
+        ];
 
-      assert_eq!(result, expected);
+        assert_eq!(result, expected);
+      }
+    }
+
+    #[tokio::test]
+    async fn returns_only_entries_above_min_score() {
+      let input = "google";
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.fuzzy_search(input, 0.5).await.unwrap();
+
+        assert_eq!(result, vec![GOOGLE_SHORTCUT.to_owned()]);
+      }
     }
 
     #[tokio::test]
     async fn returns_error_when_no_matches_exists() {
       let input = "bing";
-      let shortcut_repository: ShortcutRepository = setup().await;
-      let result = shortcut_repository.fuzzy_search(input).await;
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.fuzzy_search(input, 0.0).await;
 
-      assert!(matches!(result, Err(ShortcutError::NoMatches)));
+        assert!(matches!(result, Err(ShortcutError::NoMatches)));
+      }
     }
   }
-}
+
+  mod find_by_url_test {
+    use crate::repository::shortcut::ShortcutRepositoryTrait;
+
+    use super::{setup, G_SHORTCUT, GOOGLE_SHORTCUT};
+
+    #[tokio::test]
+    async fn returns_ordered_entries_when_url_contains_fragment() {
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.find_by_url("google").await.unwrap();
+
+        assert_eq!(result, vec![G_SHORTCUT.to_owned(), GOOGLE_SHORTCUT.to_owned()]);
+      }
+    }
+
+    #[tokio::test]
+    async fn returns_empty_when_no_url_contains_fragment() {
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.find_by_url("bing").await.unwrap();
+
+        assert!(result.is_empty());
+      }
+    }
+  }
+
+  mod bulk_test {
+    use crate::{
+      error::ShortcutError,
+      models::bulk::{BulkItemStatus, BulkMode, BulkOperation}, 
+      repository::shortcut::ShortcutRepositoryTrait
+    };
+
+    use super::setup;
+
+    fn operations() -> Vec<BulkOperation> {
+      vec![
+        BulkOperation::Create { keyword: "bing".to_owned(), url: "https://bing.co.uk".to_owned() },
+        BulkOperation::Update { keyword: "missing".to_owned(), url: "https://missing.co.uk".to_owned(), version: Some(1) },
+        BulkOperation::Update { keyword: "google".to_owned(), url: "https://google.com".to_owned(), version: Some(1) },
+        BulkOperation::Delete { keyword: "test".to_owned(), version: Some(1) },
+      ]
+    }
+
+    #[tokio::test]
+    async fn commits_every_operation_when_all_succeed() {
+      for shortcut_repository in setup().await {
+        let mut operations = operations();
+        operations.remove(1);
+
+        let result = shortcut_repository.bulk(&operations, BulkMode::AllOrNothing).await.unwrap();
+
+        assert!(result.committed);
+        assert!(result.results.iter().all(|item| item.status == BulkItemStatus::Applied));
+        assert_eq!(shortcut_repository.get("bing").await.unwrap().url, "https://bing.co.uk");
+        assert_eq!(shortcut_repository.get("google").await.unwrap().url, "https://google.com");
+        assert!(shortcut_repository.get("test").await.is_err());
+      }
+    }
+
+    #[tokio::test]
+    async fn rolls_back_everything_when_all_or_nothing_fails() {
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.bulk(&operations(), BulkMode::AllOrNothing).await.unwrap();
+
+        let statuses: Vec<BulkItemStatus> = result.results.iter().map(|item| item.status).collect();
+
+        assert!(!result.committed);
+        assert_eq!(statuses, vec![BulkItemStatus::RolledBack, BulkItemStatus::Failed, BulkItemStatus::Skipped, BulkItemStatus::Skipped]);
+        assert!(shortcut_repository.get("bing").await.is_err());
+        assert_eq!(shortcut_repository.get("google").await.unwrap().url, "https://google.co.uk");
+      }
+    }
+
+    #[tokio::test]
+    async fn keeps_successful_operations_when_best_effort_fails() {
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.bulk(&operations(), BulkMode::BestEffort).await.unwrap();
+
+        let statuses: Vec<BulkItemStatus> = result.results.iter().map(|item| item.status).collect();
+
+        assert!(result.committed);
+        assert_eq!(statuses, vec![BulkItemStatus::Applied, BulkItemStatus::Failed, BulkItemStatus::Applied, BulkItemStatus::Applied]);
+        assert_eq!(shortcut_repository.get("bing").await.unwrap().url, "https://bing.co.uk");
+        assert_eq!(shortcut_repository.get("google").await.unwrap().url, "https://google.com");
+        assert!(shortcut_repository.get("missing").await.is_err());
+      }
+    }
+
+    #[tokio::test]
+    async fn reports_unique_constraint_when_creating_existing_keyword() {
+      for shortcut_repository in setup().await {
+        let operations = vec![BulkOperation::Create { keyword: "google".to_owned(), url: "https://google.com".to_owned() }];
+
+        let result = shortcut_repository.bulk(&operations, BulkMode::BestEffort).await.unwrap();
+
+        assert_eq!(result.results[0].status, BulkItemStatus::Failed);
+        assert_eq!(result.results[0].error, Some("Keyword is not unique.".to_owned()));
+      }
+    }
+
+    #[tokio::test]
+    async fn reports_version_required_when_version_missing() {
+      for shortcut_repository in setup().await {
+        let operations = vec![
+          BulkOperation::Update { keyword: "google".to_owned(), url: "https://google.com".to_owned(), version: None },
+          BulkOperation::Delete { keyword: "test".to_owned(), version: None },
+        ];
+
+        let result = shortcut_repository.bulk(&operations, BulkMode::BestEffort).await.unwrap();
+
+        assert!(result.results.iter().all(|item| item.status == BulkItemStatus::Failed && item.error == Some(ShortcutError::VersionRequired.to_string())));
+        assert_eq!(shortcut_repository.get("google").await.unwrap().url, "https://google.co.uk");
+        assert!(shortcut_repository.get("test").await.is_ok());
+      }
+    }
+  }
+
+  mod stream_all_test {
+    use futures::TryStreamExt;
+
+    use crate::{models::shortcut::Shortcut, repository::shortcut::ShortcutRepositoryTrait};
+
+    use super::{setup, G_SHORTCUT, GLE_SHORTCUT, GOOGLE_SHORTCUT, TEST_SHORTCUT};
+
+    #[tokio::test]
+    async fn returns_every_entry_ordered_by_keyword() {
+      for shortcut_repository in setup().await {
+        let result: Vec<Shortcut> = shortcut_repository.stream_all().try_collect().await.unwrap();
+
+        let expected = vec![
+          G_SHORTCUT.to_owned(),
+          GLE_SHORTCUT.to_owned(),
+          GOOGLE_SHORTCUT.to_owned(),
+          TEST_SHORTCUT.to_owned(),
+        ];
+
+        assert_eq!(result, expected);
+      }
+    }
+  }
+
+  mod import_test {
+    use crate::{
+      models::{shortcut::Shortcut, transfer::{ConflictPolicy, ImportAction}}, 
+      repository::shortcut::ShortcutRepositoryTrait
+    };
+
+    use super::setup;
+
+    fn shortcuts() -> Vec<Shortcut> {
+      vec![
+        Shortcut::new("bing".to_owned(), "https://bing.co.uk".to_owned()),
+        Shortcut::new("test".to_owned(), "https://test.co.uk".to_owned()),
+        Shortcut::new("google".to_owned(), "https://google.com".to_owned()),
+      ]
+    }
+
+    fn actions(shortcut_repository_result: &[crate::models::transfer::ImportItem]) -> Vec<ImportAction> {
+      shortcut_repository_result.iter().map(|item| item.action).collect()
+    }
+
+    #[tokio::test]
+    async fn skips_existing_keywords_when_policy_is_skip() {
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.import(&shortcuts(), ConflictPolicy::Skip, false).await.unwrap();
+
+        assert!(result.committed);
+        assert_eq!(actions(&result.items), vec![ImportAction::Created, ImportAction::Unchanged, ImportAction::Skipped]);
+        assert_eq!(result.items[2].old_url, Some("https://google.co.uk".to_owned()));
+        assert_eq!(shortcut_repository.get("bing").await.unwrap().url, "https://bing.co.uk");
+        assert_eq!(shortcut_repository.get("google").await.unwrap().url, "https://google.co.uk");
+      }
+    }
+
+    #[tokio::test]
+    async fn replaces_existing_urls_when_policy_is_overwrite() {
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.import(&shortcuts(), ConflictPolicy::Overwrite, false).await.unwrap();
+
+        assert_eq!(actions(&result.items), vec![ImportAction::Created, ImportAction::Unchanged, ImportAction::Updated]);
+        assert_eq!(shortcut_repository.get("google").await.unwrap().url, "https://google.com");
+      }
+    }
+
+    #[tokio::test]
+    async fn creates_free_keyword_when_policy_is_rename() {
+      for shortcut_repository in setup().await {
+        shortcut_repository.create(&Shortcut::new("google-2".to_owned(), "https://google.de".to_owned())).await.unwrap();
+
+        let result = shortcut_repository.import(&shortcuts(), ConflictPolicy::Rename, false).await.unwrap();
+
+        assert_eq!(result.renamed, 1);
+        assert_eq!(result.items[2].imported_as, "google-3");
+        assert_eq!(shortcut_repository.get("google-3").await.unwrap().url, "https://google.com");
+        assert_eq!(shortcut_repository.get("google").await.unwrap().url, "https://google.co.uk");
+      }
+    }
+
+    #[tokio::test]
+    async fn rolls_back_everything_when_policy_is_fail() {
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.import(&shortcuts(), ConflictPolicy::Fail, false).await.unwrap();
+
+        assert!(!result.committed);
+        assert_eq!(result.failed, 1);
+        assert!(shortcut_repository.get("bing").await.is_err());
+      }
+    }
+
+    #[tokio::test]
+    async fn writes_nothing_when_dry_run() {
+      for shortcut_repository in setup().await {
+        let result = shortcut_repository.import(&shortcuts(), ConflictPolicy::Overwrite, true).await.unwrap();
+
+        assert!(!result.committed);
+        assert_eq!((result.created, result.updated, result.unchanged), (1, 1, 1));
+        assert!(shortcut_repository.get("bing").await.is_err());
+        assert_eq!(shortcut_repository.get("google").await.unwrap().url, "https://google.co.uk");
+      }
+    }
+  }
+
+  mod apply_sync_test {
+    use crate::{
+      error::ShortcutError, 
+      models::{shortcut::Shortcut, sync::{SyncChange, SyncPlan}}, 
+      repository::shortcut::ShortcutRepositoryTrait
+    };
+
+    use super::{mark_managed, setup};
+
+    #[tokio::test]
+    async fn applies_changes_and_marks_shortcuts_managed() {
+      for shortcut_repository in setup().await {
+        let plan = SyncPlan { changes: vec![
+          SyncChange::Create { keyword: "gh".to_owned(), url: "https://github.com".to_owned() },
+          SyncChange::Update { keyword: "g".to_owned(), old_url: "https://google.com".to_owned(), url: "https://google.de".to_owned() },
+        ] };
+        shortcut_repository.apply_sync(&plan).await.unwrap();
+
+        let created = shortcut_repository.get("gh").await.unwrap();
+        let updated = shortcut_repository.get("g").await.unwrap();
+
+        assert!(created.managed);
+        assert_eq!((updated.url.as_str(), updated.version, updated.managed), ("https://google.de", Some(2), true));
+
+        let result = shortcut_repository.update(&Shortcut::new("g".to_owned(), "https://google.fr".to_owned())).await;
+        assert!(matches!(result, Err(ShortcutError::ReadOnly)));
+      }
+    }
+
+    #[tokio::test]
+    async fn deletes_only_managed_shortcuts() {
+      for shortcut_repository in setup().await {
+        mark_managed(&shortcut_repository, "g").await;
+
+        let plan = SyncPlan { changes: vec![
+          SyncChange::Delete { keyword: "g".to_owned(), url: "https://google.com".to_owned() },
+          SyncChange::Delete { keyword: "test".to_owned(), url: "https://test.co.uk".to_owned() },
+        ] };
+        shortcut_repository.apply_sync(&plan).await.unwrap();
+
+        assert!(shortcut_repository.get("g").await.is_err());
+        assert!(shortcut_repository.get("test").await.is_ok());
+      }
+    }
+
+    #[tokio::test]
+    async fn applies_nothing_when_a_change_fails() {
+      for shortcut_repository in setup().await {
+        let plan = SyncPlan { changes: vec![
+          SyncChange::Create { keyword: "gh".to_owned(), url: "https://github.com".to_owned() },
+          SyncChange::Create { keyword: "g".to_owned(), url: "https://google.de".to_owned() },
+        ] };
+        let result = shortcut_repository.apply_sync(&plan).await;
+
+        assert!(matches!(result, Err(ShortcutError::FailedToSync)));
+        assert!(shortcut_repository.get("gh").await.is_err());
+      }
+    }
+  }
+}
\ No newline at end of file
@@ -0,0 +1,130 @@
+use crate::{
+  models::{shortcut::Shortcut, sync::SyncChange},
+  repository::{settings::SettingsStatements, shortcut::ShortcutStatements},
+};
+
+use futures::{stream::BoxStream, StreamExt};
+use sqlx::{Error, Pool, Sqlite, SqliteConnection};
+
+/// The SQLite statements, checked against the schema in `migrations` at compile time.
+impl ShortcutStatements for Sqlite {
+  async fn insert(connection: &mut SqliteConnection, shortcut: &Shortcut) -> Result<(), Error> {
+    sqlx::query!(
+        r#"INSERT INTO shortcut (created, updated, keyword, url) VALUES (?1, ?2, ?3, ?4)"#, 
+        shortcut.created, 
+        shortcut.updated,
+        shortcut.keyword,
+        shortcut.url
+      )
+      .execute(&mut *connection)
+      .await?;
+
+    Ok(())
+  }
+
+  async fn fetch_all(connection: &mut SqliteConnection) -> Result<Vec<Shortcut>, Error> {
+    sqlx::query_as!(Shortcut, r#"SELECT * FROM shortcut;"#)
+      .fetch_all(&mut *connection)
+      .await
+  }
+
+  async fn fetch_by_keyword(connection: &mut SqliteConnection, keyword: &str) -> Result<Option<Shortcut>, Error> {
+    sqlx::query_as!(Shortcut, r#"SELECT * FROM shortcut WHERE keyword = ?1;"#, keyword)
+      .fetch_optional(&mut *connection)
+      .await
+  }
+
+  async fn fetch_by_url(connection: &mut SqliteConnection, fragment: &str) -> Result<Vec<Shortcut>, Error> {
+    sqlx::query_as!(Shortcut, r#"SELECT * FROM shortcut WHERE instr(url, ?1) > 0 ORDER BY keyword;"#, fragment)
+      .fetch_all(&mut *connection)
+      .await
+  }
+
+  fn stream_all(pool: Pool<Sqlite>) -> BoxStream<'static, Result<Shortcut, Error>> {
+    async_stream::stream! {
+      let mut rows = sqlx::query_as!(Shortcut, r#"SELECT * FROM shortcut ORDER BY keyword;"#).fetch(&pool);
+
+      while let Some(row) = rows.next().await {
+        yield row;
+      }
+    }.boxed()
+  }
+
+  async fn fetch_managed(connection: &mut SqliteConnection, keyword: &str) -> Result<Option<bool>, Error> {
+    sqlx::query_scalar!(r#"SELECT managed FROM shortcut WHERE keyword = ?1"#, keyword)
+      .fetch_optional(&mut *connection)
+      .await
+  }
+
+  async fn update_url(connection: &mut SqliteConnection, shortcut: &Shortcut) -> Result<u64, Error> {
+    let result = sqlx::query!(
+        r#"UPDATE shortcut SET url = ?1, updated = ?2, version = version + 1 WHERE keyword = ?3 AND (?4 IS NULL OR version = ?4) AND managed = 0"#, 
+        shortcut.url,
+        shortcut.updated,
+        shortcut.keyword,
+        shortcut.version
+      )
+      .execute(&mut *connection)
+      .await?;
+
+    Ok(result.rows_affected())
+  }
+
+  async fn remove(connection: &mut SqliteConnection, keyword: &str, version: Option<i64>) -> Result<u64, Error> {
+    let result = sqlx::query!(r#"DELETE from shortcut WHERE keyword = ?1 AND (?2 IS NULL OR version = ?2) AND managed = 0"#, keyword, version)
+      .execute(&mut *connection)
+      .await?;
+
+    Ok(result.rows_affected())
+  }
+
+  async fn apply_change(connection: &mut SqliteConnection, change: &SyncChange, timestamp: i64) -> Result<(), Error> {
+    match change {
+      SyncChange::Create { keyword, url } => sqlx::query!(
+          r#"INSERT INTO shortcut (created, updated, keyword, url, managed) VALUES (?1, ?1, ?2, ?3, 1)"#, 
+          timestamp, 
+          keyword, 
+          url
+        )
+        .execute(&mut *connection)
+        .await?,
+      SyncChange::Update { keyword, url, .. } => sqlx::query!(
+          r#"UPDATE shortcut SET url = ?1, updated = ?2, version = version + 1, managed = 1 WHERE keyword = ?3"#, 
+          url, 
+          timestamp, 
+          keyword
+        )
+        .execute(&mut *connection)
+        .await?,
+      SyncChange::Delete { keyword, .. } => sqlx::query!(r#"DELETE FROM shortcut WHERE keyword = ?1 AND managed = 1"#, keyword)
+        .execute(&mut *connection)
+        .await?,
+    };
+
+    Ok(())
+  }
+}
+
+impl SettingsStatements for Sqlite {
+  async fn fetch_values(connection: &mut SqliteConnection) -> Result<Vec<(String, String)>, Error> {
+    let rows = sqlx::query!(r#"SELECT key, value FROM settings;"#)
+      .fetch_all(&mut *connection)
+      .await?;
+
+    Ok(rows.into_iter().map(|row| (row.key, row.value)).collect())
+  }
+
+  async fn upsert_value(connection: &mut SqliteConnection, key: &str, value: &str, updated: i64) -> Result<(), Error> {
+    sqlx::query!(
+        r#"INSERT INTO settings (key, value, updated) VALUES (?1, ?2, ?3)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated = excluded.updated;"#,
+        key,
+        value,
+        updated
+      )
+      .execute(&mut *connection)
+      .await?;
+
+    Ok(())
+  }
+}
\ No newline at end of file
@@ -0,0 +1,31 @@
+use crate::{health::readiness, metrics::METRICS, models::health::Readiness, state::AppState};
+
+use axum::{http::{header::CONTENT_TYPE, StatusCode}, response::IntoResponse, Extension, Json};
+
+/// This is the function for the `GET /livez` (and `GET /healthcheck`) endpoint.
+///
+/// It will always return `200 OK`, as answering at all means the server is alive.
+pub async fn live() -> StatusCode {
+  StatusCode::OK
+}
+
+/// This is the function for the `GET /readyz` endpoint.
+///
+/// It will check the database, its migrations and the templates, returning `503 Service Unavailable` if any of them aren't ready.
+pub async fn ready(
+  Extension(app): Extension<AppState>,
+) -> (StatusCode, Json<Readiness>) {
+  let readiness: Readiness = readiness(&app.database).await;
+  let status: StatusCode = if readiness.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+  (status, Json(readiness))
+}
+
+/// This is the function for the `GET /metrics` endpoint.
+///
+/// It will render every metric in the Prometheus text format, see `Metrics`.
+pub async fn get_metrics(
+  Extension(app): Extension<AppState>,
+) -> impl IntoResponse {
+  ([(CONTENT_TYPE, "text/plain; version=0.0.4")], METRICS.render(&app.database))
+}
\ No newline at end of file
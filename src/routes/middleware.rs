@@ -1,8 +1,11 @@
+use crate::{state::AppState, utils::url_util::{normalize_base_path, BasePath}};
+
 use axum::{
   extract::Request,
   http::{HeaderMap, StatusCode},
   middleware::Next,
-  response::IntoResponse,
+  response::{IntoResponse, Response},
+  Extension,
 };
 
 /// Used to get the header from `HTMX` requests.
@@ -16,3 +19,20 @@ pub async fn get_htmx_header(
 
   Ok(next.run(req).await)
 }
+
+/// Adds the `BasePath` the pages and redirects of the request are under, which is the configured `base_path`,
+/// or the `X-Forwarded-Prefix` set by a reverse proxy that strips it when `trust_forwarded_headers` is set.
+pub async fn base_path(
+  Extension(app): Extension<AppState>,
+  mut req: Request,
+  next: Next,
+) -> Response {
+  let prefix: Option<String> = req.headers().get("x-forwarded-prefix")
+    .filter(|_| app.config.trust_forwarded_headers)
+    .and_then(|value| value.to_str().ok())
+    .and_then(normalize_base_path);
+
+  req.extensions_mut().insert(BasePath(prefix.unwrap_or_else(|| app.config.base_path.clone())));
+
+  next.run(req).await
+}
\ No newline at end of file
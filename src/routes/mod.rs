@@ -1,27 +1,65 @@
 use axum::{
+  extract::DefaultBodyLimit,
   middleware::from_fn,
-  routing::{delete, get, post},
+  routing::{delete, get, post, put},
   Router,
 };
 
 use self::{
+  health::get_metrics,
+  health::live,
+  health::ready,
   middleware::get_htmx_header,
+  shortcut::bulk_shortcuts,
   shortcut::post_shortcut,
   shortcut::redirect_shortcut,
   shortcut::search_shortcut,
   shortcut::update_shortcut,
   shortcut::delete_shortcut,
+  shortcut::export_shortcuts,
+  shortcut::get_shortcut,
+  shortcut::replace_shortcut_urls,
+  shortcut::preview_import_shortcuts,
+  shortcut::import_shortcuts,
+  settings::get_autostart,
+  settings::update_autostart,
+  settings::get_settings,
+  settings::update_settings,
 };
 
+pub mod health;
 pub mod middleware;
+pub mod settings;
 pub mod shortcut;
 
+/// The routes for monitoring, the liveness and readiness probes and the metrics.
+///
+/// `/healthcheck` is kept for the probes that already use it, and like before answers `200` whenever the server is up.
+pub fn create_health_routes() -> Router {
+  Router::new()
+    .route("/metrics", get(get_metrics))
+    .route("/livez", get(live))
+    .route("/readyz", get(ready))
+    .route("/healthcheck", get(live))
+}
+
 pub fn create_api_routes() -> Router {
   Router::new()
     .route("/search", get(search_shortcut))
     .route("/post", post(post_shortcut))
     .route("/get", get(redirect_shortcut))
+    .route("/shortcut", get(get_shortcut))
     .route("/update", post(update_shortcut))
     .route("/delete", delete(delete_shortcut))
+    .route("/bulk", post(bulk_shortcuts))
+    .route("/bulk/replace-url", post(replace_shortcut_urls))
+    .route("/export", get(export_shortcuts))
+    // Browser databases are often larger than the default body limit of 2MB.
+    .route("/import/preview", post(preview_import_shortcuts).layer(DefaultBodyLimit::max(64 * 1024 * 1024)))
+    .route("/import", post(import_shortcuts))
+    .route("/settings", get(get_settings))
+    .route("/settings", put(update_settings))
+    .route("/settings/autostart", get(get_autostart))
+    .route("/settings/autostart", put(update_autostart))
     .layer(from_fn(get_htmx_header))
 }
@@ -0,0 +1,67 @@
+use crate::{
+  autostart,
+  error::{AutostartError, TemplateError},
+  macros::renderable::Renderable,
+  models::settings::{AutostartRequest, Settings},
+  state::AppState,
+  templates::components::AutostartTemplate,
+  utils::url_util::BasePath,
+  TERA,
+};
+
+use axum::{response::Html, Extension, Form};
+use tracing::error;
+
+/// Renders whether shortcut starts on login, along with the error if it couldn't be checked or changed.
+fn autostart_html(result: Result<bool, AutostartError>, base_path: &BasePath) -> Result<Html<String>, TemplateError> {
+  let tera = TERA.load();
+  let context: AutostartTemplate = match result {
+    Ok(enabled) => AutostartTemplate { enabled, error: None },
+    Err(err) => {
+      error!("{}", err);
+      AutostartTemplate { enabled: false, error: Some(err.to_string()) }
+    },
+  };
+
+  context.get_html(&tera, &base_path.0)
+}
+
+/// This is the function for the `GET /api/settings/autostart` endpoint.
+///
+/// It will render whether shortcut is registered to start on login.
+pub async fn get_autostart(
+  Extension(base_path): Extension<BasePath>,
+) -> Result<Html<String>, TemplateError> {
+  autostart_html(autostart::is_enabled(), &base_path)
+}
+
+/// This is the function for the `PUT /api/settings/autostart` endpoint.
+///
+/// It will register (or unregister) shortcut to start on login, and render the result.
+pub async fn update_autostart(
+  Extension(base_path): Extension<BasePath>,
+  Form(params): Form<AutostartRequest>,
+) -> Result<Html<String>, TemplateError> {
+  autostart_html(autostart::set_enabled(params.enabled).and_then(|_| autostart::is_enabled()), &base_path)
+}
+
+/// This is the function for the `GET /api/settings` endpoint.
+///
+/// It will call the `Settings_Service` function `get` and return the settings form.
+pub async fn get_settings(
+  Extension(app): Extension<AppState>,
+  Extension(base_path): Extension<BasePath>,
+) -> Result<Html<String>, TemplateError> {
+  app.settings_service.get(&base_path.0)
+}
+
+/// This is the function for the `PUT /api/settings` endpoint.
+///
+/// It will call the `Settings_Service` function `update`, which publishes the new settings to every request from then on.
+pub async fn update_settings(
+  Extension(app): Extension<AppState>,
+  Extension(base_path): Extension<BasePath>,
+  Form(params): Form<Settings>,
+) -> Result<Html<String>, TemplateError> {
+  app.settings_service.update(params, &base_path.0).await
+}
\ No newline at end of file
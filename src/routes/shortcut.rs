@@ -1,24 +1,49 @@
 use crate::{
+  error::{ShortcutError, TemplateError},
   macros::renderable::Renderable,
-  models::shortcut::{PostRequest, KeywordRequest}, 
+  models::{
+    bulk::{BulkReport, BulkRequest, ReplaceUrlReport, ReplaceUrlRequest},
+    settings::Settings,
+    shortcut::{PostRequest, KeywordRequest},
+    transfer::{ConflictPolicy, ExportRequest, ImportFormat, ImportRequest},
+  }, 
   state::AppState, 
-  templates::components::EmptyTemplate, 
+  templates::components::{EmptyTemplate, ErrorAlert}, 
+  utils::url_util::{public_ui_url, BasePath},
   TERA, 
 };
 
 use axum::{
-  extract::Query, response::{Html, Redirect}, Extension, Form
+  body::Body,
+  extract::{Multipart, Query}, 
+  http::{header::{CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, IF_MATCH}, HeaderMap, StatusCode}, 
+  response::{Html, IntoResponse, Redirect}, 
+  Extension, Form, Json
 };
 
-use tera::Tera;
-use tracing::debug;
+use tracing::{debug, error};
+
+/// Reads the expected version from an `If-Match` header, which only matches strong ETags (`"3"`), not weak ones (`W/"3"`).
+fn if_match_version(headers: &HeaderMap) -> Option<i64> {
+  headers.get(IF_MATCH)?
+    .to_str().ok()?
+    .trim()
+    .strip_prefix('"')?
+    .strip_suffix('"')?
+    .parse().ok()
+}
+
+/// `HTMX` only swaps successful responses, so its requests always get a `200` and the rendered alert or dialog explains what happened.
+fn htmx_status(htmx: bool, (status, html): (StatusCode, Html<String>)) -> (StatusCode, Html<String>) {
+  if htmx { (StatusCode::OK, html) } else { (status, html) }
+}
 
 /// Used for when update is retrieved, but `params` are invalid to reduce load on db.
-pub async fn get_empty_html() -> Html<String> {
-  let tera:Tera = TERA.read().unwrap().clone();
+pub async fn get_empty_html() -> Result<Html<String>, TemplateError> {
+  let tera = TERA.load();
   let context: EmptyTemplate = EmptyTemplate {};
 
-  context.get_html(tera)
+  context.get_html(&tera, "")
 }
 
 /// This is the fuction for the `/api/search` endpoint. 
@@ -28,15 +53,18 @@ pub async fn get_empty_html() -> Html<String> {
 /// If the keyword inputted is empty, it will instead return an empty `Html` `String`.
 pub async fn search_shortcut(
   Extension(app): Extension<AppState>,
+  Extension(base_path): Extension<BasePath>,
   Query(params): Query<KeywordRequest>,
-) -> Result<Html<String>, ()> {
+) -> Result<Html<String>, TemplateError> {
   debug!("{:?}", params.keyword);
 
   if params.keyword.is_empty() {
-    return Ok(get_empty_html().await);
+    return get_empty_html().await;
   }
 
-  Ok(app.shortcut_service.find_similar(&params.keyword.to_lowercase()).await)
+  let min_score: f32 = app.settings.borrow().search_min_score as f32;
+
+  app.shortcut_service.find_similar(&params.keyword.to_lowercase(), min_score, &base_path.0).await
 }
 
 /// This is the function for the `/api/post` endpoint.
@@ -44,11 +72,12 @@ pub async fn search_shortcut(
 /// It will call the `Shortcut_Service` function `create` and return the result.
 pub async fn post_shortcut(
   Extension(app): Extension<AppState>,
+  Extension(base_path): Extension<BasePath>,
   Form(params): Form<PostRequest>,
-) -> Result<Html<String>, ()> {
+) -> Result<Html<String>, TemplateError> {
   debug!("{}: {}", params.keyword, params.url);
 
-  Ok(app.shortcut_service.create(&params).await)
+  app.shortcut_service.create(&params, &base_path.0).await
 }
 
 /// This is the function for the `/api/get` endpoint.
@@ -56,33 +85,217 @@ pub async fn post_shortcut(
 /// It will call the `Shortcut_Service` function `get` and return the result.
 pub async fn redirect_shortcut(
   Extension(app): Extension<AppState>,
+  Extension(base_path): Extension<BasePath>,
+  headers: HeaderMap,
   Form(params): Form<KeywordRequest>,
 ) -> Result<Redirect, ()> {
   debug!("{:?}", params.keyword);
 
-  Ok(app.shortcut_service.get(&params.keyword.to_lowercase()).await)
+  let settings: Settings = app.settings.borrow().clone();
+  let ui_url: String = public_ui_url(&app.config, &headers, &base_path);
+
+  Ok(app.shortcut_service.get(&params.keyword.to_lowercase(), &ui_url, &base_path.0, &settings).await)
+}
+
+/// This is the function for the `/api/shortcut` endpoint.
+/// 
+/// It will call the `Shortcut_Service` function `find` and return the shortcut as JSON, with its version as the `ETag`.
+pub async fn get_shortcut(
+  Extension(app): Extension<AppState>,
+  Query(params): Query<KeywordRequest>,
+) -> Result<impl IntoResponse, ShortcutError> {
+  debug!("{:?}", params.keyword);
+
+  let shortcut = app.shortcut_service.find(&params.keyword.to_lowercase()).await?;
+  let etag = format!("\"{}\"", shortcut.version.ok_or(ShortcutError::FailedToGet)?);
+
+  Ok(([(ETAG, etag)], Json(shortcut)))
 }
 
 /// This is the function for the `/api/update` endpoint.
 /// 
 /// It will call the `Shortcut_Service` function `update` and return the result.
+/// 
+/// The expected version is taken from the `If-Match` header, falling back to the `version` form field.
 pub async fn update_shortcut(
   Extension(app): Extension<AppState>,
-  Form(params): Form<PostRequest>,
-) -> Result<Html<String>, ()> {
+  Extension(base_path): Extension<BasePath>,
+  Extension(htmx): Extension<bool>,
+  headers: HeaderMap,
+  Form(mut params): Form<PostRequest>,
+) -> Result<(StatusCode, Html<String>), TemplateError> {
   debug!("{:?}", params.keyword);
 
-  Ok(app.shortcut_service.update(&params).await)
+  params.version = if_match_version(&headers).or(params.version);
+
+  app.shortcut_service.update(&params, &base_path.0).await.map(|response| htmx_status(htmx, response))
 }
 
 /// This is the function for the `/api/delete` endpoint.
 /// 
 /// It will call the `Shortcut_Service` function `delete` and return the result.
+/// 
+/// The expected version is taken from the `If-Match` header, falling back to the `version` form field.
 pub async fn delete_shortcut(
   Extension(app): Extension<AppState>,
+  Extension(base_path): Extension<BasePath>,
+  Extension(htmx): Extension<bool>,
+  headers: HeaderMap,
   Form(params): Form<KeywordRequest>,
-) -> Result<Html<String>, ()> {
+) -> Result<(StatusCode, Html<String>), TemplateError> {
   debug!("{:?}", params.keyword);
 
-  Ok(app.shortcut_service.delete(params.keyword.as_str()).await)
+  let version = if_match_version(&headers).or(params.version);
+
+  app.shortcut_service.delete(params.keyword.as_str(), version, &base_path.0).await.map(|response| htmx_status(htmx, response))
+}
+
+/// This is the function for the `/api/bulk` endpoint.
+/// 
+/// It will call the `Shortcut_Service` function `bulk` and return the per-item report as JSON.
+pub async fn bulk_shortcuts(
+  Extension(app): Extension<AppState>,
+  Json(params): Json<BulkRequest>,
+) -> Result<Json<BulkReport>, ShortcutError> {
+  debug!("bulk request of {} operations ({:?})", params.operations.len(), params.mode);
+
+  app.shortcut_service.bulk(&params).await.map(Json)
 }
+
+/// This is the function for the `/api/bulk/replace-url` endpoint.
+/// 
+/// It will call the `Shortcut_Service` function `replace_urls` and return the planned or applied replacements as JSON.
+pub async fn replace_shortcut_urls(
+  Extension(app): Extension<AppState>,
+  Json(params): Json<ReplaceUrlRequest>,
+) -> Result<Json<ReplaceUrlReport>, ShortcutError> {
+  debug!("replace {:?} with {:?} (dry run: {})", params.find, params.replace, params.dry_run);
+
+  app.shortcut_service.replace_urls(&params).await.map(Json)
+}
+
+/// This is the function for the `/api/export` endpoint.
+/// 
+/// It will call the `Shortcut_Service` function `export` and stream the result back as a file download.
+pub async fn export_shortcuts(
+  Extension(app): Extension<AppState>,
+  Query(params): Query<ExportRequest>,
+) -> impl IntoResponse {
+  debug!("export as {:?}", params.format);
+
+  let headers = [
+    (CONTENT_TYPE, params.format.content_type().to_owned()),
+    (CONTENT_DISPOSITION, format!("attachment; filename=\"shortcuts.{}\"", params.format.extension())),
+  ];
+
+  (headers, Body::from_stream(app.shortcut_service.export(params.format)))
+}
+
+/// The fields of the multipart upload sent to the `/api/import/preview` endpoint.
+struct ImportUpload {
+  contents: Vec<u8>,
+  format: ImportFormat,
+  policy: ConflictPolicy,
+}
+
+/// Reads the `file` field, and the optional `format` and `policy` fields, of an import upload.
+/// 
+/// Without a `format` field the format is guessed from the name of the uploaded file, falling back to JSON.
+async fn read_import_upload(mut multipart: Multipart) -> Result<ImportUpload, ShortcutError> {
+  let mut contents: Option<Vec<u8>> = None;
+  let mut format: Option<ImportFormat> = None;
+  let mut file_format: Option<ImportFormat> = None;
+  let mut policy: ConflictPolicy = ConflictPolicy::default();
+
+  while let Some(field) = multipart.next_field().await.map_err(|err| {
+    error!("Failed to read import upload: {}", err);
+    ShortcutError::InvalidImport
+  })? {
+    let name: String = field.name().unwrap_or_default().to_owned();
+    if name == "file" {
+      file_format = field.file_name().and_then(ImportFormat::from_file_name);
+    }
+
+    let bytes = field.bytes().await.map_err(|err| {
+      error!("Failed to read import upload field ({}): {}", name, err);
+      ShortcutError::InvalidImport
+    })?;
+    let value = || serde_json::Value::from(String::from_utf8_lossy(&bytes).into_owned());
+
+    match name.as_str() {
+      "file" => contents = Some(bytes.to_vec()),
+      "format" if !bytes.is_empty() => format = Some(serde_json::from_value(value()).map_err(|_| ShortcutError::InvalidImport)?),
+      "policy" if !bytes.is_empty() => policy = serde_json::from_value(value()).map_err(|_| ShortcutError::InvalidImport)?,
+      _ => {},
+    }
+  }
+
+  Ok(ImportUpload { 
+    contents: contents.ok_or(ShortcutError::InvalidImport)?, 
+    format: format.or(file_format).unwrap_or(ImportFormat::Json), 
+    policy,
+  })
+}
+
+/// This is the function for the `/api/import/preview` endpoint.
+/// 
+/// It will read the uploaded file and call the `Shortcut_Service` function `preview_import` and return the result.
+pub async fn preview_import_shortcuts(
+  Extension(app): Extension<AppState>,
+  Extension(base_path): Extension<BasePath>,
+  Extension(htmx): Extension<bool>,
+  multipart: Multipart,
+) -> Result<(StatusCode, Html<String>), TemplateError> {
+  match read_import_upload(multipart).await {
+    Ok(upload) => {
+      debug!("preview import as {:?} ({:?})", upload.format, upload.policy);
+
+      Ok((StatusCode::OK, app.shortcut_service.preview_import(&upload.contents, upload.format, upload.policy, &base_path.0).await?))
+    },
+    Err(err) => {
+      let tera = TERA.load();
+      let context: ErrorAlert = ErrorAlert { error: err.to_string(), successful: false };
+
+      Ok(htmx_status(htmx, (err.status_code(), context.get_html(&tera, &base_path.0)?)))
+    }
+  }
+}
+
+/// This is the function for the `/api/import` endpoint.
+/// 
+/// It will call the `Shortcut_Service` function `import` with the confirmed preview and return the summary.
+pub async fn import_shortcuts(
+  Extension(app): Extension<AppState>,
+  Extension(base_path): Extension<BasePath>,
+  Form(params): Form<ImportRequest>,
+) -> Result<Html<String>, TemplateError> {
+  debug!("import ({:?})", params.policy);
+
+  app.shortcut_service.import(&params, &base_path.0).await
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod shortcut_routes_tests {
+  mod if_match_version_tests {
+    use axum::http::{header::IF_MATCH, HeaderMap, HeaderValue};
+
+    use crate::routes::shortcut::if_match_version;
+
+    fn headers(if_match: &str) -> HeaderMap {
+      HeaderMap::from_iter([(IF_MATCH, HeaderValue::from_str(if_match).unwrap())])
+    }
+
+    #[test]
+    fn returns_version_of_strong_etag() {
+      assert_eq!(if_match_version(&headers("\"3\"")), Some(3));
+    }
+
+    #[test]
+    fn returns_none_when_etag_weak_or_unquoted() {
+      assert_eq!(if_match_version(&headers("W/\"3\"")), None);
+      assert_eq!(if_match_version(&headers("3")), None);
+      assert_eq!(if_match_version(&HeaderMap::new()), None);
+    }
+  }
+}
\ No newline at end of file
@@ -1,29 +1,495 @@
-#[allow(unused_imports)]
-use crate::models::shortcut::Shortcut;
+use crate::{backup::create_backup, error::MigrationError, models::migration::{MigrationState, MigrationStatus}, repository::memory::MemoryStore};
 
-use std::{env, str::FromStr};
-use sqlx::{sqlite::{SqlitePool, SqliteConnectOptions}, Pool, Sqlite};
-use tracing::info;
+use std::{fs, path::{Path, PathBuf}, str::FromStr, time::Duration};
+use sqlx::{
+  migrate::{Migrate, Migration, Migrator},
+  sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+  Pool, Sqlite,
+};
+#[cfg(feature = "postgres")]
+use sqlx::{postgres::{PgConnectOptions, PgPoolOptions}, Postgres};
+use tracing::{info, warn};
 
-pub async fn connect_db() -> Pool<Sqlite> {
-  let database_url = env::var("DATABASE_URL").expect("DATABASE_FILENAME not set");
-  let options = SqliteConnectOptions::from_str(&database_url).unwrap();
+/// The migrations embedded in the binary, used to migrate the database and to check backups before they're restored.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
-  let pool: SqlitePool = SqlitePool::connect_with(options).await.expect("DB connection failed");
+/// The PostgreSQL migrations, kept at the same versions as `MIGRATOR`.
+#[cfg(feature = "postgres")]
+pub static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// The `database_url` of an ephemeral server, which keeps everything in memory, see `MemoryStore`.
+pub const MEMORY_URL: &str = "memory:";
+
+/// How the connection pool is opened, see `Config::pool`.
+///
+/// The journal mode, synchronous level, busy timeout and foreign keys are SQLite pragmas, set on every connection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolConfig {
+  pub journal_mode: SqliteJournalMode,
+  pub synchronous: SqliteSynchronous,
+  /// How long a write waits for another connection's write to finish, before failing with `SQLITE_BUSY`.
+  pub busy_timeout: Duration,
+  pub foreign_keys: bool,
+  pub min_connections: u32,
+  pub max_connections: u32,
+  /// How many prepared statements each connection keeps.
+  pub statement_cache_capacity: usize,
+}
+
+impl Default for PoolConfig {
+  fn default() -> Self {
+    Self {
+      // Readers don't block the writer (or each other) in WAL mode, and `Normal` is still durable with it.
+      journal_mode: SqliteJournalMode::Wal,
+      synchronous: SqliteSynchronous::Normal,
+      busy_timeout: Duration::from_secs(5),
+      foreign_keys: true,
+      min_connections: 1,
+      max_connections: 10,
+      statement_cache_capacity: 100,
+    }
+  }
+}
+
+/// The database the data is stored in, picked by the scheme of the `database_url`.
+///
+/// Code that's the same for every backend is written once with the `dispatch!` macro.
+#[derive(Clone)]
+pub enum Database {
+  Sqlite(Pool<Sqlite>),
+  #[cfg(feature = "postgres")]
+  Postgres(Pool<Postgres>),
+  /// Nothing is written anywhere, so there's nothing to migrate or back up.
+  Memory(MemoryStore),
+}
+
+impl Database {
+  /// The migrations for the backend, `None` for `Database::Memory`.
+  pub fn migrator(&self) -> Option<&'static Migrator> {
+    match self {
+      Database::Sqlite(_) => Some(&MIGRATOR),
+      #[cfg(feature = "postgres")]
+      Database::Postgres(_) => Some(&POSTGRES_MIGRATOR),
+      Database::Memory(_) => None,
+    }
+  }
+
+  /// Closes every connection, waiting for the ones in use to be returned, so SQLite can checkpoint its WAL.
+  pub async fn close(&self) {
+    dispatch!(self, pool => pool.close().await, _ => ())
+  }
+
+  /// The SQLite pool, for what only SQLite supports e.g. backups.
+  pub fn sqlite(&self) -> Option<&Pool<Sqlite>> {
+    match self {
+      Database::Sqlite(pool) => Some(pool),
+      _ => None,
+    }
+  }
+}
+
+/// Whether the url is for a PostgreSQL database, every url other than it and `MEMORY_URL` is opened as SQLite.
+pub fn is_postgres(database_url: &str) -> bool {
+  database_url.starts_with("postgres://") || database_url.starts_with("postgresql://")
+}
+
+/// Checks the url can be opened by this build, without connecting to it.
+///
+/// # Parameters
+/// - `database_url`: The database to check.
+///
+/// # Returns
+/// - `Result<(), String>`, will be why the url can't be used, e.g. a PostgreSQL url when built without the `postgres` feature.
+pub fn check_url(database_url: &str) -> Result<(), String> {
+  if database_url == MEMORY_URL {
+    return Ok(());
+  }
+
+  match is_postgres(database_url) {
+    #[cfg(feature = "postgres")]
+    true => PgConnectOptions::from_str(database_url).map(|_| ()).map_err(|err| err.to_string()),
+    #[cfg(not(feature = "postgres"))]
+    true => Err("PostgreSQL needs shortcut to be built with the `postgres` feature".to_owned()),
+    false => SqliteConnectOptions::from_str(database_url).map(|_| ()).map_err(|err| err.to_string()),
+  }
+}
+
+/// The migrations that are applied, leaving out the `.down.sql` halves of the reversible ones.
+pub fn up_migrations(migrator: &'static Migrator) -> impl Iterator<Item = &'static Migration> {
+  migrator.iter().filter(|migration| !migration.migration_type.is_down_migration())
+}
+
+/// Opens the database without migrating it, as the `migrate` command does.
+///
+/// # Parameters
+/// - `database_url`: The database to open.
+/// - `pool`: How to open the connection pool, the pragmas are only for SQLite.
+///
+/// # Returns
+/// - `Result<Database, MigrationError>`, will be the database, or `MigrationError::Unavailable` if it can't be opened.
+pub async fn connect(database_url: &str, pool: &PoolConfig) -> Result<Database, MigrationError> {
+  let unavailable = |err: sqlx::Error| MigrationError::Unavailable(err.to_string());
+  check_url(database_url).map_err(MigrationError::Unavailable)?;
+
+  if database_url == MEMORY_URL {
+    info!("Keeping everything in memory, it'll be lost when the server stops.");
+    return Ok(Database::Memory(MemoryStore::default()));
+  }
+
+  #[cfg(feature = "postgres")]
+  if is_postgres(database_url) {
+    let options = PgConnectOptions::from_str(database_url).map_err(unavailable)?
+      .statement_cache_capacity(pool.statement_cache_capacity);
+
+    let pool: Pool<Postgres> = PgPoolOptions::new()
+      .min_connections(pool.min_connections)
+      .max_connections(pool.max_connections)
+      .connect_with(options)
+      .await
+      .map_err(unavailable)?;
+    info!("Database connection made.");
+
+    return Ok(Database::Postgres(pool));
+  }
+
+  let options = SqliteConnectOptions::from_str(database_url).map_err(unavailable)?
+    .journal_mode(pool.journal_mode)
+    .synchronous(pool.synchronous)
+    .busy_timeout(pool.busy_timeout)
+    .foreign_keys(pool.foreign_keys)
+    .statement_cache_capacity(pool.statement_cache_capacity);
+
+  // SQLite creates a missing database but not its directory, e.g. the platform data directory of the default `database_url`.
+  if let Some(directory) = options.get_filename().parent().filter(|directory| !directory.as_os_str().is_empty() && !directory.exists()) {
+    fs::create_dir_all(directory).map_err(|err| MigrationError::Unavailable(err.to_string()))?;
+  }
+
+  let pool: Pool<Sqlite> = SqlitePoolOptions::new()
+    .min_connections(pool.min_connections)
+    .max_connections(pool.max_connections)
+    .connect_with(options)
+    .await
+    .map_err(unavailable)?;
   info!("Database connection made.");
 
-  let _ = sqlx::migrate!("./migrations").run(&pool).await; 
+  Ok(Database::Sqlite(pool))
+}
+
+/// Opens the database and applies any pending migrations, see `migrate_up`.
+///
+/// # Parameters
+/// - `database_url`: The database to open.
+/// - `pool`: How to open the connection pool, see `connect`.
+/// - `backup_dir`: Where to back up the database before it's migrated, beside the database when not given.
+///
+/// # Returns
+/// - `Result<Database, MigrationError>`, will be the migrated database, or the `MigrationError` that stops the server starting on a broken schema.
+pub async fn connect_db(database_url: &str, pool: &PoolConfig, backup_dir: Option<&Path>) -> Result<Database, MigrationError> {
+  let database: Database = connect(database_url, pool).await?;
+
+  let applied: Vec<i64> = migrate_up(&database, backup_dir).await?;
+  if !applied.is_empty() {
+    info!("Applied migrations {:?}.", applied);
+  }
   info!("Migrations complete!");
 
-  // let google: Shortcut = Shortcut::new("google".to_owned(), "https://google.co.uk".to_owned());
+  Ok(database)
+}
+
+/// Lists every migration embedded in the binary or applied to the database, and where each is up to.
+///
+/// # Parameters
+/// - `database`: The database to check.
+///
+/// # Returns
+/// - `Result<Vec<MigrationStatus>, MigrationError>`, will be the migrations ordered by version, or `MigrationError::FailedToRead`.
+pub async fn migration_status(database: &Database) -> Result<Vec<MigrationStatus>, MigrationError> {
+  let failed = |err: sqlx::Error| MigrationError::FailedToRead(err.to_string());
+  let Some(migrator) = database.migrator() else {
+    return Ok(vec![]);
+  };
+
+  let applied: Vec<(i64, String, bool, String)> = dispatch!(database, pool => {
+    let mut connection = pool.acquire().await.map_err(failed)?;
+    connection.ensure_migrations_table().await.map_err(|err| MigrationError::FailedToRead(err.to_string()))?;
+
+    sqlx::query_as("SELECT version, description, success, CAST(installed_on AS TEXT) FROM _sqlx_migrations")
+      .fetch_all(&mut *connection)
+      .await
+      .map_err(failed)?
+  }, _ => vec![]);
+
+  let mut statuses: Vec<MigrationStatus> = up_migrations(migrator)
+    .map(|migration| {
+      let found = applied.iter().find(|(version, ..)| *version == migration.version);
+
+      MigrationStatus {
+        version: migration.version,
+        description: migration.description.to_string(),
+        state: match found {
+          Some((_, _, true, _)) => MigrationState::Applied,
+          Some((_, _, false, _)) => MigrationState::Failed,
+          None => MigrationState::Pending,
+        },
+        installed_on: found.map(|(.., installed_on)| installed_on.clone()),
+      }
+    })
+    .collect();
+
+  statuses.extend(applied.iter()
+    .filter(|(version, ..)| !migrator.version_exists(*version))
+    .map(|(version, description, _, installed_on)| MigrationStatus {
+      version: *version,
+      description: description.clone(),
+      state: MigrationState::Unknown,
+      installed_on: Some(installed_on.clone()),
+    }));
+  statuses.sort_by_key(|status| status.version);
+
+  Ok(statuses)
+}
+
+/// Backs up a database that already has a schema, before it's changed, so it can be put back with `shortcut restore`.
+///
+/// A new or in-memory database has nothing worth keeping, so isn't backed up,
+/// and a PostgreSQL database is left to its own backups e.g. `pg_dump`.
+async fn backup_before_migrating(database: &Database, statuses: &[MigrationStatus], backup_dir: Option<&Path>) -> Result<(), MigrationError> {
+  let has_schema: bool = statuses.iter().any(|status| status.state != MigrationState::Pending);
+
+  let Some(pool) = database.sqlite() else {
+    if has_schema {
+      warn!("Migrating the PostgreSQL database without a backup, use pg_dump to keep one.");
+    }
+    return Ok(());
+  };
+  let database: PathBuf = pool.connect_options().get_filename().to_path_buf();
+
+  if !has_schema || !database.is_file() {
+    return Ok(());
+  }
+
+  let directory: PathBuf = match backup_dir {
+    Some(directory) => directory.to_path_buf(),
+    None => database.parent().unwrap_or(Path::new(".")).to_path_buf(),
+  };
+  let path: PathBuf = create_backup(pool, &directory).await.map_err(|err| MigrationError::FailedToBackup(err.to_string()))?;
+  info!("Backed up database to {} before migrating.", path.display());
+
+  Ok(())
+}
+
+/// Applies the pending migrations, backing up the database first if there are any.
+///
+/// Every applied migration is checked against the embedded ones, so a database migrated by a different build,
+/// or left part way through a migration, is an error rather than something to carry on with.
+///
+/// # Parameters
+/// - `database`: The database to migrate.
+/// - `backup_dir`: Where to back up the database, beside the database when not given.
+///
+/// # Returns
+/// - `Result<Vec<i64>, MigrationError>`, will be the versions applied, or the `MigrationError` explaining why the database couldn't be migrated.
+pub async fn migrate_up(database: &Database, backup_dir: Option<&Path>) -> Result<Vec<i64>, MigrationError> {
+  let statuses: Vec<MigrationStatus> = migration_status(database).await?;
+  let pending: Vec<i64> = statuses.iter().filter(|status| status.state == MigrationState::Pending).map(|status| status.version).collect();
+
+  if !pending.is_empty() {
+    backup_before_migrating(database, &statuses, backup_dir).await?;
+  }
+
+  if let Some(migrator) = database.migrator() {
+    dispatch!(database, pool => migrator.run(pool).await, _ => Ok(())).map_err(|err| MigrationError::FailedToMigrate(err.to_string()))?;
+  }
+
+  Ok(pending)
+}
+
+/// Reverts the applied migrations after `target`, newest first, backing up the database first.
+///
+/// # Parameters
+/// - `database`: The database to migrate.
+/// - `backup_dir`: Where to back up the database, beside the database when not given.
+/// - `target`: The version to go back to, `0` reverting every migration, and the one before the latest when not given.
+///
+/// # Returns
+/// - `Result<Vec<i64>, MigrationError>`, will be the versions reverted, newest first, or the `MigrationError` explaining why they couldn't be.
+pub async fn migrate_down(database: &Database, backup_dir: Option<&Path>, target: Option<i64>) -> Result<Vec<i64>, MigrationError> {
+  let statuses: Vec<MigrationStatus> = migration_status(database).await?;
+  let applied: Vec<i64> = statuses.iter().filter(|status| status.state == MigrationState::Applied).map(|status| status.version).collect();
+
+  let target: i64 = target.unwrap_or_else(|| applied.iter().rev().nth(1).copied().unwrap_or(0));
+  let reverted: Vec<i64> = applied.into_iter().rev().filter(|version| *version > target).collect();
+
+  if reverted.is_empty() {
+    return Ok(reverted);
+  }
+
+  backup_before_migrating(database, &statuses, backup_dir).await?;
+  if let Some(migrator) = database.migrator() {
+    dispatch!(database, pool => migrator.undo(pool, target).await, _ => Ok(())).map_err(|err| MigrationError::FailedToMigrate(err.to_string()))?;
+  }
+
+  Ok(reverted)
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod schema_tests {
+  use sqlx::{Pool, Sqlite, SqlitePool};
+  use std::{fs, path::{Path, PathBuf}};
+
+  use crate::schema::Database;
+
+  /// An empty directory of its own for each test, with a database in it that hasn't been migrated.
+  async fn test_database(name: &str) -> (Database, Pool<Sqlite>, PathBuf) {
+    let directory: PathBuf = std::env::temp_dir().join(format!("shortcut-schema-tests-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    let pool = SqlitePool::connect(&format!("sqlite://{}?mode=rwc", directory.join("shortcut.db").display())).await.unwrap();
+
+    (Database::Sqlite(pool.clone()), pool, directory)
+  }
+
+  /// The backups written into the directory.
+  fn backups(directory: &Path) -> usize {
+    crate::backup::list_backups(&directory.join("backups")).unwrap().len()
+  }
+
+  mod connect_tests {
+    use futures::future::join_all;
+    use sqlx::Row;
+
+    use crate::{
+      models::shortcut::Shortcut,
+      repository::shortcut::{ShortcutRepository, ShortcutRepositoryTrait},
+      schema::{connect_db, PoolConfig},
+    };
+
+    use super::test_database;
+
+    #[tokio::test]
+    async fn sets_pragmas_on_every_connection() {
+      let (_, _, directory) = test_database("connect-pragmas").await;
+      let url = format!("sqlite://{}?mode=rwc", directory.join("shortcut.db").display());
+
+      let database = connect_db(&url, &PoolConfig::default(), None).await.unwrap();
+      let pool = database.sqlite().unwrap();
+
+      let journal_mode: String = sqlx::query("PRAGMA journal_mode").fetch_one(pool).await.unwrap().get(0);
+      let busy_timeout: i64 = sqlx::query("PRAGMA busy_timeout").fetch_one(pool).await.unwrap().get(0);
+      let foreign_keys: i64 = sqlx::query("PRAGMA foreign_keys").fetch_one(pool).await.unwrap().get(0);
+      assert_eq!((journal_mode.as_str(), busy_timeout, foreign_keys), ("wal", 5000, 1));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn creates_updates_and_gets_concurrently_without_busy_errors() {
+      let (_, _, directory) = test_database("connect-stress").await;
+      let url = format!("sqlite://{}?mode=rwc", directory.join("shortcut.db").display());
+      let repository = ShortcutRepository::new(connect_db(&url, &PoolConfig::default(), None).await.unwrap());
+
+      // Each task writes its own keyword over and over, while reading back every other task's
+      let tasks = (0..16).map(|task| {
+        let repository = repository.clone();
+        tokio::spawn(async move {
+          let keyword = format!("k{}", task);
+          let shortcut = |round: i64| Shortcut {
+            created: Some(round),
+            updated: Some(round),
+            ..Shortcut::new(keyword.clone(), format!("https://example.com/{}/{}", task, round))
+          };
+
+          repository.create(&shortcut(0)).await.unwrap();
+          for round in 1..=25 {
+            repository.update(&shortcut(round)).await.unwrap();
+            repository.get(&format!("k{}", (task + round) % 16)).await.ok();
+          }
+        })
+      });
+      for result in join_all(tasks).await {
+        result.unwrap();
+      }
+
+      for task in 0..16 {
+        let result = repository.get(&format!("k{}", task)).await.unwrap();
+        assert_eq!((result.url, result.version), (format!("https://example.com/{}/25", task), Some(26)));
+      }
+    }
+  }
+
+  mod migrate_up_tests {
+    use crate::{models::migration::MigrationState, schema::{migrate_up, migration_status, up_migrations, MIGRATOR}};
+
+    use super::{backups, test_database};
+
+    #[tokio::test]
+    async fn returns_every_version_without_backup_when_database_new() {
+      let (database, _, directory) = test_database("up-new").await;
+
+      let result = migrate_up(&database, Some(&directory.join("backups"))).await.unwrap();
+
+      assert_eq!(result, up_migrations(&MIGRATOR).map(|migration| migration.version).collect::<Vec<i64>>());
+      assert!(migration_status(&database).await.unwrap().iter().all(|status| status.state == MigrationState::Applied));
+      assert_eq!(backups(&directory), 0);
+    }
+
+    #[tokio::test]
+    async fn returns_pending_versions_after_backing_up() {
+      let (database, pool, directory) = test_database("up-pending").await;
+      // As a database last migrated by an older version of shortcut would be.
+      migrate_up(&database, None).await.unwrap();
+      sqlx::query("DELETE FROM _sqlx_migrations WHERE version = 4").execute(&pool).await.unwrap();
+      sqlx::query("DROP TABLE settings").execute(&pool).await.unwrap();
+
+      let result = migrate_up(&database, Some(&directory.join("backups"))).await.unwrap();
+
+      assert_eq!(result, vec![4]);
+      assert_eq!(backups(&directory), 1);
+    }
+
+    #[tokio::test]
+    async fn returns_exception_when_applied_migration_unknown() {
+      let (database, pool, _) = test_database("up-unknown").await;
+      migrate_up(&database, None).await.unwrap();
+      sqlx::query("INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES (999, 'newer', 1, x'00', 0)")
+        .execute(&pool).await.unwrap();
+
+      let status = migration_status(&database).await.unwrap();
+
+      assert_eq!(status.last().unwrap().state, MigrationState::Unknown);
+      assert!(migrate_up(&database, None).await.is_err());
+    }
+  }
+
+  mod migrate_down_tests {
+    use crate::{models::migration::MigrationState, schema::{migrate_down, migrate_up, migration_status}};
+
+    use super::{backups, test_database};
+
+    #[tokio::test]
+    async fn returns_latest_version_when_no_target() {
+      let (database, _, directory) = test_database("down-latest").await;
+      migrate_up(&database, None).await.unwrap();
+
+      let result = migrate_down(&database, Some(&directory.join("backups")), None).await.unwrap();
+      let status = migration_status(&database).await.unwrap();
+
+      assert_eq!(result, vec![4]);
+      assert_eq!(status.last().unwrap().state, MigrationState::Pending);
+      assert_eq!(backups(&directory), 1);
+    }
+
+    #[tokio::test]
+    async fn returns_every_version_when_reverted_to_zero_and_up_again() {
+      let (database, pool, directory) = test_database("down-all").await;
+      migrate_up(&database, None).await.unwrap();
+      sqlx::query("INSERT INTO shortcut (created, updated, keyword, url) VALUES (1, 1, 'google', 'https://google.co.uk')").execute(&pool).await.unwrap();
 
-  // sqlx::query("INSERT OR IGNORE INTO shortcut (created, updated, keyword, url) VALUES (?1, ?2, ?3, ?4)")
-  // .bind(google.created)
-  // .bind(google.updated)
-  // .bind(google.keyword)
-  // .bind(google.url)
-  // .execute(&pool).await.expect("DB new entry failed");
-  // info!("Successfully added DB entry.");
+      let result = migrate_down(&database, Some(&directory.join("backups")), Some(0)).await.unwrap();
 
-  pool
+      assert_eq!(result, vec![4, 3, 2, 1]);
+      assert!(migration_status(&database).await.unwrap().iter().all(|status| status.state == MigrationState::Pending));
+      assert_eq!(migrate_up(&database, None).await.unwrap(), vec![1, 2, 3, 4]);
+    }
+  }
 }
\ No newline at end of file
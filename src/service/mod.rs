@@ -1 +1,2 @@
-pub mod shortcut;
+pub mod settings;
+pub mod shortcut;
\ No newline at end of file
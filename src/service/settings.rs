@@ -0,0 +1,137 @@
+use crate::{
+  error::{SettingsError, TemplateError},
+  macros::renderable::Renderable,
+  models::settings::{Settings, REDIRECT_STATUSES},
+  repository::settings::SettingsRepositoryTrait,
+  templates::components::SettingsTemplate,
+  TERA,
+};
+
+use axum::response::Html;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Keeps the `Settings` in the db and publishes every change, so they take effect without a restart.
+#[derive(Clone)]
+pub struct SettingsService<R: SettingsRepositoryTrait + Send + Sync> {
+  repository: R,
+  sender: Arc<watch::Sender<Settings>>,
+}
+
+impl <R: SettingsRepositoryTrait + Send + Sync> SettingsService<R> {
+  /// Creates a new instance of `SettingsService`, publishing the `Settings` it was loaded with.
+  pub fn new(repository: R, settings: Settings) -> Self {
+    Self {
+      repository,
+      sender: Arc::new(watch::channel(settings).0),
+    }
+  }
+
+  /// Subscribes to the current `Settings`, which change whenever they're saved.
+  pub fn subscribe(&self) -> watch::Receiver<Settings> {
+    self.sender.subscribe()
+  }
+
+  /// Renders the settings form.
+  fn render(&self, settings: Settings, message: Option<String>, error: Option<String>, base_path: &str) -> Result<Html<String>, TemplateError> {
+    let tera = TERA.load();
+    let context: SettingsTemplate = SettingsTemplate { settings, statuses: REDIRECT_STATUSES.to_vec(), message, error };
+
+    context.get_html(&tera, base_path)
+  }
+
+  /// Renders the settings form with the current `Settings`.
+  ///
+  /// # Parameters
+  /// - `base_path`: The path the UI is served under for the request, see `BasePath`.
+  ///
+  /// # Returns
+  /// - `Result<Html<String>, TemplateError>` of the settings form.
+  pub fn get(&self, base_path: &str) -> Result<Html<String>, TemplateError> {
+    self.render(self.sender.borrow().clone(), None, None, base_path)
+  }
+
+  /// Validates and saves the `Settings`, then publishes them.
+  ///
+  /// # Parameters
+  /// - `settings`: The new `Settings`.
+  /// - `base_path`: The path the UI is served under for the request, see `BasePath`.
+  ///
+  /// # Returns
+  /// - `Result<Html<String>, TemplateError>` of the settings form, saying whether they were saved.
+  pub async fn update(&self, settings: Settings, base_path: &str) -> Result<Html<String>, TemplateError> {
+    match self.save(settings.clone()).await {
+      Ok(()) => self.render(settings, Some("Settings saved.".to_owned()), None, base_path),
+      Err(err) => self.render(settings, None, Some(err.to_string()), base_path),
+    }
+  }
+
+  /// Validates and saves the `Settings`, only publishing them once they're in the db.
+  ///
+  /// # Parameters
+  /// - `settings`: The new `Settings`.
+  ///
+  /// # Returns
+  /// - `Result<(), SettingsError>`, will be `SettingsError::Invalid` if a setting is out of range, or `SettingsError::FailedToSave`.
+  pub async fn save(&self, settings: Settings) -> Result<(), SettingsError> {
+    settings.validate()?;
+    self.repository.save(&settings).await?;
+    self.sender.send_replace(settings);
+
+    Ok(())
+  }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod settings_service_tests {
+  mod save_tests {
+    use crate::{
+      error::SettingsError,
+      models::settings::{Fallback, Settings},
+      repository::settings::MockSettingsRepositoryTrait,
+      service::settings::SettingsService,
+    };
+
+    #[tokio::test]
+    async fn publishes_settings_when_saved() {
+      let mut mock: MockSettingsRepositoryTrait = MockSettingsRepositoryTrait::default();
+      mock.expect_save().times(1).returning(|_| Ok(()));
+
+      let settings_service = SettingsService::new(mock, Settings::default());
+      let mut receiver = settings_service.subscribe();
+      let settings = Settings { fallback: Fallback::Home, search_min_score: 0.5, ..Settings::default() };
+
+      settings_service.save(settings.clone()).await.unwrap();
+
+      assert!(receiver.has_changed().unwrap());
+      assert_eq!(*receiver.borrow_and_update(), settings);
+    }
+
+    #[tokio::test]
+    async fn returns_exception_and_keeps_settings_when_invalid() {
+      let mut mock: MockSettingsRepositoryTrait = MockSettingsRepositoryTrait::default();
+      mock.expect_save().never();
+
+      let settings_service = SettingsService::new(mock, Settings::default());
+      let receiver = settings_service.subscribe();
+      let result = settings_service.save(Settings { redirect_status: 302, ..Settings::default() }).await;
+
+      assert!(matches!(result, Err(SettingsError::Invalid(_))));
+      assert!(!receiver.has_changed().unwrap());
+    }
+
+    #[tokio::test]
+    async fn returns_exception_and_keeps_settings_when_save_fails() {
+      let mut mock: MockSettingsRepositoryTrait = MockSettingsRepositoryTrait::default();
+      mock.expect_save().returning(|_| Err(SettingsError::FailedToSave));
+
+      let settings_service = SettingsService::new(mock, Settings::default());
+      let receiver = settings_service.subscribe();
+      let result = settings_service.save(Settings { analytics: true, ..Settings::default() }).await;
+
+      assert!(matches!(result, Err(SettingsError::FailedToSave)));
+      assert_eq!(*receiver.borrow(), Settings::default());
+    }
+  }
+}
\ No newline at end of file
@@ -1,14 +1,66 @@
 use crate::{
-  error::ShortcutError, 
+  error::{ShortcutError, TemplateError}, 
   macros::renderable::Renderable,
-  models::shortcut::{PostRequest, Shortcut}, 
+  metrics::METRICS,
+  models::{
+    bulk::{BulkMode, BulkOperation, BulkReport, BulkRequest, ReplaceUrlReport, ReplaceUrlRequest, UrlReplacement},
+    settings::{Fallback, Settings},
+    shortcut::{expand_query, PostRequest, Shortcut},
+    sync::SyncPlan,
+    transfer::{ConflictPolicy, FileFormat, ImportFormat, ImportRequest, ImportSummary},
+  }, 
   repository::shortcut::ShortcutRepositoryTrait, 
-  templates::components::{CheckUpdateTemplate, CreateNewTemplate, ErrorDialog, ErrorAlert, InfoDialog, InfoAlert, SearchResultsTemplate, SuccessDialog, SuccessAlert}, 
+  templates::components::{
+    CheckUpdateTemplate, CreateNewTemplate, ErrorDialog, ErrorAlert, ImportPreviewTemplate, ImportSummaryTemplate, InfoDialog, InfoAlert, 
+    SearchResultsTemplate, SuccessDialog, SuccessAlert,
+  }, 
+  utils::{format_util::{export_stream, parse_import}, sync_util::plan_sync, system_util::get_timestamp},
   TERA
 };
 
-use axum::response::{Html, Redirect, Result};
-use tera::Tera;
+use axum::{http::StatusCode, response::{Html, Redirect, Result}};
+use futures::{Stream, TryStreamExt};
+use std::{collections::BTreeMap, time::Instant};
+use tracing::error;
+use url::form_urlencoded::byte_serialize;
+
+/// Prepares imported `Shortcut`'s to be created, the same way `Shortcut::from_request` does for a single one.
+/// 
+/// Keywords are lowercased, ids and versions are dropped and missing timestamps default to now. 
+/// A row without a keyword or url makes the whole import invalid.
+fn normalise_import(shortcuts: Vec<Shortcut>) -> Result<Vec<Shortcut>, ShortcutError> {
+  let now: i64 = get_timestamp();
+
+  shortcuts.into_iter()
+    .map(|shortcut| {
+      let keyword: String = shortcut.keyword.trim().to_lowercase();
+      let url: String = shortcut.url.trim().to_owned();
+
+      if keyword.is_empty() || url.is_empty() {
+        return Err(ShortcutError::InvalidImport);
+      }
+
+      Ok(Shortcut { 
+        id: None, 
+        created: shortcut.created.or(Some(now)), 
+        updated: shortcut.updated.or(Some(now)), 
+        keyword, 
+        url, 
+        version: None,
+        managed: false,
+      })
+    })
+    .collect()
+}
+
+/// A redirect with one of the `REDIRECT_STATUSES`, falling back to `303 See Other`.
+fn redirect_with_status(status: u16, url: &str) -> Redirect {
+  match status {
+    307 => Redirect::temporary(url),
+    308 => Redirect::permanent(url),
+    _ => Redirect::to(url),
+  }
+}
 
 #[derive(Clone)]
 pub struct ShortcutService<R: ShortcutRepositoryTrait + Send + Sync> {
@@ -28,28 +80,32 @@ impl <R: ShortcutRepositoryTrait + Send + Sync> ShortcutService<R> {
   ///
   /// # Parameters
   /// - `keyword`: The search query string.
+  /// - `min_score`: The score (from 0 to 1) a keyword has to beat to be a result.
+  /// - `base_path`: The path the UI is served under for the request, see `BasePath`.
   ///
   /// # Returns
-  /// - `Html<String>` containing matching results or suggestions.
-  pub async fn find_similar(&self, keyword: &str) -> Html<String> {
-    let result: Result<Vec<Shortcut>, ShortcutError> = self.repository.fuzzy_search(keyword).await;
-    let tera:Tera = TERA.read().unwrap().clone();
+  /// - `Result<Html<String>, TemplateError>` containing matching results or suggestions.
+  pub async fn find_similar(&self, keyword: &str, min_score: f32, base_path: &str) -> Result<Html<String>, TemplateError> {
+    let start: Instant = Instant::now();
+    let result: Result<Vec<Shortcut>, ShortcutError> = self.repository.fuzzy_search(keyword, min_score).await;
+    METRICS.search_duration.observe(&[], start.elapsed());
+    let tera = TERA.load();
   
     match result {
       Ok(shortcuts) => {
         let context: SearchResultsTemplate = SearchResultsTemplate { shortcuts, query: keyword.to_owned() };
         
-        context.get_html(tera)
+        context.get_html(&tera, base_path)
       },
       Err(ShortcutError::NoMatches) => {
         let context: CreateNewTemplate = CreateNewTemplate { keyword: keyword.to_owned() };
 
-        context.get_html(tera)
+        context.get_html(&tera, base_path)
       }
       Err(err) => {
         let context: ErrorAlert = ErrorAlert { error:err.to_string(), successful: false };
         
-        context.get_html(tera)
+        context.get_html(&tera, base_path)
       }
     }
   }
@@ -58,57 +114,74 @@ impl <R: ShortcutRepositoryTrait + Send + Sync> ShortcutService<R> {
   ///
   /// # Parameters
   /// - `shortcut`: The data to create the new shortcut.
+  /// - `base_path`: The path the UI is served under for the request, see `BasePath`.
   ///
   /// # Returns
-  /// - `Html<String>` indicating success or failure.
-  pub async fn create(&self, params: &PostRequest) -> Html<String> {
+  /// - `Result<Html<String>, TemplateError>` indicating success or failure.
+  pub async fn create(&self, params: &PostRequest, base_path: &str) -> Result<Html<String>, TemplateError> {
     let new_shortcut: Shortcut = Shortcut::from_request(params);
     let result: Result<bool, ShortcutError> = self.repository.create(&new_shortcut).await;
-    let tera: Tera = TERA.read().unwrap().clone();
+    METRICS.record_write("create", &result);
+    let tera = TERA.load();
   
     match result {
       Ok(_) => {
         let context: SuccessAlert = SuccessAlert { message: "Successfully created shortcut!".into(), successful: true };
   
-        context.get_html(tera)
+        context.get_html(&tera, base_path)
       },
       Err(ShortcutError::UniqueConstraintError) => match self.repository.get(&new_shortcut.keyword).await {
         Ok(shortcut_to_update) => {
           if shortcut_to_update.url == new_shortcut.url {
             let context = InfoAlert { message: "Shortcut already added.".into(), successful: true };
             
-            context.get_html(tera)
+            context.get_html(&tera, base_path)
+          } else if shortcut_to_update.managed {
+            let context: ErrorAlert = ErrorAlert { error:ShortcutError::ReadOnly.to_string(), successful: false };
+            
+            context.get_html(&tera, base_path)
           } else {
             let context: CheckUpdateTemplate = CheckUpdateTemplate { shortcut: shortcut_to_update, new_url: new_shortcut.url, successful: false };
             
-            context.get_html(tera)
+            context.get_html(&tera, base_path)
           }
         },
         Err(err) => {
           let context: ErrorAlert = ErrorAlert { error:err.to_string(), successful: false };
           
-          context.get_html(tera)
+          context.get_html(&tera, base_path)
         }
       },
       Err(err) => {
         let context: ErrorAlert = ErrorAlert { error:err.to_string(), successful: false };
         
-        context.get_html(tera)
+        context.get_html(&tera, base_path)
       }
     }
   }
 
-  /// Updates an existing shortcut.
+  /// Updates an existing shortcut, as long as it hasn't changed since `req.version` was read.
   ///
   /// # Parameters
   /// - `req`: The updated shortcut request.
+  /// - `base_path`: The path the UI is served under for the request, see `BasePath`.
   ///
   /// # Returns
-  /// - `Html<String>` indicating success or failure.
-  pub async fn update(&self, req: &PostRequest) -> Html<String> {
+  /// - `Result<(StatusCode, Html<String>), TemplateError>` indicating success or failure.
+  pub async fn update(&self, req: &PostRequest, base_path: &str) -> Result<(StatusCode, Html<String>), TemplateError> {
+    let tera = TERA.load();
+
+    if req.version.is_none() {
+      let err = ShortcutError::VersionRequired;
+      METRICS.writes.increment(&["update", "conflict"]);
+      let context: ErrorAlert = ErrorAlert { error:err.to_string(), successful: false };
+
+      return Ok((err.status_code(), context.get_html(&tera, base_path)?));
+    }
+
     let shortcut: Shortcut = Shortcut::from_request(req);
     let result: Result<bool, ShortcutError> = self.repository.update(&shortcut).await;
-    let tera:Tera = TERA.read().unwrap().clone();
+    METRICS.record_write("update", &result);
   
     match result {
       Ok(res) => {
@@ -116,70 +189,293 @@ impl <R: ShortcutRepositoryTrait + Send + Sync> ShortcutService<R> {
           let message: String = "Successfully updated shortcut!".to_string();
           let context: SuccessAlert = SuccessAlert { message, successful: true };
           
-          context.get_html(tera)
+          Ok((StatusCode::OK, context.get_html(&tera, base_path)?))
         } else {
           let message: String = "Shortcut could not be found!".to_string();
           let context: InfoAlert = InfoAlert { message, successful: true };
           
-          context.get_html(tera)
+          Ok((StatusCode::NOT_FOUND, context.get_html(&tera, base_path)?))
         }
       },
       Err(err) => {
         let context: ErrorAlert = ErrorAlert { error:err.to_string(), successful: false };
         
-        context.get_html(tera)
+        Ok((err.status_code(), context.get_html(&tera, base_path)?))
       }
     }
   }
 
-  /// Deletes an existing shortcut.
+  /// Deletes an existing shortcut, as long as it hasn't changed since `version` was read.
   ///
   /// # Parameters
-  /// - `req`: The keyword for shortcut to delete.
+  /// - `keyword`: The keyword for shortcut to delete.
+  /// - `version`: The version of the shortcut the delete was requested for.
+  /// - `base_path`: The path the UI is served under for the request, see `BasePath`.
   ///
   /// # Returns
-  /// - `Html<String>` indicating success or failure.
-  pub async fn delete(&self, keyword: &str) -> Html<String> {
-    let result: Result<bool, ShortcutError> = self.repository.delete(keyword).await;
-    let tera:Tera = TERA.read().unwrap().clone();
+  /// - `Result<(StatusCode, Html<String>), TemplateError>` indicating success or failure.
+  pub async fn delete(&self, keyword: &str, version: Option<i64>, base_path: &str) -> Result<(StatusCode, Html<String>), TemplateError> {
+    let tera = TERA.load();
     let action = "delete".to_owned();
+
+    let result: Result<bool, ShortcutError> = match version {
+      Some(_) => self.repository.delete(keyword, version).await,
+      None => Err(ShortcutError::VersionRequired),
+    };
+    METRICS.record_write("delete", &result);
+
     match result {
       Ok(true) => {
         let title: String = "Successfully deleted shortcut!".to_string();
         let message: String = format!("Deleted shortcut for keyword: {}", keyword).to_string();
         let context: SuccessDialog = SuccessDialog { title, message, keyword: keyword.to_owned(), action, status: "success".to_string() };
         
-        context.get_html(tera)
+        Ok((StatusCode::OK, context.get_html(&tera, base_path)?))
       },  
       Ok(false) => {
         let title: String = "Shortcut could not be found!".to_string();
         let message: String = format!("Could not delete shortcut for keyword: {}", keyword).to_string();
         let context: InfoDialog = InfoDialog { title, message, keyword: keyword.to_owned(), action, status: "info".to_string() };
         
-        context.get_html(tera)
+        Ok((StatusCode::NOT_FOUND, context.get_html(&tera, base_path)?))
+      },
+      Err(err @ ShortcutError::ReadOnly) => {
+        let title: String = "Shortcut is read-only!".to_string();
+        let context: ErrorDialog = ErrorDialog { title, message:err.to_string(), keyword: keyword.to_owned(), action, status: "error".to_string() };
+        
+        Ok((err.status_code(), context.get_html(&tera, base_path)?))
+      },
+      Err(err @ (ShortcutError::VersionConflict | ShortcutError::VersionRequired)) => {
+        let title: String = "Shortcut has been changed!".to_string();
+        let context: ErrorDialog = ErrorDialog { title, message:err.to_string(), keyword: keyword.to_owned(), action, status: "error".to_string() };
+        
+        Ok((err.status_code(), context.get_html(&tera, base_path)?))
       },
       Err(err) => {
         let title: String = "Error while deleting shortcut!".to_string();
         let context: ErrorDialog = ErrorDialog { title, message:err.to_string(), keyword: keyword.to_owned(), action, status: "error".to_string() };
         
-        context.get_html(tera)
+        Ok((err.status_code(), context.get_html(&tera, base_path)?))
       }
     }
   }
 
-  /// Retrieves a shortcut by keyword and returns a redirect to its URL.
+  /// Retrieves a shortcut by keyword, used by the JSON read endpoint to expose the `version` as an ETag.
   ///
   /// # Parameters
   /// - `keyword`: The shortcut's keyword.
   ///
   /// # Returns
+  /// - `Result<Shortcut, ShortcutError>` with the stored shortcut.
+  pub async fn find(&self, keyword: &str) -> Result<Shortcut, ShortcutError> {
+    self.repository.get(keyword).await
+  }
+
+  /// Retrieves a shortcut by keyword and returns a redirect to its URL.
+  /// 
+  /// Anything typed after the keyword, e.g. `gh shortcut`, is the query put into the `{query}` placeholder of the url.
+  /// A keyword containing spaces is still matched as a whole first.
+  ///
+  /// # Parameters
+  /// - `keyword`: The shortcut's keyword, optionally followed by a query.
+  /// - `ui_url`: Where to redirect the user if the shortcut can't be fetched.
+  /// - `base_path`: The path the UI is served under for the request, which the search page is under.
+  /// - `settings`: The current `Settings`, deciding the redirect status and where a missing keyword goes.
+  ///
+  /// # Returns
   /// - `Redirect` that redirects the user to the corresponding URL.
-  pub async fn get(&self, keyword: &str) -> Redirect {
-    return match self.repository.get(keyword).await {
-      Ok(shortcut) =>Redirect::to(&shortcut.url),
-      Err(ShortcutError::NotFound) => Redirect::to(&format!("/search?keyword={}", keyword)),
-      Err(_) => Redirect::to(&(std::env::var("UI_URL").unwrap_or(String::from("http://localhost:3000/")))),
+  pub async fn get(&self, keyword: &str, ui_url: &str, base_path: &str, settings: &Settings) -> Redirect {
+    let mut result: Result<(Shortcut, &str), ShortcutError> = self.repository.get(keyword).await.map(|shortcut| (shortcut, ""));
+
+    if let (Err(ShortcutError::NotFound), Some((name, query))) = (&result, keyword.trim().split_once(char::is_whitespace)) {
+      result = self.repository.get(name).await.map(|shortcut| (shortcut, query.trim()));
+    }
+
+    // Keywords sent to the search or home page are misses, only those sent to the fallback url are fallbacks.
+    let outcome: &str = match (&result, &settings.fallback) {
+      (Ok(_), _) => "hit",
+      (Err(ShortcutError::NotFound), Fallback::Url) => "fallback",
+      (Err(ShortcutError::NotFound), _) => "miss",
+      (Err(_), _) => "error",
+    };
+    METRICS.resolutions.increment(&[outcome]);
+
+    match result {
+      Ok((shortcut, query)) => redirect_with_status(settings.redirect_status, &shortcut.expand(query)),
+      Err(ShortcutError::NotFound) => match settings.fallback {
+        Fallback::Search => Redirect::to(&format!("{}/search?keyword={}", base_path, byte_serialize(keyword.as_bytes()).collect::<String>())),
+        Fallback::Home => Redirect::to(ui_url),
+        Fallback::Url => Redirect::to(&expand_query(&settings.fallback_url, keyword)),
+      },
+      Err(_) => Redirect::to(ui_url),
+    }
+  }
+
+  /// Applies a list of create, update and delete operations within a single transaction.
+  ///
+  /// # Parameters
+  /// - `req`: The operations and the `BulkMode` to apply them with.
+  ///
+  /// # Returns
+  /// - `Result<BulkReport, ShortcutError>` with the outcome of each operation.
+  pub async fn bulk(&self, req: &BulkRequest) -> Result<BulkReport, ShortcutError> {
+    let operations: Vec<BulkOperation> = req.operations.iter().map(BulkOperation::normalised).collect();
+
+    self.repository.bulk(&operations, req.mode).await
+  }
+
+  /// Finds every shortcut whose url contains `find` and replaces it with `replace`.
+  ///
+  /// When `dry_run` is set nothing is written and only the planned replacements are returned,
+  /// otherwise the replacements are applied as a single all-or-nothing bulk update.
+  ///
+  /// # Parameters
+  /// - `req`: The text to find, its replacement and whether this is a dry run.
+  ///
+  /// # Returns
+  /// - `Result<ReplaceUrlReport, ShortcutError>` with the planned replacements and, if applied, the `BulkReport`.
+  pub async fn replace_urls(&self, req: &ReplaceUrlRequest) -> Result<ReplaceUrlReport, ShortcutError> {
+    if req.find.is_empty() {
+      return Ok(ReplaceUrlReport { dry_run: req.dry_run, replacements: vec![], report: None });
+    }
+
+    let replacements: Vec<UrlReplacement> = self.repository.find_by_url(&req.find).await?
+      .into_iter()
+      .map(|shortcut| UrlReplacement {
+        new_url: shortcut.url.replace(&req.find, &req.replace),
+        old_url: shortcut.url,
+        keyword: shortcut.keyword,
+        version: shortcut.version,
+      })
+      .filter(|replacement| replacement.new_url != replacement.old_url)
+      .collect();
+
+    if req.dry_run || replacements.is_empty() {
+      return Ok(ReplaceUrlReport { dry_run: req.dry_run, replacements, report: None });
+    }
+
+    let operations: Vec<BulkOperation> = replacements.iter()
+      .map(|replacement| BulkOperation::Update { keyword: replacement.keyword.clone(), url: replacement.new_url.clone(), version: replacement.version })
+      .collect();
+    let report: BulkReport = self.repository.bulk(&operations, BulkMode::AllOrNothing).await?;
+
+    Ok(ReplaceUrlReport { dry_run: false, replacements, report: Some(report) })
+  }
+
+  /// Exports every shortcut in the given format, streamed one row at a time.
+  ///
+  /// # Parameters
+  /// - `format`: The `FileFormat` to write.
+  ///
+  /// # Returns
+  /// - A stream of `String` chunks making up the exported file.
+  pub fn export(&self, format: FileFormat) -> impl Stream<Item = Result<String, ShortcutError>> + Send + 'static {
+    export_stream(format, self.repository.stream_all())
+  }
+
+  /// Parses an uploaded file and works out what importing it would do, without writing anything.
+  ///
+  /// # Parameters
+  /// - `contents`: The bytes of the uploaded file.
+  /// - `format`: The `ImportFormat` of the file.
+  /// - `policy`: The `ConflictPolicy` for keywords that already exist.
+  /// - `base_path`: The path the UI is served under for the request, see `BasePath`.
+  ///
+  /// # Returns
+  /// - `Result<Html<String>, TemplateError>` with the previewed changes and a form to confirm them, or an error alert.
+  pub async fn preview_import(&self, contents: &[u8], format: ImportFormat, policy: ConflictPolicy, base_path: &str) -> Result<Html<String>, TemplateError> {
+    let tera = TERA.load();
+    let shortcuts: Result<Vec<Shortcut>, ShortcutError> = parse_import(format, contents).await.and_then(normalise_import);
+
+    let result = match shortcuts {
+      Ok(shortcuts) => self.repository.import(&shortcuts, policy, true).await
+        .map(|summary| (summary, serde_json::to_string(&shortcuts).unwrap_or_default())),
+      Err(err) => Err(err),
+    };
+
+    match result {
+      Ok((summary, payload)) => {
+        let context: ImportPreviewTemplate = ImportPreviewTemplate { summary, policy, payload };
+
+        context.get_html(&tera, base_path)
+      },
+      Err(err) => {
+        let context: ErrorAlert = ErrorAlert { error:err.to_string(), successful: false };
+
+        context.get_html(&tera, base_path)
+      }
+    }
+  }
+
+  /// Imports a file straight away, without a preview, as used by the command line.
+  ///
+  /// # Parameters
+  /// - `contents`: The bytes of the file.
+  /// - `format`: The `ImportFormat` of the file.
+  /// - `policy`: The `ConflictPolicy` for keywords that already exist.
+  /// - `dry_run`: Whether to roll back instead of committing.
+  ///
+  /// # Returns
+  /// - `Result<ImportSummary, ShortcutError>` with the outcome of each imported shortcut.
+  pub async fn import_file(&self, contents: &[u8], format: ImportFormat, policy: ConflictPolicy, dry_run: bool) -> Result<ImportSummary, ShortcutError> {
+    let shortcuts: Vec<Shortcut> = parse_import(format, contents).await.and_then(normalise_import)?;
+
+    self.repository.import(&shortcuts, policy, dry_run).await
+  }
+
+  /// Imports a previously previewed set of shortcuts within a single transaction.
+  ///
+  /// # Parameters
+  /// - `req`: The previewed shortcuts and the `ConflictPolicy` to import them with.
+  /// - `base_path`: The path the UI is served under for the request, see `BasePath`.
+  ///
+  /// # Returns
+  /// - `Result<Html<String>, TemplateError>` with a summary of the created, updated and skipped shortcuts, or an error alert.
+  pub async fn import(&self, req: &ImportRequest, base_path: &str) -> Result<Html<String>, TemplateError> {
+    let tera = TERA.load();
+    let shortcuts: Result<Vec<Shortcut>, ShortcutError> = serde_json::from_str(&req.payload)
+      .map_err(|err| {
+        error!("Failed to read import payload: {}", err);
+        ShortcutError::InvalidImport
+      })
+      .and_then(normalise_import);
+
+    let result: Result<ImportSummary, ShortcutError> = match shortcuts {
+      Ok(shortcuts) => self.repository.import(&shortcuts, req.policy, false).await,
+      Err(err) => Err(err),
     };
+
+    match result {
+      Ok(summary) => {
+        let context: ImportSummaryTemplate = ImportSummaryTemplate { summary, policy: req.policy };
+
+        context.get_html(&tera, base_path)
+      },
+      Err(err) => {
+        let context: ErrorAlert = ErrorAlert { error:err.to_string(), successful: false };
+
+        context.get_html(&tera, base_path)
+      }
+    }
+  }
+
+  /// Brings the database in line with the declarative shortcuts file, see `plan_sync`.
+  ///
+  /// # Parameters
+  /// - `declared`: Each keyword in the shortcuts file and its url.
+  /// - `dry_run`: Whether to only plan the changes, without applying them.
+  ///
+  /// # Returns
+  /// - `Result<SyncPlan, ShortcutError>` with the planned (or applied) changes.
+  pub async fn sync(&self, declared: &BTreeMap<String, String>, dry_run: bool) -> Result<SyncPlan, ShortcutError> {
+    let existing: Vec<Shortcut> = self.repository.stream_all().try_collect().await?;
+    let plan: SyncPlan = plan_sync(&existing, declared);
+
+    if !dry_run && !plan.is_empty() {
+      self.repository.apply_sync(&plan).await?;
+    }
+
+    Ok(plan)
   }
 }
 
@@ -197,6 +493,8 @@ mod shortcut_repository_tests {
         updated: Some(4), 
         keyword: "bing".to_owned(),
         url: "https://bing.co.uk".to_owned(),
+        version: Some(1),
+        managed: false,
       };
 
     pub static ref GOOGLE_SHORTCUT: Shortcut = Shortcut { 
@@ -205,6 +503,8 @@ mod shortcut_repository_tests {
         updated: Some(2), 
         keyword: "google".to_owned(),
         url: "https://google.co.uk".to_owned(),
+        version: Some(1),
+        managed: false,
       };
 
     pub static ref G_SHORTCUT: Shortcut = Shortcut { 
@@ -213,6 +513,8 @@ mod shortcut_repository_tests {
         updated: Some(22), 
         keyword: "g".to_owned(),
         url: "https://google.com".to_owned(),
+        version: Some(1),
+        managed: false,
       };
 
     pub static ref GLE_SHORTCUT: Shortcut = Shortcut { 
@@ -221,6 +523,8 @@ mod shortcut_repository_tests {
         updated: Some(2222), 
         keyword: "gle".to_owned(),
         url: "https://greatlightexchange.co.uk".to_owned(),
+        version: Some(1),
+        managed: false,
       };
 
     pub static ref GOOGLE_COM_SHORTCUT: Shortcut = Shortcut { 
@@ -229,6 +533,8 @@ mod shortcut_repository_tests {
         updated: Some(3), 
         keyword: "google".to_owned(),
         url: "https://google.com".to_owned(),
+        version: Some(1),
+        managed: false,
       };
 
     pub static ref TEST_SHORTCUT: Shortcut = Shortcut { 
@@ -237,21 +543,26 @@ mod shortcut_repository_tests {
         updated: Some(222), 
         keyword: "test".to_owned(),
         url: "https://test.co.uk".to_owned(),
+        version: Some(1),
+        managed: false,
       };
     
   }
 
   mod get_tests {
-    use axum::response::Redirect;
+    use axum::{http::{header::LOCATION, StatusCode}, response::{IntoResponse, Redirect}};
 
     use crate::{
       error::ShortcutError, 
+      models::{settings::{Fallback, Settings}, shortcut::Shortcut}, 
       repository::shortcut::MockShortcutRepositoryTrait, 
       service::shortcut::ShortcutService
     };
 
     use super::GOOGLE_SHORTCUT;
 
+    const UI_URL: &str = "http://localhost:8035/";
+
     #[tokio::test]
     async fn get_redirect_to_shortcut_when_exists() {
       let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
@@ -260,7 +571,7 @@ mod shortcut_repository_tests {
       let shortcut_service = ShortcutService::new(mock);
 
       let input = "google".to_string();
-      let result = shortcut_service.get(&input).await;
+      let result = shortcut_service.get(&input, UI_URL, "", &Settings::default()).await;
       let _expected_redirect = Redirect::to(&GOOGLE_SHORTCUT.url);
 
       assert!(matches!(result, _expected_redirect))
@@ -274,16 +585,68 @@ mod shortcut_repository_tests {
       let shortcut_service = ShortcutService::new(mock);
 
       let input = "google".to_string();
-      let result = shortcut_service.get(&input).await;
-      let _expected_redirect = Redirect::to(&format!("/search?keyword={}", input));
+      let result = shortcut_service.get(&input, UI_URL, "/go", &Settings::default()).await.into_response();
 
-      assert!(matches!(result, _expected_redirect))
+      assert_eq!(result.headers().get(LOCATION).unwrap(), "/go/search?keyword=google");
+    }
+
+    #[tokio::test]
+    async fn get_redirect_to_search_with_encoded_keyword() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_get().returning(|_| Err(ShortcutError::NotFound));
+
+      let shortcut_service = ShortcutService::new(mock);
+
+      let result = shortcut_service.get("/café & co", UI_URL, "", &Settings::default()).await.into_response();
+
+      assert_eq!(result.headers().get(LOCATION).unwrap(), "/search?keyword=%2Fcaf%C3%A9+%26+co");
+    }
+
+    #[tokio::test]
+    async fn get_redirect_to_expanded_url_when_query_given() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_get().withf(|keyword| keyword == "gh rust lang").returning(|_| Err(ShortcutError::NotFound));
+      mock.expect_get().withf(|keyword| keyword == "gh").returning(|_| Ok(Shortcut::new("gh".to_owned(), "https://github.com/search?q={query}".to_owned())));
+
+      let shortcut_service = ShortcutService::new(mock);
+      let result = shortcut_service.get("gh rust lang", UI_URL, "", &Settings::default()).await.into_response();
+
+      assert_eq!(result.headers().get(LOCATION).unwrap(), "https://github.com/search?q=rust+lang");
+    }
+
+    #[tokio::test]
+    async fn get_redirect_with_status_and_fallback_from_settings() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_get().withf(|keyword| keyword == "google").returning(|_| Ok(GOOGLE_SHORTCUT.to_owned()));
+      mock.expect_get().withf(|keyword| keyword != "google").returning(|_| Err(ShortcutError::NotFound));
+
+      let shortcut_service = ShortcutService::new(mock);
+      let settings = Settings { fallback: Fallback::Url, redirect_status: 308, ..Settings::default() };
+
+      let found = shortcut_service.get("google", UI_URL, "", &settings).await.into_response();
+      let missing = shortcut_service.get("rust docs", UI_URL, "", &settings).await.into_response();
+
+      assert_eq!(found.status(), StatusCode::PERMANENT_REDIRECT);
+      assert_eq!(found.headers().get(LOCATION).unwrap(), GOOGLE_SHORTCUT.url.as_str());
+      assert_eq!(missing.status(), StatusCode::SEE_OTHER);
+      assert_eq!(missing.headers().get(LOCATION).unwrap(), "https://duckduckgo.com/?q=rust+docs");
+    }
+
+    #[tokio::test]
+    async fn get_redirect_to_ui_when_failed() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_get().returning(|_| Err(ShortcutError::FailedToGet));
+
+      let shortcut_service = ShortcutService::new(mock);
+      let result = shortcut_service.get("google", UI_URL, "", &Settings::default()).await.into_response();
+
+      assert_eq!(result.headers().get(LOCATION).unwrap(), UI_URL);
     }
   }
 
   mod update_tests {
-    use tera::Tera;
-
+    use axum::http::StatusCode;
+    
     use crate::{
       error::ShortcutError, 
       macros::renderable::Renderable, 
@@ -301,16 +664,17 @@ mod shortcut_repository_tests {
 
       let shortcut_service = ShortcutService::new(mock);
 
-      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned() };
-      let result = shortcut_service.update(&input).await;
+      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: Some(1) };
+      let result = shortcut_service.update(&input, "").await.unwrap();
 
       let message: String = "Successfully updated shortcut!".to_string();
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let context: SuccessAlert = SuccessAlert { message, successful: true };
       
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
-      assert_eq!(result.0, _expected_result.0)
+      assert_eq!(result.0, StatusCode::OK);
+      assert_eq!(result.1.0, _expected_result.0)
     }
 
     #[tokio::test]
@@ -320,16 +684,16 @@ mod shortcut_repository_tests {
 
       let shortcut_service = ShortcutService::new(mock);
 
-      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned() };
-      let result = shortcut_service.update(&input).await;
+      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: Some(1) };
+      let result = shortcut_service.update(&input, "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let message: String = "Shortcut could not be found!".to_string();
       let context: InfoAlert = InfoAlert { message, successful: true };
       
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
-      assert_eq!(result.0, _expected_result.0)
+      assert_eq!(result.1.0, _expected_result.0)
     }
 
     #[tokio::test]
@@ -339,21 +703,59 @@ mod shortcut_repository_tests {
 
       let shortcut_service = ShortcutService::new(mock);
 
-      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned() };
-      let result = shortcut_service.update(&input).await;
+      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: Some(1) };
+      let result = shortcut_service.update(&input, "").await.unwrap();
       
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let context: ErrorAlert = ErrorAlert { error: ShortcutError::FailedToUpdate.to_string(), successful: false };
       
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
-      assert_eq!(result.0, _expected_result.0)
+      assert_eq!(result.1.0, _expected_result.0)
+    }
+
+    #[tokio::test]
+    async fn return_conflict_html_when_version_is_stale() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_update().returning(|_| Err(ShortcutError::VersionConflict));
+
+      let shortcut_service = ShortcutService::new(mock);
+
+      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: Some(1) };
+      let result = shortcut_service.update(&input, "").await.unwrap();
+      
+      let tera = TERA.load();
+      let context: ErrorAlert = ErrorAlert { error: ShortcutError::VersionConflict.to_string(), successful: false };
+      
+      let _expected_result = context.get_html(&tera, "").unwrap();
+
+      assert_eq!(result.0, StatusCode::PRECONDITION_FAILED);
+      assert_eq!(result.1.0, _expected_result.0)
+    }
+
+    #[tokio::test]
+    async fn return_error_html_without_updating_when_version_missing() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_update().never();
+
+      let shortcut_service = ShortcutService::new(mock);
+
+      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: None };
+      let result = shortcut_service.update(&input, "").await.unwrap();
+      
+      let tera = TERA.load();
+      let context: ErrorAlert = ErrorAlert { error: ShortcutError::VersionRequired.to_string(), successful: false };
+      
+      let _expected_result = context.get_html(&tera, "").unwrap();
+
+      assert_eq!(result.0, StatusCode::PRECONDITION_REQUIRED);
+      assert_eq!(result.1.0, _expected_result.0)
     }
   }
 
   mod delete_tests {
-    use tera::Tera;
-
+    use axum::http::StatusCode;
+    
     use crate::{
       error::ShortcutError, 
       macros::renderable::Renderable, 
@@ -368,66 +770,86 @@ mod shortcut_repository_tests {
     #[tokio::test]
     async fn return_success_html_when_updated() {
       let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
-      mock.expect_delete().returning(|_| Ok(true));
+      mock.expect_delete().returning(|_, _| Ok(true));
 
       let shortcut_service = ShortcutService::new(mock);
 
       let keyword: &str = "google";
-      let result = shortcut_service.delete(keyword).await;
+      let result = shortcut_service.delete(keyword, Some(1), "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let title: String = "Successfully deleted shortcut!".to_string();
       let message: String = format!("Deleted shortcut for keyword: {}", keyword.to_owned()).to_string();
       let context: SuccessDialog = SuccessDialog { title, message, keyword: keyword.to_owned(), action: ACTION.to_string(), status: "success".to_string() };
       
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
-      assert_eq!(result.0, _expected_result.0)
+      assert_eq!(result.0, StatusCode::OK);
+      assert_eq!(result.1.0, _expected_result.0)
     }
 
     #[tokio::test]
     async fn return_info_html_when_no_entry_found() {
       let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
-      mock.expect_delete().returning(|_| Ok(false));
+      mock.expect_delete().returning(|_, _| Ok(false));
 
       let shortcut_service = ShortcutService::new(mock);
         
       let keyword: &str = "google";
-      let result = shortcut_service.delete(keyword).await;
+      let result = shortcut_service.delete(keyword, Some(1), "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let title: String = "Shortcut could not be found!".to_string();
       let message: String = format!("Could not delete shortcut for keyword: {}", keyword.to_owned()).to_string();
       let context: InfoDialog = InfoDialog { title, message, keyword: keyword.to_owned(), action: ACTION.to_string(), status: "info".to_string() };
 
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
-      assert_eq!(result.0, _expected_result.0)
+      assert_eq!(result.1.0, _expected_result.0)
     }
 
     #[tokio::test]
     async fn return_error_html_when_failed_to_update() {
       let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
-      mock.expect_delete().returning(|_|  Err(ShortcutError::FailedToDelete));
+      mock.expect_delete().returning(|_, _|  Err(ShortcutError::FailedToDelete));
 
       let shortcut_service = ShortcutService::new(mock);
         
       let keyword: &str = "google";
-      let result = shortcut_service.delete(keyword).await;
+      let result = shortcut_service.delete(keyword, Some(1), "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let title: String = "Error while deleting shortcut!".to_string();
       let context: ErrorDialog = ErrorDialog { title, message: ShortcutError::FailedToDelete.to_string(), keyword: keyword.to_owned(), action: ACTION.to_string(), status: "error".to_string() };
       
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
-      assert_eq!(result.0, _expected_result.0)
+      assert_eq!(result.1.0, _expected_result.0)
+    }
+
+    #[tokio::test]
+    async fn return_conflict_html_when_version_is_stale() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_delete().returning(|_, _|  Err(ShortcutError::VersionConflict));
+
+      let shortcut_service = ShortcutService::new(mock);
+        
+      let keyword: &str = "google";
+      let result = shortcut_service.delete(keyword, Some(1), "").await.unwrap();
+
+      let tera = TERA.load();
+      let title: String = "Shortcut has been changed!".to_string();
+      let context: ErrorDialog = ErrorDialog { title, message: ShortcutError::VersionConflict.to_string(), keyword: keyword.to_owned(), action: ACTION.to_string(), status: "error".to_string() };
+      
+      let _expected_result = context.get_html(&tera, "").unwrap();
+
+      assert_eq!(result.0, StatusCode::PRECONDITION_FAILED);
+      assert_eq!(result.1.0, _expected_result.0)
     }
   }
 
   mod create_tests {
-    use tera::Tera;
-
+    
     use crate::{
       error::ShortcutError, 
       macros::renderable::Renderable, 
@@ -445,13 +867,13 @@ mod shortcut_repository_tests {
 
       let shortcut_service = ShortcutService::new(mock);
 
-      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned() };
-      let result = shortcut_service.create(&input).await;
+      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: None };
+      let result = shortcut_service.create(&input, "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let context: SuccessAlert = SuccessAlert { message: "Successfully created shortcut!".into(), successful: true };
       
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
       assert_eq!(result.0, _expected_result.0)
     }
@@ -464,13 +886,13 @@ mod shortcut_repository_tests {
 
       let shortcut_service = ShortcutService::new(mock);
 
-      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned() };
-      let result = shortcut_service.create(&input).await;
+      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: None };
+      let result = shortcut_service.create(&input, "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let context = InfoAlert { message: "Shortcut already added.".into(), successful: true };
       
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
       assert_eq!(result.0, _expected_result.0)
     }
@@ -483,13 +905,13 @@ mod shortcut_repository_tests {
 
       let shortcut_service = ShortcutService::new(mock);
 
-      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned() };
-      let result = shortcut_service.create(&input).await;
+      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: None };
+      let result = shortcut_service.create(&input, "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();            
+      let tera = TERA.load();
       let context: CheckUpdateTemplate = CheckUpdateTemplate { shortcut: GOOGLE_COM_SHORTCUT.to_owned(), new_url: input.url, successful: false };
             
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
       assert_eq!(result.0, _expected_result.0)
     }
@@ -502,13 +924,13 @@ mod shortcut_repository_tests {
 
       let shortcut_service = ShortcutService::new(mock);
 
-      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned() };
-      let result = shortcut_service.create(&input).await;
+      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: None };
+      let result = shortcut_service.create(&input, "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();            
+      let tera = TERA.load();
       let context: ErrorAlert = ErrorAlert { error: ShortcutError::FailedToGet.to_string(), successful: false };
             
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
       assert_eq!(result.0, _expected_result.0)
     }
@@ -520,21 +942,20 @@ mod shortcut_repository_tests {
 
       let shortcut_service = ShortcutService::new(mock);
 
-      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned() };
-      let result = shortcut_service.create(&input).await;
+      let input = PostRequest { keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: None };
+      let result = shortcut_service.create(&input, "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();            
+      let tera = TERA.load();
       let context: ErrorAlert = ErrorAlert { error: ShortcutError::FailedToCreate.to_string(), successful: false };
             
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
       assert_eq!(result.0, _expected_result.0)
     }
   }
 
   mod find_similar_tests {
-    use tera::Tera;
-
+    
     use crate::{
       error::ShortcutError, 
       macros::renderable::Renderable, 
@@ -548,18 +969,18 @@ mod shortcut_repository_tests {
     #[tokio::test]
     async fn return_success_html_when_matches_found() {
       let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
-      mock.expect_fuzzy_search().returning(|_| Ok(vec!(GOOGLE_SHORTCUT.to_owned(), GLE_SHORTCUT.to_owned(), G_SHORTCUT.to_owned())));
+      mock.expect_fuzzy_search().returning(|_, _| Ok(vec!(GOOGLE_SHORTCUT.to_owned(), GLE_SHORTCUT.to_owned(), G_SHORTCUT.to_owned())));
 
       let shortcut_service = ShortcutService::new(mock);
 
       let input = "google".to_owned();
-      let result = shortcut_service.find_similar(&input).await;
+      let result = shortcut_service.find_similar(&input, 0.0, "").await.unwrap();
       let shortcuts: Vec<Shortcut> = vec!(GOOGLE_SHORTCUT.to_owned(), GLE_SHORTCUT.to_owned(), G_SHORTCUT.to_owned());
 
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let context: SearchResultsTemplate = SearchResultsTemplate { shortcuts: shortcuts.to_owned(), query: input };
       
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
       assert_eq!(result.0, _expected_result.0)
     }
@@ -567,17 +988,17 @@ mod shortcut_repository_tests {
     #[tokio::test]
     async fn return_create_new_html_when_no_matches_found() {
       let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
-      mock.expect_fuzzy_search().returning(|_| Err(ShortcutError::NoMatches));
+      mock.expect_fuzzy_search().returning(|_, _| Err(ShortcutError::NoMatches));
 
       let shortcut_service = ShortcutService::new(mock);
 
       let input = "google".to_owned();
-      let result = shortcut_service.find_similar(&input).await;
+      let result = shortcut_service.find_similar(&input, 0.0, "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let context: CreateNewTemplate = CreateNewTemplate { keyword: input };
       
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
       assert_eq!(result.0, _expected_result.0)
     }
@@ -585,19 +1006,240 @@ mod shortcut_repository_tests {
     #[tokio::test]
     async fn return_error_html_when_error_returned() {
       let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
-      mock.expect_fuzzy_search().returning(|_| Err(ShortcutError::FailedToSearch));
+      mock.expect_fuzzy_search().returning(|_, _| Err(ShortcutError::FailedToSearch));
 
       let shortcut_service = ShortcutService::new(mock);
 
       let input = "google".to_owned();
-      let result = shortcut_service.find_similar(&input).await;
+      let result = shortcut_service.find_similar(&input, 0.0, "").await.unwrap();
 
-      let tera:Tera = TERA.read().unwrap().clone();
+      let tera = TERA.load();
       let context: ErrorAlert = ErrorAlert { error:ShortcutError:: FailedToSearch.to_string(), successful: false };
       
-      let _expected_result = context.get_html(tera);
+      let _expected_result = context.get_html(&tera, "").unwrap();
 
       assert_eq!(result.0, _expected_result.0)
     }
   }
-}
+
+  mod replace_urls_tests {
+    use crate::{
+      models::bulk::{BulkItemResult, BulkItemStatus, BulkMode, BulkOperation, BulkReport, ReplaceUrlRequest, UrlReplacement}, 
+      repository::shortcut::MockShortcutRepositoryTrait, 
+      service::shortcut::{shortcut_repository_tests::{GOOGLE_SHORTCUT, G_SHORTCUT}, ShortcutService}
+    };
+
+    fn request(dry_run: bool) -> ReplaceUrlRequest {
+      ReplaceUrlRequest { find: "google".to_owned(), replace: "bing".to_owned(), dry_run }
+    }
+
+    fn expected_replacements() -> Vec<UrlReplacement> {
+      vec![
+        UrlReplacement { keyword: "g".to_owned(), old_url: "https://google.com".to_owned(), new_url: "https://bing.com".to_owned(), version: Some(1) },
+        UrlReplacement { keyword: "google".to_owned(), old_url: "https://google.co.uk".to_owned(), new_url: "https://bing.co.uk".to_owned(), version: Some(1) },
+      ]
+    }
+
+    #[tokio::test]
+    async fn return_planned_replacements_without_writing_when_dry_run() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_find_by_url().returning(|_| Ok(vec!(G_SHORTCUT.to_owned(), GOOGLE_SHORTCUT.to_owned())));
+      mock.expect_bulk().never();
+
+      let shortcut_service = ShortcutService::new(mock);
+      let result = shortcut_service.replace_urls(&request(true)).await.unwrap();
+
+      assert!(result.dry_run);
+      assert_eq!(result.replacements, expected_replacements());
+      assert_eq!(result.report, None);
+    }
+
+    #[tokio::test]
+    async fn return_report_when_replacements_applied() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_find_by_url().returning(|_| Ok(vec!(G_SHORTCUT.to_owned(), GOOGLE_SHORTCUT.to_owned())));
+      mock.expect_bulk()
+        .withf(|operations, mode| {
+          *mode == BulkMode::AllOrNothing 
+            && operations == [
+              BulkOperation::Update { keyword: "g".to_owned(), url: "https://bing.com".to_owned(), version: Some(1) },
+              BulkOperation::Update { keyword: "google".to_owned(), url: "https://bing.co.uk".to_owned(), version: Some(1) },
+            ]
+        })
+        .returning(|operations, mode| Ok(BulkReport {
+          mode,
+          committed: true,
+          results: operations.iter().enumerate()
+            .map(|(index, operation)| BulkItemResult { index, keyword: operation.keyword().to_owned(), status: BulkItemStatus::Applied, error: None })
+            .collect(),
+        }));
+
+      let shortcut_service = ShortcutService::new(mock);
+      let result = shortcut_service.replace_urls(&request(false)).await.unwrap();
+
+      assert!(!result.dry_run);
+      assert_eq!(result.replacements, expected_replacements());
+      assert!(result.report.unwrap().committed);
+    }
+  }
+
+  mod export_tests {
+    use futures::{stream, StreamExt, TryStreamExt};
+
+    use crate::{
+      error::ShortcutError,
+      models::{shortcut::Shortcut, transfer::FileFormat}, 
+      repository::shortcut::MockShortcutRepositoryTrait, 
+      service::shortcut::{shortcut_repository_tests::{GOOGLE_SHORTCUT, G_SHORTCUT}, ShortcutService}
+    };
+
+    #[tokio::test]
+    async fn return_every_shortcut_when_exported() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_stream_all().returning(|| stream::iter(vec!(Ok(G_SHORTCUT.to_owned()), Ok(GOOGLE_SHORTCUT.to_owned()))).boxed());
+
+      let shortcut_service = ShortcutService::new(mock);
+      let chunks: Vec<String> = shortcut_service.export(FileFormat::Json).try_collect().await.unwrap();
+      let result: Vec<Shortcut> = serde_json::from_str(&chunks.concat()).unwrap();
+
+      assert_eq!(result, vec!(G_SHORTCUT.to_owned(), GOOGLE_SHORTCUT.to_owned()));
+    }
+
+    #[tokio::test]
+    async fn return_error_when_stream_fails() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_stream_all().returning(|| stream::iter(vec!(Err(ShortcutError::FailedToExport))).boxed());
+
+      let shortcut_service = ShortcutService::new(mock);
+      let result: Result<Vec<String>, ShortcutError> = shortcut_service.export(FileFormat::Csv).try_collect().await;
+
+      assert!(matches!(result, Err(ShortcutError::FailedToExport)));
+    }
+  }
+
+  mod import_tests {
+    
+    use crate::{
+      error::ShortcutError, 
+      macros::renderable::Renderable, 
+      models::transfer::{ConflictPolicy, ImportAction, ImportFormat, ImportItem, ImportRequest, ImportSummary}, 
+      repository::shortcut::MockShortcutRepositoryTrait, 
+      service::shortcut::ShortcutService, 
+      templates::components::{ErrorAlert, ImportSummaryTemplate}, 
+      TERA
+    };
+
+    fn summary(committed: bool) -> ImportSummary {
+      ImportSummary::new(committed, vec![ImportItem { 
+        keyword: "google".to_owned(), 
+        imported_as: "google".to_owned(), 
+        action: ImportAction::Created, 
+        old_url: None, 
+        url: "https://google.co.uk".to_owned(),
+      }])
+    }
+
+    #[tokio::test]
+    async fn preview_normalises_shortcuts_and_runs_dry_import() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_import()
+        .withf(|shortcuts, policy, dry_run| {
+          shortcuts.len() == 1 && shortcuts[0].keyword == "google" && shortcuts[0].version.is_none() 
+            && shortcuts[0].created.is_some() && *policy == ConflictPolicy::Rename && *dry_run
+        })
+        .returning(|_, _, _| Ok(summary(false)));
+
+      let shortcut_service = ShortcutService::new(mock);
+      let result = shortcut_service.preview_import(b"keyword,url\nGoogle,https://google.co.uk\n", ImportFormat::Csv, ConflictPolicy::Rename, "").await.unwrap();
+
+      assert!(result.0.contains("confirmImportForm"));
+    }
+
+    #[tokio::test]
+    async fn preview_returns_error_html_without_importing_when_file_invalid() {
+      let mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+
+      let shortcut_service = ShortcutService::new(mock);
+      let result = shortcut_service.preview_import(b"keyword,url\n,https://google.co.uk\n", ImportFormat::Csv, ConflictPolicy::Skip, "").await.unwrap();
+
+      let tera = TERA.load();
+      let context: ErrorAlert = ErrorAlert { error:ShortcutError::InvalidImport.to_string(), successful: false };
+
+      assert_eq!(result.0, context.get_html(&tera, "").unwrap().0)
+    }
+
+    #[tokio::test]
+    async fn import_returns_summary_html_when_imported() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_import()
+        .withf(|shortcuts, policy, dry_run| shortcuts.len() == 1 && *policy == ConflictPolicy::Overwrite && !*dry_run)
+        .returning(|_, _, _| Ok(summary(true)));
+
+      let shortcut_service = ShortcutService::new(mock);
+      let request = ImportRequest { 
+        payload: r#"[{"id":null,"created":1,"updated":1,"keyword":"google","url":"https://google.co.uk","version":null}]"#.to_owned(), 
+        policy: ConflictPolicy::Overwrite,
+      };
+      let result = shortcut_service.import(&request, "").await.unwrap();
+
+      let tera = TERA.load();
+      let context: ImportSummaryTemplate = ImportSummaryTemplate { summary: summary(true), policy: ConflictPolicy::Overwrite };
+
+      assert_eq!(result.0, context.get_html(&tera, "").unwrap().0)
+    }
+
+    #[tokio::test]
+    async fn import_returns_error_html_when_import_fails() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_import().returning(|_, _, _| Err(ShortcutError::FailedToImport));
+
+      let shortcut_service = ShortcutService::new(mock);
+      let request = ImportRequest { payload: "[]".to_owned(), policy: ConflictPolicy::Skip };
+      let result = shortcut_service.import(&request, "").await.unwrap();
+
+      let tera = TERA.load();
+      let context: ErrorAlert = ErrorAlert { error:ShortcutError::FailedToImport.to_string(), successful: false };
+
+      assert_eq!(result.0, context.get_html(&tera, "").unwrap().0)
+    }
+  }
+
+  mod sync_tests {
+    use futures::{stream, StreamExt};
+    use std::collections::BTreeMap;
+
+    use crate::{
+      models::sync::SyncChange, 
+      repository::shortcut::MockShortcutRepositoryTrait, 
+      service::shortcut::{shortcut_repository_tests::GOOGLE_SHORTCUT, ShortcutService}
+    };
+
+    fn declared() -> BTreeMap<String, String> {
+      BTreeMap::from([("gh".to_owned(), "https://github.com".to_owned())])
+    }
+
+    #[tokio::test]
+    async fn return_plan_without_applying_when_dry_run() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_stream_all().returning(|| stream::iter(vec!(Ok(GOOGLE_SHORTCUT.to_owned()))).boxed());
+      mock.expect_apply_sync().never();
+
+      let shortcut_service = ShortcutService::new(mock);
+      let result = shortcut_service.sync(&declared(), true).await.unwrap();
+
+      assert_eq!(result.changes, vec![SyncChange::Create { keyword: "gh".to_owned(), url: "https://github.com".to_owned() }]);
+    }
+
+    #[tokio::test]
+    async fn return_plan_after_applying_it() {
+      let mut mock: MockShortcutRepositoryTrait = MockShortcutRepositoryTrait::default(); 
+      mock.expect_stream_all().returning(|| stream::iter(vec!(Ok(GOOGLE_SHORTCUT.to_owned()))).boxed());
+      mock.expect_apply_sync().times(1).withf(|plan| plan.counts() == (1, 0, 0)).returning(|_| Ok(()));
+
+      let shortcut_service = ShortcutService::new(mock);
+      let result = shortcut_service.sync(&declared(), false).await.unwrap();
+
+      assert_eq!(result.counts(), (1, 0, 0));
+    }
+  }
+}
\ No newline at end of file
@@ -1,14 +1,38 @@
-use crate::{repository::shortcut::ShortcutRepository, service::shortcut::ShortcutService};
+use crate::{
+  config::Config,
+  models::settings::Settings,
+  repository::{settings::SettingsRepository, shortcut::ShortcutRepository},
+  schema::Database,
+  service::{settings::SettingsService, shortcut::ShortcutService},
+};
+
+use std::sync::Arc;
+use tokio::sync::watch;
 
 #[derive(Clone)]
 pub struct AppState {
   pub shortcut_service: ShortcutService<ShortcutRepository>,
+  pub settings_service: SettingsService<SettingsRepository>,
+  /// The current `Settings`, updated whenever they're saved from the settings page.
+  pub settings: watch::Receiver<Settings>,
+  pub config: Arc<Config>,
+  /// The database the repositories use, for checking it's ready.
+  pub database: Database,
 }
 
 impl AppState {
-  pub fn new(shortcut_service: ShortcutService<ShortcutRepository>) -> Self {
+  pub fn new(
+    shortcut_service: ShortcutService<ShortcutRepository>, 
+    settings_service: SettingsService<SettingsRepository>, 
+    config: Arc<Config>,
+    database: Database,
+  ) -> Self {
     Self {
       shortcut_service,
+      settings: settings_service.subscribe(),
+      settings_service,
+      config,
+      database,
     }
   }
 }
\ No newline at end of file
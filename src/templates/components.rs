@@ -1,58 +1,47 @@
-use crate::{macros::renderable::Renderable, models::shortcut::Shortcut};
+use crate::{macros::renderable::Renderable, models::{settings::Settings, shortcut::Shortcut, transfer::{ConflictPolicy, ImportSummary}}};
 
-use axum::response::Html;
 use serde::Serialize;
-use tera::Tera;
-use tera_hot_reload::TeraTemplate;
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "components/alert/error.html")]
+#[derive(Serialize)]
 pub struct ErrorAlert {
   pub error: String,
   pub successful: bool,
 }
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "components/alert/info.html")]
+#[derive(Serialize)]
 pub struct InfoAlert {
   pub message: String,
   pub successful: bool,
 }
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "components/alert/success.html")]
+#[derive(Serialize)]
 pub struct SuccessAlert {
   pub message: String,
   pub successful: bool,
 }
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "components/search/search_results.html")]
+#[derive(Serialize)]
 pub struct SearchResultsTemplate {
   pub shortcuts: Vec<Shortcut>,
   pub query: String,
 }
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "components/create/check_update.html")]
+#[derive(Serialize)]
 pub struct CheckUpdateTemplate {
   pub shortcut: Shortcut,
   pub new_url: String,
   pub successful: bool,
 }
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "components/search/create_new.html")]
+#[derive(Serialize)]
 pub struct CreateNewTemplate {
   pub keyword: String,
 }
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "components/common/empty.html")]
+#[derive(Serialize)]
 pub struct EmptyTemplate {}
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "components/dialog/success.html")]
+#[derive(Serialize)]
 pub struct SuccessDialog {
   pub title: String,
   pub message: String,
@@ -61,8 +50,7 @@ pub struct SuccessDialog {
   pub status: String,
 }
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "components/dialog/error.html")]
+#[derive(Serialize)]
 pub struct ErrorDialog {
   pub title: String,
   pub message: String,
@@ -71,8 +59,7 @@ pub struct ErrorDialog {
   pub status: String,
 }
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "components/dialog/info.html")]
+#[derive(Serialize)]
 pub struct InfoDialog {
   pub title: String,
   pub message: String,
@@ -81,15 +68,46 @@ pub struct InfoDialog {
   pub status: String,
 }
 
+#[derive(Serialize)]
+pub struct ImportPreviewTemplate {
+  pub summary: ImportSummary,
+  pub policy: ConflictPolicy,
+  pub payload: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportSummaryTemplate {
+  pub summary: ImportSummary,
+  pub policy: ConflictPolicy,
+}
+
+#[derive(Serialize)]
+pub struct AutostartTemplate {
+  pub enabled: bool,
+  pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SettingsTemplate {
+  pub settings: Settings,
+  pub statuses: Vec<u16>,
+  pub message: Option<String>,
+  pub error: Option<String>,
+}
+
 impl_renderable!(
-  SearchResultsTemplate, 
-  SuccessAlert, 
-  CheckUpdateTemplate, 
-  ErrorAlert, 
-  InfoAlert, 
-  CreateNewTemplate,
-  EmptyTemplate,
-  SuccessDialog,
-  ErrorDialog,
-  InfoDialog
-);
+  ErrorAlert => "components/alert/error.html",
+  InfoAlert => "components/alert/info.html",
+  SuccessAlert => "components/alert/success.html",
+  SearchResultsTemplate => "components/search/search_results.html",
+  CheckUpdateTemplate => "components/create/check_update.html",
+  CreateNewTemplate => "components/search/create_new.html",
+  EmptyTemplate => "components/common/empty.html",
+  SuccessDialog => "components/dialog/success.html",
+  ErrorDialog => "components/dialog/error.html",
+  InfoDialog => "components/dialog/info.html",
+  ImportPreviewTemplate => "components/import/preview.html",
+  ImportSummaryTemplate => "components/import/summary.html",
+  AutostartTemplate => "components/settings/autostart.html",
+  SettingsTemplate => "components/settings/settings.html",
+);
\ No newline at end of file
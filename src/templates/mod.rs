@@ -1,24 +1,37 @@
-use crate::{TERA, macros::renderable::Renderable};
+use crate::{TERA, macros::renderable::Renderable, utils::url_util::BasePath};
 
-use axum::{http::StatusCode, response::{IntoResponse, Redirect}, routing::get, Router};
-use pages::{CreatePageTemplate, SearchPageTemplate};
+use axum::{response::Redirect, routing::{get, MethodRouter}, Extension, Router};
+use pages::{CreatePageTemplate, ImportPageTemplate, SearchPageTemplate, SettingsPageTemplate};
 
 pub mod components;
 pub mod pages;
 
-async fn redirect_to_search() -> Redirect {
-  Redirect::to("/search")
+pub async fn redirect_to_search(Extension(base_path): Extension<BasePath>) -> Redirect {
+  Redirect::to(&base_path.join("/search"))
 }
 
-async fn health_check() -> impl IntoResponse {
-  // Return HTTP 200 OK to signify the application is healthy
-  StatusCode::OK
+/// The `GET` route for a page that only needs its template.
+///
+/// In debug builds the page is rendered on every request, so it picks up templates changed since startup,
+/// release builds can't change their templates so the page is rendered once, for the configured `base_path`.
+/// A request under another `BasePath`, i.e. with an `X-Forwarded-Prefix`, gets the page rendered for it.
+fn page<T: Renderable + Clone + Send + Sync + 'static>(template: T, base_path: &str) -> MethodRouter {
+  let rendered = (!cfg!(debug_assertions)).then(|| (base_path.to_owned(), template.get_html(&TERA.load(), base_path)));
+
+  get(move |Extension(base_path): Extension<BasePath>| async move {
+    match rendered {
+      Some((rendered_for, rendered)) if rendered_for == base_path.0 => rendered,
+      _ => template.get_html(&TERA.load(), &base_path.0),
+    }
+  })
 }
 
-pub fn create_ui_routes() -> Router {
+/// The pages of the UI, pre-rendered in release builds for the configured `base_path`, see `page`.
+pub fn create_ui_routes(base_path: &str) -> Router {
   Router::new()
     .route("/", get(redirect_to_search))
-    .route("/healthcheck", get(health_check))
-    .route("/search", get(SearchPageTemplate{}.get_html(TERA.read().unwrap().clone())))
-    .route("/create", get(CreatePageTemplate{}.get_html(TERA.read().unwrap().clone())))
+    .route("/search", page(SearchPageTemplate{}, base_path))
+    .route("/create", page(CreatePageTemplate{}, base_path))
+    .route("/import", page(ImportPageTemplate{}, base_path))
+    .route("/settings", page(SettingsPageTemplate{}, base_path))
 }
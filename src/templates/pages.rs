@@ -1,16 +1,22 @@
 use crate::macros::renderable::Renderable;
 
-use axum::response::Html;
 use serde::Serialize;
-use tera::Tera;
-use tera_hot_reload::TeraTemplate;
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "pages/createPage.html")]
+#[derive(Serialize, Clone)]
 pub struct CreatePageTemplate {}
 
-#[derive(TeraTemplate, Serialize)]
-#[template(path = "pages/searchPage.html")]
+#[derive(Serialize, Clone)]
 pub struct SearchPageTemplate {}
 
-impl_renderable!(SearchPageTemplate, CreatePageTemplate);
+#[derive(Serialize, Clone)]
+pub struct ImportPageTemplate {}
+
+#[derive(Serialize, Clone)]
+pub struct SettingsPageTemplate {}
+
+impl_renderable!(
+  CreatePageTemplate => "pages/createPage.html",
+  SearchPageTemplate => "pages/searchPage.html",
+  ImportPageTemplate => "pages/importPage.html",
+  SettingsPageTemplate => "pages/settingsPage.html",
+);
\ No newline at end of file
@@ -0,0 +1,406 @@
+use crate::{error::TlsError, models::tls::CertificateFiles};
+
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose, SanType};
+use rustls::{
+  client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+  crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+  pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+  ClientConfig, DigitallySignedStruct, ServerConfig, SignatureScheme,
+};
+use std::{
+  fs::{self, OpenOptions},
+  io::{self, Write},
+  net::{IpAddr, SocketAddr},
+  path::{Path, PathBuf},
+  sync::Arc,
+  time::Duration,
+};
+use time::OffsetDateTime;
+use tokio::{net::{TcpListener, TcpStream}, sync::mpsc};
+use tokio_rustls::{client, server::TlsStream, TlsAcceptor, TlsConnector};
+use tracing::debug;
+
+/// The directory within the platform data directory the certificates are generated in, e.g. `~/.local/share/shortcut/tls` on Linux.
+const TLS_DIR: &str = "shortcut/tls";
+const CA_FILE: &str = "ca.pem";
+const CA_KEY_FILE: &str = "ca-key.pem";
+const CERT_FILE: &str = "cert.pem";
+const KEY_FILE: &str = "key.pem";
+/// How long the local CA is valid for.
+const CA_DAYS: u32 = 3650;
+/// How long a certificate is valid for, the most that browsers accept.
+const CERT_DAYS: u32 = 825;
+/// How long a client has to finish the handshake, so a slow one doesn't hold a connection open.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many connections that finished the handshake can wait for the server to take them.
+const ACCEPTED_BACKLOG: usize = 64;
+
+/// Where the certificates are generated by default, `None` if the platform has no data directory.
+pub fn tls_dir() -> Option<PathBuf> {
+  dirs::data_dir().map(|directory| directory.join(TLS_DIR))
+}
+
+/// The cryptography TLS is served and probed with, given explicitly so it doesn't depend on the process-wide default.
+fn provider() -> Arc<CryptoProvider> {
+  Arc::new(ring::default_provider())
+}
+
+/// The parameters every certificate starts from, named `name` and valid from now for `days`.
+///
+/// The serial number is left to `rcgen`, which derives it from the new key, so browsers don't mistake a new certificate for one they've seen.
+fn certificate_params(name: &str, days: u32) -> CertificateParams {
+  let mut params: CertificateParams = CertificateParams::default();
+  params.distinguished_name = DistinguishedName::new();
+  params.distinguished_name.push(DnType::CommonName, name);
+  params.not_before = OffsetDateTime::now_utc();
+  params.not_after = params.not_before + time::Duration::days(days.into());
+
+  params
+}
+
+/// A CA that can only sign certificates for servers, not other CAs.
+fn create_ca() -> Result<(Certificate, KeyPair), rcgen::Error> {
+  let key: KeyPair = KeyPair::generate()?;
+  let mut params: CertificateParams = certificate_params("shortcut local CA", CA_DAYS);
+  params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+  params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+
+  Ok((params.self_signed(&key)?, key))
+}
+
+/// A server certificate for the hostnames (or IP addresses), signed by the CA.
+fn create_certificate(ca: &Certificate, ca_key: &KeyPair, hostnames: &[String]) -> Result<(Certificate, KeyPair), TlsError> {
+  let generation_failed = |err: rcgen::Error| TlsError::FailedToGenerate(err.to_string());
+  let mut params: CertificateParams = certificate_params(&hostnames[0], CERT_DAYS);
+  params.is_ca = IsCa::ExplicitNoCa;
+  params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+  params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+  params.use_authority_key_identifier_extension = true;
+  params.subject_alt_names = hostnames.iter()
+    .map(|hostname| match hostname.parse::<IpAddr>() {
+      Ok(address) => Ok(SanType::IpAddress(address)),
+      Err(_) => hostname.as_str().try_into().map(SanType::DnsName).map_err(|_| TlsError::InvalidHostname(hostname.clone())),
+    })
+    .collect::<Result<_, _>>()?;
+
+  let key: KeyPair = KeyPair::generate().map_err(generation_failed)?;
+  let cert: Certificate = params.signed_by(&key, ca, ca_key).map_err(generation_failed)?;
+
+  Ok((cert, key))
+}
+
+/// Reads the CA generated by an earlier run, `None` if there isn't one.
+fn read_ca(directory: &Path) -> Result<Option<(Certificate, KeyPair)>, TlsError> {
+  let (cert_path, key_path) = (directory.join(CA_FILE), directory.join(CA_KEY_FILE));
+  if !cert_path.exists() || !key_path.exists() {
+    return Ok(None);
+  }
+
+  let failed = |path: &Path, err: String| TlsError::FailedToRead(path.display().to_string(), err);
+  let read = |path: &Path| fs::read_to_string(path).map_err(|err| failed(path, err.to_string()));
+  let key: KeyPair = KeyPair::from_pem(&read(&key_path)?).map_err(|err| failed(&key_path, err.to_string()))?;
+  // Only the subject and key identifier of the CA go into the certificates it signs, so it's signed again in memory rather than kept as it was read.
+  let cert: Certificate = CertificateParams::from_ca_cert_pem(&read(&cert_path)?)
+    .and_then(|params| params.self_signed(&key))
+    .map_err(|err| failed(&cert_path, err.to_string()))?;
+
+  Ok(Some((cert, key)))
+}
+
+/// Writes a PEM file, only readable by the current user when it's a private key.
+///
+/// A private key is written to a new file created with those permissions, so it's never readable by others, even for a moment.
+fn write_pem(path: &Path, contents: &str, private: bool) -> Result<(), TlsError> {
+  let failed = |err: io::Error| TlsError::FailedToWrite(path.display().to_string(), err.to_string());
+  let mut options: OpenOptions = OpenOptions::new();
+  options.write(true);
+
+  if private {
+    match fs::remove_file(path) {
+      Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(failed(err)),
+      _ => {},
+    }
+    options.create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+  } else {
+    options.create(true).truncate(true);
+  }
+
+  options.open(path)
+    .and_then(|mut file| file.write_all(contents.as_bytes()))
+    .map_err(failed)
+}
+
+/// Generates a certificate for the hostnames, signed by a local CA that is created the first time and reused after,
+/// so the CA only has to be trusted once.
+///
+/// # Parameters
+/// - `directory`: Where to write the files, see `tls_dir`.
+/// - `hostnames`: The hostnames or IP addresses to serve, e.g. `go.internal` and `localhost`, the first is the common name.
+///
+/// # Returns
+/// - `Result<CertificateFiles, TlsError>`, will be the files written, or a `TlsError` if a hostname is invalid or a file couldn't be read or written.
+pub fn generate(directory: &Path, hostnames: &[String]) -> Result<CertificateFiles, TlsError> {
+  if hostnames.is_empty() {
+    return Err(TlsError::InvalidHostname(String::new()));
+  }
+  if let Some(hostname) = hostnames.iter().find(|hostname| hostname.is_empty() || hostname.contains(|c: char| c.is_whitespace() || c == '/')) {
+    return Err(TlsError::InvalidHostname(hostname.clone()));
+  }
+  fs::create_dir_all(directory).map_err(|err| TlsError::FailedToWrite(directory.display().to_string(), err.to_string()))?;
+
+  let (ca, ca_key, ca_created) = match read_ca(directory)? {
+    Some((ca, ca_key)) => (ca, ca_key, false),
+    None => {
+      let (ca, ca_key) = create_ca().map_err(|err| TlsError::FailedToGenerate(err.to_string()))?;
+      write_pem(&directory.join(CA_KEY_FILE), &ca_key.serialize_pem(), true)?;
+      write_pem(&directory.join(CA_FILE), &ca.pem(), false)?;
+      (ca, ca_key, true)
+    },
+  };
+
+  let (cert, key) = create_certificate(&ca, &ca_key, hostnames)?;
+  write_pem(&directory.join(KEY_FILE), &key.serialize_pem(), true)?;
+  write_pem(&directory.join(CERT_FILE), &cert.pem(), false)?;
+
+  Ok(CertificateFiles {
+    ca: directory.join(CA_FILE),
+    ca_created,
+    cert: directory.join(CERT_FILE),
+    key: directory.join(KEY_FILE),
+    hostnames: hostnames.to_vec(),
+    valid_days: CERT_DAYS,
+  })
+}
+
+/// Loads the certificate and private key to serve HTTPS with, checking they belong together.
+///
+/// # Parameters
+/// - `cert`: The PEM certificate, followed by any intermediate certificates.
+/// - `key`: The PEM private key of the certificate.
+///
+/// # Returns
+/// - `Result<TlsAcceptor, TlsError>`, will be the acceptor, or `TlsError::FailedToRead` naming the file that couldn't be used.
+pub fn acceptor(cert: &Path, key: &Path) -> Result<TlsAcceptor, TlsError> {
+  let failed = |path: &Path, err: String| TlsError::FailedToRead(path.display().to_string(), err);
+
+  let chain: Vec<CertificateDer<'static>> = CertificateDer::pem_file_iter(cert)
+    .and_then(|certs| certs.collect())
+    .map_err(|err| failed(cert, err.to_string()))?;
+  if chain.is_empty() {
+    return Err(failed(cert, "no certificate found".to_owned()));
+  }
+  let private_key: PrivateKeyDer<'static> = PrivateKeyDer::from_pem_file(key).map_err(|err| failed(key, err.to_string()))?;
+
+  let mut config: ServerConfig = ServerConfig::builder_with_provider(provider())
+    .with_safe_default_protocol_versions()
+    .map_err(|err| failed(cert, err.to_string()))?
+    .with_no_client_auth()
+    .with_single_cert(chain, private_key)
+    .map_err(|err| failed(key, err.to_string()))?;
+  // The server only speaks HTTP/1.
+  config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+  Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Accepts whatever certificate the server has, as the probe only checks whether a server of ours is answering, not which.
+#[derive(Debug)]
+struct AnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AnyCertificate {
+  fn verify_server_cert(&self, _: &CertificateDer<'_>, _: &[CertificateDer<'_>], _: &ServerName<'_>, _: &[u8], _: UnixTime) -> Result<ServerCertVerified, rustls::Error> {
+    Ok(ServerCertVerified::assertion())
+  }
+
+  fn verify_tls12_signature(&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+    verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+  }
+
+  fn verify_tls13_signature(&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+    verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+  }
+
+  fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+    self.0.signature_verification_algorithms.supported_schemes()
+  }
+}
+
+/// Connects without checking the certificate, only to probe whether a server of ours is answering, see `instance::probe`.
+pub async fn connect_unverified(stream: TcpStream) -> io::Result<client::TlsStream<TcpStream>> {
+  let config: ClientConfig = ClientConfig::builder_with_provider(provider())
+    .with_safe_default_protocol_versions()
+    .map_err(io::Error::other)?
+    .dangerous()
+    .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider())))
+    .with_no_client_auth();
+
+  TlsConnector::from(Arc::new(config)).connect(ServerName::try_from("localhost").map_err(io::Error::other)?, stream).await
+}
+
+/// Accepts TCP connections, handing them to the server once the TLS handshake is done.
+///
+/// The handshakes run in tasks of their own, so a client that is slow to finish one doesn't hold up the rest.
+pub struct TlsListener {
+  accepted: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+  address: SocketAddr,
+}
+
+impl TlsListener {
+  /// Starts accepting connections, until the listener is dropped.
+  pub fn new(listener: TcpListener, acceptor: TlsAcceptor) -> io::Result<Self> {
+    let address: SocketAddr = listener.local_addr()?;
+    let (sender, accepted) = mpsc::channel(ACCEPTED_BACKLOG);
+    tokio::spawn(accept_connections(listener, acceptor, sender));
+
+    Ok(Self { accepted, address })
+  }
+
+  pub fn local_addr(&self) -> SocketAddr {
+    self.address
+  }
+}
+
+/// Accepts connections and runs their handshakes, until the `TlsListener` is dropped.
+async fn accept_connections(mut listener: TcpListener, acceptor: TlsAcceptor, sender: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>) {
+  loop {
+    let (stream, address) = tokio::select! {
+      _ = sender.closed() => return,
+      // Retries errors such as running out of file descriptors, as the server does.
+      accepted = axum::serve::Listener::accept(&mut listener) => accepted,
+    };
+
+    let (acceptor, sender) = (acceptor.clone(), sender.clone());
+    tokio::spawn(async move {
+      match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+        Ok(Ok(stream)) => {
+          let _ = sender.send((stream, address)).await;
+        },
+        Ok(Err(err)) => debug!("TLS handshake with {} failed: {}", address, err),
+        Err(_) => debug!("TLS handshake with {} timed out.", address),
+      }
+    });
+  }
+}
+
+impl axum::serve::Listener for TlsListener {
+  type Io = TlsStream<TcpStream>;
+  type Addr = SocketAddr;
+
+  async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+    match self.accepted.recv().await {
+      Some(accepted) => accepted,
+      // Only once the accepting task has stopped, which it doesn't while the listener is alive.
+      None => std::future::pending().await,
+    }
+  }
+
+  fn local_addr(&self) -> io::Result<Self::Addr> {
+    Ok(self.address)
+  }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod tls_tests {
+  use std::{fs, path::PathBuf};
+
+  /// An empty directory of its own for each test.
+  fn test_directory(name: &str) -> PathBuf {
+    let directory: PathBuf = std::env::temp_dir().join(format!("shortcut-tls-tests-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+
+    directory
+  }
+
+  mod generate_tests {
+    use rustls::{
+      client::{danger::ServerCertVerifier, WebPkiServerVerifier},
+      pki_types::{pem::PemObject, CertificateDer, ServerName, UnixTime},
+      RootCertStore,
+    };
+    use std::{fs, sync::Arc};
+
+    use crate::{error::TlsError, tls::{generate, provider}};
+
+    use super::test_directory;
+
+    #[test]
+    fn returns_certificate_signed_by_reused_ca() {
+      let directory = test_directory("generate");
+      let hostnames = vec!["go.internal".to_owned(), "127.0.0.1".to_owned()];
+
+      let first = generate(&directory, &hostnames).unwrap();
+      let ca = fs::read(&first.ca).unwrap();
+      let second = generate(&directory, &hostnames).unwrap();
+
+      assert!(first.ca_created);
+      assert!(!second.ca_created);
+      assert_eq!(fs::read(&second.ca).unwrap(), ca);
+      let mut roots = RootCertStore::empty();
+      roots.add(CertificateDer::from_pem_slice(&ca).unwrap()).unwrap();
+      let verifier = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider()).build().unwrap();
+      let cert = CertificateDer::from_pem_file(&second.cert).unwrap();
+      let verify = |name: &'static str| verifier.verify_server_cert(&cert, &[], &ServerName::try_from(name).unwrap(), &[], UnixTime::now());
+      assert!(verify("go.internal").is_ok());
+      assert!(verify("127.0.0.1").is_ok());
+      assert!(verify("other.internal").is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn returns_keys_only_readable_by_owner() {
+      use std::os::unix::fs::PermissionsExt;
+
+      let directory = test_directory("permissions");
+      let files = generate(&directory, &["localhost".to_owned()]).unwrap();
+      generate(&directory, &["localhost".to_owned()]).unwrap();
+
+      for key in [directory.join("ca-key.pem"), files.key] {
+        assert_eq!(fs::metadata(key).unwrap().permissions().mode() & 0o777, 0o600);
+      }
+    }
+
+    #[test]
+    fn returns_exception_when_hostname_invalid() {
+      let result = generate(&test_directory("invalid"), &["go internal".to_owned()]);
+
+      assert!(matches!(result, Err(TlsError::InvalidHostname(hostname)) if hostname == "go internal"));
+    }
+  }
+
+  mod listener_tests {
+    use axum::{routing::get, Router};
+    use rustls::{pki_types::{pem::PemObject, CertificateDer, ServerName}, ClientConfig, RootCertStore};
+    use std::sync::Arc;
+    use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream}};
+    use tokio_rustls::TlsConnector;
+
+    use crate::tls::{acceptor, generate, provider, TlsListener};
+
+    use super::test_directory;
+
+    #[tokio::test]
+    async fn serves_https_trusted_through_generated_ca() {
+      let files = generate(&test_directory("listener"), &["localhost".to_owned()]).unwrap();
+      let listener = TlsListener::new(TcpListener::bind("127.0.0.1:0").await.unwrap(), acceptor(&files.cert, &files.key).unwrap()).unwrap();
+      let address = listener.local_addr();
+      tokio::spawn(async move { axum::serve(listener, Router::new().route("/", get(|| async { "secure" }))).await });
+      let mut roots = RootCertStore::empty();
+      roots.add(CertificateDer::from_pem_file(&files.ca).unwrap()).unwrap();
+      let config = ClientConfig::builder_with_provider(provider()).with_safe_default_protocol_versions().unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+      let tcp = TcpStream::connect(address).await.unwrap();
+      let mut stream = TlsConnector::from(Arc::new(config)).connect(ServerName::try_from("localhost").unwrap(), tcp).await.unwrap();
+      stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+      let mut response = String::new();
+      stream.read_to_string(&mut response).await.unwrap();
+
+      assert!(response.starts_with("HTTP/1.1 200 OK"));
+      assert!(response.ends_with("secure"));
+    }
+  }
+}
\ No newline at end of file
@@ -0,0 +1,208 @@
+use crate::state::AppState;
+
+use axum::{
+  extract::Path as UrlPath,
+  http::{header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH}, HeaderMap, StatusCode},
+  response::{IntoResponse, Response},
+  Extension,
+};
+use rust_embed::RustEmbed;
+use std::{collections::BTreeMap, fs, io, path::{Component, Path, PathBuf}};
+use tera::Tera;
+use tracing::error;
+
+/// The templates, embedded in release builds and read from `ui/templates` of the source tree in debug builds.
+#[derive(RustEmbed)]
+#[folder = "ui/templates/"]
+struct Templates;
+
+/// The static assets served at `/assets`, embedded the same way as `Templates`.
+#[derive(RustEmbed)]
+#[folder = "ui/assets/"]
+struct Assets;
+
+/// The directories of a theme, each file in them replaces the built-in file with the same path.
+const THEME_TEMPLATES: &str = "templates";
+const THEME_ASSETS: &str = "assets";
+
+/// The directory the built-in templates are read from in debug builds, watched for hot reload.
+#[cfg(debug_assertions)]
+pub const TEMPLATES_SOURCE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/ui/templates");
+
+/// Every file under the directory, keyed by its path relative to it with `/` separators.
+fn files(directory: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+  let mut found: Vec<(String, PathBuf)> = vec![];
+  let mut pending: Vec<PathBuf> = vec![directory.to_path_buf()];
+
+  while let Some(current) = pending.pop() {
+    for entry in fs::read_dir(&current)? {
+      let path: PathBuf = entry?.path();
+
+      if path.is_dir() {
+        pending.push(path);
+      } else if let Ok(relative) = path.strip_prefix(directory) {
+        let name: Vec<String> = relative.components().map(|component| component.as_os_str().to_string_lossy().into_owned()).collect();
+        found.push((name.join("/"), path));
+      }
+    }
+  }
+
+  Ok(found)
+}
+
+/// The theme's templates directory, if the theme has one.
+pub fn theme_templates(theme_dir: Option<&Path>) -> Option<PathBuf> {
+  theme_dir.map(|directory| directory.join(THEME_TEMPLATES)).filter(|directory| directory.is_dir())
+}
+
+/// Loads the built-in templates, replacing any with the same path in the theme.
+///
+/// # Parameters
+/// - `theme_dir`: The theme directory, whose `templates` directory overrides the built-in templates.
+///
+/// # Returns
+/// - `tera::Result<Tera>`, will be the compiled templates, or the error reading or compiling them.
+pub fn load_templates(theme_dir: Option<&Path>) -> tera::Result<Tera> {
+  let mut templates: BTreeMap<String, String> = BTreeMap::new();
+
+  for name in Templates::iter() {
+    if let Some(file) = Templates::get(&name) {
+      let contents: String = String::from_utf8(file.data.into_owned())
+        .map_err(|err| tera::Error::msg(format!("Template {} is not UTF-8: {}", name, err)))?;
+      templates.insert(name.into_owned(), contents);
+    }
+  }
+
+  if let Some(directory) = theme_templates(theme_dir) {
+    let overrides = files(&directory).map_err(|err| tera::Error::msg(format!("Failed to read {}: {}", directory.display(), err)))?;
+
+    for (name, path) in overrides {
+      let contents: String = fs::read_to_string(&path)
+        .map_err(|err| tera::Error::msg(format!("Failed to read {}: {}", path.display(), err)))?;
+      templates.insert(name, contents);
+    }
+  }
+
+  let mut tera: Tera = Tera::default();
+  tera.add_raw_templates(templates)?;
+
+  Ok(tera)
+}
+
+/// The file for the asset path within the directory, `None` if the path tries to leave it, e.g. `../config.toml`.
+fn theme_file(directory: &Path, path: &str) -> Option<PathBuf> {
+  let relative: &Path = Path::new(path);
+
+  relative.components()
+    .all(|component| matches!(component, Component::Normal(_)))
+    .then(|| directory.join(relative))
+    .filter(|file| file.is_file())
+}
+
+/// This is the function for the `/assets/{*path}` endpoint.
+///
+/// It serves the theme's copy of the asset if there is one, otherwise the built-in asset,
+/// with an `ETag` so unchanged built-in assets are only sent once.
+pub async fn serve_asset(
+  Extension(app): Extension<AppState>,
+  UrlPath(path): UrlPath<String>,
+  headers: HeaderMap,
+) -> Response {
+  let themed: Option<PathBuf> = app.config.theme_dir.as_deref().and_then(|theme| theme_file(&theme.join(THEME_ASSETS), &path));
+
+  if let Some(file) = themed {
+    return match tokio::fs::read(&file).await {
+      Ok(contents) => ([(CONTENT_TYPE, mime_guess::from_path(&file).first_or_octet_stream().to_string())], contents).into_response(),
+      Err(err) => {
+        error!("Failed to read asset ({}): {}", file.display(), err);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+      },
+    };
+  }
+
+  let Some(file) = Assets::get(&path) else {
+    return StatusCode::NOT_FOUND.into_response();
+  };
+
+  let etag: String = format!("\"{}\"", file.metadata.sha256_hash().iter().map(|byte| format!("{:02x}", byte)).collect::<String>());
+  if headers.get(IF_NONE_MATCH).is_some_and(|value| value.as_bytes() == etag.as_bytes()) {
+    return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+  }
+
+  ([(CONTENT_TYPE, file.metadata.mimetype().to_owned()), (ETAG, etag)], file.data.into_owned()).into_response()
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod ui_tests {
+  use std::{fs, path::PathBuf};
+
+  fn theme(name: &str) -> PathBuf {
+    let directory: PathBuf = std::env::temp_dir().join(format!("shortcut-ui-tests-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(directory.join("templates/components/alert")).unwrap();
+    fs::create_dir_all(directory.join("assets")).unwrap();
+
+    directory
+  }
+
+  mod load_templates_tests {
+    use tera::Context;
+
+    use crate::ui::load_templates;
+
+    use super::theme;
+
+    #[test]
+    fn returns_built_in_templates() {
+      let tera = load_templates(None).unwrap();
+
+      assert!(tera.get_template_names().any(|name| name == "pages/searchPage.html"));
+    }
+
+    #[test]
+    fn returns_theme_template_over_built_in() {
+      let directory = theme("override");
+      std::fs::write(directory.join("templates/components/alert/error.html"), "Oops: {{ error }}").unwrap();
+
+      let tera = load_templates(Some(&directory)).unwrap();
+      let mut context = Context::new();
+      context.insert("error", "<b>");
+
+      assert_eq!(tera.render("components/alert/error.html", &context).unwrap(), "Oops: &lt;b&gt;");
+      assert!(tera.get_template_names().any(|name| name == "pages/searchPage.html"));
+    }
+
+    #[test]
+    fn returns_exception_when_theme_template_invalid() {
+      let directory = theme("invalid");
+      std::fs::write(directory.join("templates/components/alert/error.html"), "{% if %}").unwrap();
+
+      assert!(load_templates(Some(&directory)).is_err());
+    }
+  }
+
+  mod theme_file_tests {
+    use crate::ui::theme_file;
+
+    use super::theme;
+
+    #[test]
+    fn returns_file_within_theme() {
+      let directory = theme("assets");
+      std::fs::write(directory.join("assets/main.css"), "body {}").unwrap();
+
+      assert_eq!(theme_file(&directory.join("assets"), "main.css"), Some(directory.join("assets/main.css")));
+      assert_eq!(theme_file(&directory.join("assets"), "missing.css"), None);
+    }
+
+    #[test]
+    fn returns_none_when_path_leaves_theme() {
+      let directory = theme("traversal");
+      std::fs::write(directory.join("secret.txt"), "secret").unwrap();
+
+      assert_eq!(theme_file(&directory.join("assets"), "../secret.txt"), None);
+      assert_eq!(theme_file(&directory.join("assets"), "/etc/passwd"), None);
+    }
+  }
+}
\ No newline at end of file
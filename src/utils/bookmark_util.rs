@@ -0,0 +1,282 @@
+use crate::{error::ShortcutError, models::shortcut::{Shortcut, QUERY_PLACEHOLDER}, utils::system_util::get_timestamp};
+
+use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection, SqliteConnection};
+use std::{fs, path::PathBuf, sync::atomic::{AtomicUsize, Ordering}};
+use tracing::error;
+use url::Url;
+
+/// Numbers the temporary copies of uploaded browser databases, so concurrent imports never share a file.
+static NEXT_DATABASE: AtomicUsize = AtomicUsize::new(0);
+
+/// The browsers whose keyword databases can be imported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Browser {
+  Chrome,
+  Firefox,
+}
+
+impl Browser {
+  /// The query listing every keyword and its url, which differs between the browsers' schemas.
+  fn keywords_query(&self) -> &'static str {
+    match self {
+      Browser::Chrome => "SELECT keyword, url FROM keywords ORDER BY id;",
+      Browser::Firefox => "SELECT k.keyword, p.url FROM moz_keywords k JOIN moz_places p ON p.id = k.place_id ORDER BY k.id;",
+    }
+  }
+}
+
+/// Converts the search placeholders of the browsers into `QUERY_PLACEHOLDER`.
+///
+/// Firefox uses `%s` (and `%S` for an unencoded query) while Chrome uses `{searchTerms}`.
+pub fn convert_placeholders(url: &str) -> String {
+  url.replace("%s", QUERY_PLACEHOLDER)
+    .replace("%S", QUERY_PLACEHOLDER)
+    .replace("{searchTerms}", QUERY_PLACEHOLDER)
+}
+
+/// Creates the imported `Shortcut`, or `None` if the url isn't a web page e.g. Chrome's `{google:baseURL}` or Firefox's `place:` queries.
+fn to_shortcut(keyword: &str, url: &str, created: Option<i64>, updated: Option<i64>) -> Option<Shortcut> {
+  let keyword: &str = keyword.trim();
+  let url: String = convert_placeholders(url.trim());
+
+  let is_web_page: bool = Url::parse(&url).is_ok_and(|parsed| matches!(parsed.scheme(), "http" | "https"));
+  if keyword.is_empty() || !is_web_page {
+    return None;
+  }
+
+  let mut shortcut: Shortcut = Shortcut::new(keyword.to_owned(), url);
+  shortcut.created = created.or(shortcut.created);
+  shortcut.updated = updated.or(created).or(shortcut.updated);
+
+  Some(shortcut)
+}
+
+/// Decodes the few HTML entities browsers write into bookmark attributes.
+fn decode_entities(value: &str) -> String {
+  value.replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&#39;", "'")
+    .replace("&#x27;", "'")
+    .replace("&amp;", "&")
+}
+
+/// Splits the inside of a tag, e.g. `A HREF="..." SHORTCUTURL=g`, into its uppercased attribute names and values.
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+  let mut attributes: Vec<(String, String)> = vec![];
+  let mut rest: &str = tag.trim_start();
+
+  while !rest.is_empty() {
+    let name_end: usize = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+    let name: String = rest[..name_end].to_uppercase();
+    rest = rest[name_end..].trim_start();
+
+    let value: String = if let Some(after_equals) = rest.strip_prefix('=') {
+      let after_equals: &str = after_equals.trim_start();
+
+      match after_equals.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+          let inner: &str = &after_equals[1..];
+          let value_end: usize = inner.find(quote).unwrap_or(inner.len());
+
+          rest = inner.get(value_end + 1..).unwrap_or("");
+          inner[..value_end].to_owned()
+        },
+        _ => {
+          let value_end: usize = after_equals.find(char::is_whitespace).unwrap_or(after_equals.len());
+
+          rest = &after_equals[value_end..];
+          after_equals[..value_end].to_owned()
+        },
+      }
+    } else {
+      String::new()
+    };
+
+    if !name.is_empty() {
+      attributes.push((name, decode_entities(&value)));
+    }
+    rest = rest.trim_start();
+  }
+
+  attributes
+}
+
+/// Parses a Netscape bookmark file into `Shortcut`'s.
+///
+/// Only bookmarks with a keyword are imported, given as `SHORTCUTURL` by Firefox or `KEYWORD` by other exporters.
+/// The `ADD_DATE` and `LAST_MODIFIED` of the bookmark are kept as `created` and `updated`.
+///
+/// # Parameters
+/// - `contents`: The contents of the bookmark file.
+///
+/// # Returns
+/// - `Vec<Shortcut>` of the keyword bookmarks in file order.
+pub fn parse_netscape_bookmarks(contents: &str) -> Vec<Shortcut> {
+  // ASCII lowercasing keeps every byte offset the same, so positions found here can be used on `contents`.
+  let lowercase: String = contents.to_ascii_lowercase();
+  let mut shortcuts: Vec<Shortcut> = vec![];
+  let mut position: usize = 0;
+
+  while let Some(start) = lowercase[position..].find("<a ").map(|offset| position + offset + 3) {
+    let end: usize = lowercase[start..].find('>').map(|offset| start + offset).unwrap_or(contents.len());
+    let attributes: Vec<(String, String)> = parse_attributes(&contents[start..end]);
+    let attribute = |name: &str| attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+
+    let keyword: Option<&str> = attribute("SHORTCUTURL").or(attribute("KEYWORD"));
+    let timestamp = |name: &str| attribute(name).and_then(|value| value.parse::<i64>().ok());
+
+    if let (Some(keyword), Some(url)) = (keyword, attribute("HREF")) {
+      shortcuts.extend(to_shortcut(keyword, url, timestamp("ADD_DATE"), timestamp("LAST_MODIFIED")));
+    }
+    position = end;
+  }
+
+  shortcuts
+}
+
+/// Reads every keyword from an open browser database.
+///
+/// # Parameters
+/// - `connection`: The connection to the browser's database.
+/// - `browser`: The `Browser` the database belongs to.
+///
+/// # Returns
+/// - `Result<Vec<Shortcut>, sqlx::Error>`, will be the keywords which can be used as `Shortcut`'s, or the error from reading them.
+pub async fn read_keywords(connection: &mut SqliteConnection, browser: Browser) -> Result<Vec<Shortcut>, sqlx::Error> {
+  let rows: Vec<(String, String)> = sqlx::query_as(browser.keywords_query())
+    .fetch_all(&mut *connection)
+    .await?;
+
+  Ok(rows.iter().filter_map(|(keyword, url)| to_shortcut(keyword, url, None, None)).collect())
+}
+
+/// Reads every keyword from an uploaded browser database.
+///
+/// SQLite can only open files, so the upload is written to a temporary file which is removed once read.
+/// Browsers lock these databases while running, so they should be copied (or the browser closed) before uploading.
+///
+/// # Parameters
+/// - `contents`: The bytes of the uploaded database.
+/// - `browser`: The `Browser` the database belongs to.
+///
+/// # Returns
+/// - `Result<Vec<Shortcut>, ShortcutError>`, will be the imported `Shortcut`'s, or `ShortcutError::InvalidImport` if the database can't be read.
+pub async fn read_browser_database(contents: &[u8], browser: Browser) -> Result<Vec<Shortcut>, ShortcutError> {
+  let path: PathBuf = std::env::temp_dir().join(format!(
+    "shortcut-import-{}-{}-{}.sqlite", 
+    std::process::id(), 
+    get_timestamp(), 
+    NEXT_DATABASE.fetch_add(1, Ordering::Relaxed),
+  ));
+
+  let result: Result<Vec<Shortcut>, sqlx::Error> = async {
+    fs::write(&path, contents)?;
+
+    let mut connection: SqliteConnection = SqliteConnectOptions::new()
+      .filename(&path)
+      .read_only(true)
+      .connect()
+      .await?;
+    let shortcuts: Result<Vec<Shortcut>, sqlx::Error> = read_keywords(&mut connection, browser).await;
+
+    connection.close().await?;
+    shortcuts
+  }.await;
+
+  let _ = fs::remove_file(&path);
+
+  result.map_err(|err| {
+    error!("Failed to read {:?} keywords: {}", browser, err);
+    ShortcutError::InvalidImport
+  })
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod bookmark_util_tests {
+  use sqlx::{Connection, Executor, SqliteConnection};
+
+  use super::{read_keywords, Browser};
+
+  async fn fixture_database(script: &str) -> SqliteConnection {
+    let mut connection: SqliteConnection = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+    connection.execute(script).await.unwrap();
+
+    connection
+  }
+
+  mod parse_netscape_bookmarks_tests {
+    use crate::utils::bookmark_util::parse_netscape_bookmarks;
+
+    const BOOKMARKS: &str = include_str!("../../fixtures/import/bookmarks.html");
+
+    #[test]
+    fn returns_only_bookmarks_with_keywords() {
+      let result = parse_netscape_bookmarks(BOOKMARKS);
+      let keywords: Vec<&str> = result.iter().map(|shortcut| shortcut.keyword.as_str()).collect();
+
+      assert_eq!(keywords, vec!["gh", "w", "mdn"]);
+    }
+
+    #[test]
+    fn returns_converted_placeholders_and_decoded_urls() {
+      let result = parse_netscape_bookmarks(BOOKMARKS);
+
+      assert_eq!(result[0].url, "https://github.com/search?q={query}&type=repositories");
+      assert_eq!(result[1].url, "https://en.wikipedia.org/wiki/Special:Search?search={query}");
+      assert_eq!(result[2].url, "https://developer.mozilla.org/");
+    }
+
+    #[test]
+    fn returns_bookmark_dates_as_timestamps() {
+      let result = parse_netscape_bookmarks(BOOKMARKS);
+
+      assert_eq!((result[0].created, result[0].updated), (Some(1700000000), Some(1700000500)));
+      assert_eq!((result[1].created, result[1].updated), (Some(1700001000), Some(1700001000)));
+    }
+
+    #[test]
+    fn returns_nothing_when_not_bookmarks() {
+      let result = parse_netscape_bookmarks("keyword,url\ngoogle,https://google.co.uk");
+
+      assert!(result.is_empty());
+    }
+  }
+
+  mod read_keywords_tests {
+    use super::{fixture_database, read_keywords, Browser};
+
+    #[tokio::test]
+    async fn returns_chrome_keywords_without_browser_only_urls() {
+      let mut connection = fixture_database(include_str!("../../fixtures/import/chrome_web_data.sql")).await;
+      let result = read_keywords(&mut connection, Browser::Chrome).await.unwrap();
+      let shortcuts: Vec<(&str, &str)> = result.iter().map(|shortcut| (shortcut.keyword.as_str(), shortcut.url.as_str())).collect();
+
+      assert_eq!(shortcuts, vec![
+        ("yt", "https://www.youtube.com/results?search_query={query}"),
+        ("crates", "https://crates.io/search?q={query}"),
+      ]);
+    }
+
+    #[tokio::test]
+    async fn returns_firefox_keywords_with_their_places() {
+      let mut connection = fixture_database(include_str!("../../fixtures/import/firefox_places.sql")).await;
+      let result = read_keywords(&mut connection, Browser::Firefox).await.unwrap();
+      let shortcuts: Vec<(&str, &str)> = result.iter().map(|shortcut| (shortcut.keyword.as_str(), shortcut.url.as_str())).collect();
+
+      assert_eq!(shortcuts, vec![
+        ("rs", "https://doc.rust-lang.org/std/?search={query}"),
+        ("ddg", "https://duckduckgo.com/?q={query}"),
+      ]);
+    }
+
+    #[tokio::test]
+    async fn returns_error_when_not_browser_database() {
+      let mut connection = fixture_database("CREATE TABLE shortcut (keyword TEXT);").await;
+      let result = read_keywords(&mut connection, Browser::Firefox).await;
+
+      assert!(result.is_err());
+    }
+  }
+}
\ No newline at end of file
@@ -0,0 +1,254 @@
+use crate::{
+  error::ShortcutError, 
+  models::{shortcut::Shortcut, transfer::{FileFormat, ImportFormat}}, 
+  utils::bookmark_util::{parse_netscape_bookmarks, read_browser_database, Browser},
+};
+
+use async_stream::try_stream;
+use futures::{stream::BoxStream, Stream, TryStreamExt};
+use tracing::error;
+
+/// The header row of a CSV export, in the same order as the fields of `Shortcut`.
+const CSV_HEADER: &str = "id,created,updated,keyword,url,version,managed\n";
+
+/// Serializes a single `Shortcut` as one row/entry of the given format.
+///
+/// JSON entries are written as elements of a top-level array, so every entry but the first is prefixed with a comma.
+fn serialize_row(format: FileFormat, shortcut: &Shortcut, first: bool) -> Result<String, ShortcutError> {
+  let result = match format {
+    FileFormat::Json => serde_json::to_string(shortcut)
+      .map(|json| format!("{}\n  {}", if first { "" } else { "," }, json))
+      .map_err(|err| err.to_string()),
+    FileFormat::Csv => {
+      let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(vec![]);
+
+      writer.serialize(shortcut)
+        .map_err(|err| err.to_string())
+        .and_then(|_| writer.into_inner().map_err(|err| err.to_string()))
+        .and_then(|bytes| String::from_utf8(bytes).map_err(|err| err.to_string()))
+    },
+    FileFormat::Yaml => serde_yaml::to_string(&[shortcut]).map_err(|err| err.to_string()),
+  };
+
+  result.map_err(|err| {
+    error!("Failed to serialize shortcut ({}) as {:?}: {}", shortcut.keyword, format, err);
+    ShortcutError::FailedToExport
+  })
+}
+
+/// Turns a stream of `Shortcut`'s into a stream of chunks of the given format, so a whole export never has to be held in memory.
+///
+/// # Parameters
+/// - `format`: The `FileFormat` to write.
+/// - `shortcuts`: The stream of `Shortcut`'s to write.
+///
+/// # Returns
+/// - A stream of `String` chunks which joined together make up the exported file, or `ShortcutError` if reading or serializing fails.
+pub fn export_stream(
+  format: FileFormat,
+  mut shortcuts: BoxStream<'static, Result<Shortcut, ShortcutError>>,
+) -> impl Stream<Item = Result<String, ShortcutError>> + Send + 'static {
+  try_stream! {
+    match format {
+      FileFormat::Json => yield "[".to_owned(),
+      FileFormat::Csv => yield CSV_HEADER.to_owned(),
+      FileFormat::Yaml => {},
+    }
+
+    let mut count: usize = 0;
+    while let Some(shortcut) = shortcuts.try_next().await? {
+      yield serialize_row(format, &shortcut, count == 0)?;
+      count += 1;
+    }
+
+    match format {
+      FileFormat::Json => yield "\n]\n".to_owned(),
+      FileFormat::Yaml if count == 0 => yield "[]\n".to_owned(),
+      _ => {},
+    }
+  }
+}
+
+/// Parses the contents of an import file into `Shortcut`'s.
+///
+/// Only `keyword` and `url` are required, every other field is optional so hand-written files stay simple e.g. a CSV of `keyword,url`.
+///
+/// # Parameters
+/// - `format`: The `FileFormat` of the contents.
+/// - `contents`: The contents of the file.
+///
+/// # Returns
+/// - `Result<Vec<Shortcut>, ShortcutError>`, will be the parsed `Shortcut`'s in file order, or `ShortcutError::InvalidImport` if the file can't be read.
+pub fn parse_shortcuts(format: FileFormat, contents: &str) -> Result<Vec<Shortcut>, ShortcutError> {
+  let result: Result<Vec<Shortcut>, String> = match format {
+    FileFormat::Json => serde_json::from_str(contents).map_err(|err| err.to_string()),
+    FileFormat::Csv => csv::Reader::from_reader(contents.as_bytes())
+      .deserialize()
+      .collect::<Result<Vec<Shortcut>, _>>()
+      .map_err(|err| err.to_string()),
+    FileFormat::Yaml => serde_yaml::from_str(contents).map_err(|err| err.to_string()),
+  };
+
+  result.map_err(|err| {
+    error!("Failed to parse import file as {:?}: {}", format, err);
+    ShortcutError::InvalidImport
+  })
+}
+
+/// Parses an uploaded import file of any `ImportFormat` into `Shortcut`'s.
+///
+/// # Parameters
+/// - `format`: The `ImportFormat` of the file.
+/// - `contents`: The bytes of the file, as browser databases aren't text.
+///
+/// # Returns
+/// - `Result<Vec<Shortcut>, ShortcutError>`, will be the parsed `Shortcut`'s in file order, or `ShortcutError::InvalidImport` if the file can't be read.
+pub async fn parse_import(format: ImportFormat, contents: &[u8]) -> Result<Vec<Shortcut>, ShortcutError> {
+  let text = || std::str::from_utf8(contents).map_err(|err| {
+    error!("Failed to read import file as {:?}: {}", format, err);
+    ShortcutError::InvalidImport
+  });
+
+  match format {
+    ImportFormat::Json => parse_shortcuts(FileFormat::Json, text()?),
+    ImportFormat::Csv => parse_shortcuts(FileFormat::Csv, text()?),
+    ImportFormat::Yaml => parse_shortcuts(FileFormat::Yaml, text()?),
+    ImportFormat::Html => Ok(parse_netscape_bookmarks(text()?)),
+    ImportFormat::Chrome => read_browser_database(contents, Browser::Chrome).await,
+    ImportFormat::Firefox => read_browser_database(contents, Browser::Firefox).await,
+  }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod format_util_tests {
+  use futures::{stream, StreamExt, TryStreamExt};
+  use lazy_static::lazy_static;
+
+  use crate::{error::ShortcutError, models::{shortcut::Shortcut, transfer::FileFormat}};
+
+  use super::{export_stream, parse_shortcuts};
+
+  lazy_static! {
+    pub static ref GOOGLE_SHORTCUT: Shortcut = Shortcut {
+        id: Some(1),
+        created: Some(1),
+        updated: Some(2),
+        keyword: "google".to_owned(),
+        url: "https://google.co.uk".to_owned(),
+        version: Some(1),
+        managed: false,
+      };
+
+    pub static ref G_SHORTCUT: Shortcut = Shortcut {
+        id: Some(2),
+        created: Some(11),
+        updated: Some(22),
+        keyword: "g".to_owned(),
+        url: "https://google.com/search?q=a,b".to_owned(),
+        version: Some(3),
+        managed: false,
+      };
+  }
+
+  async fn export(format: FileFormat, shortcuts: Vec<Shortcut>) -> String {
+    let shortcuts = stream::iter(shortcuts.into_iter().map(Ok::<Shortcut, ShortcutError>)).boxed();
+    let chunks: Vec<String> = export_stream(format, shortcuts).try_collect().await.unwrap();
+
+    chunks.concat()
+  }
+
+  mod export_stream_tests {
+    use crate::{error::ShortcutError, models::{shortcut::Shortcut, transfer::FileFormat}};
+    use futures::{stream, StreamExt, TryStreamExt};
+
+    use super::{export, export_stream, GOOGLE_SHORTCUT, G_SHORTCUT};
+
+    #[tokio::test]
+    async fn returns_json_array_when_exporting_json() {
+      let result = export(FileFormat::Json, vec![GOOGLE_SHORTCUT.to_owned(), G_SHORTCUT.to_owned()]).await;
+      let parsed: Vec<Shortcut> = serde_json::from_str(&result).unwrap();
+
+      assert_eq!(parsed, vec![GOOGLE_SHORTCUT.to_owned(), G_SHORTCUT.to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn returns_empty_json_array_when_no_shortcuts() {
+      let result = export(FileFormat::Json, vec![]).await;
+      let parsed: Vec<Shortcut> = serde_json::from_str(&result).unwrap();
+
+      assert!(parsed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn returns_quoted_csv_rows_with_header_when_exporting_csv() {
+      let result = export(FileFormat::Csv, vec![GOOGLE_SHORTCUT.to_owned(), G_SHORTCUT.to_owned()]).await;
+
+      assert_eq!(result, "id,created,updated,keyword,url,version,managed\n\
+        1,1,2,google,https://google.co.uk,1,false\n\
+        2,11,22,g,\"https://google.com/search?q=a,b\",3,false\n");
+    }
+
+    #[tokio::test]
+    async fn returns_yaml_sequence_when_exporting_yaml() {
+      let result = export(FileFormat::Yaml, vec![GOOGLE_SHORTCUT.to_owned(), G_SHORTCUT.to_owned()]).await;
+      let parsed: Vec<Shortcut> = serde_yaml::from_str(&result).unwrap();
+
+      assert_eq!(parsed, vec![GOOGLE_SHORTCUT.to_owned(), G_SHORTCUT.to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn returns_empty_yaml_sequence_when_no_shortcuts() {
+      let result = export(FileFormat::Yaml, vec![]).await;
+      let parsed: Vec<Shortcut> = serde_yaml::from_str(&result).unwrap();
+
+      assert!(parsed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn returns_error_when_reading_fails() {
+      let shortcuts = stream::iter(vec![Ok(GOOGLE_SHORTCUT.to_owned()), Err(ShortcutError::FailedToExport)]).boxed();
+      let result: Result<Vec<String>, ShortcutError> = export_stream(FileFormat::Json, shortcuts).try_collect().await;
+
+      assert!(matches!(result, Err(ShortcutError::FailedToExport)));
+    }
+  }
+
+  mod parse_shortcuts_tests {
+    use crate::{error::ShortcutError, models::{shortcut::Shortcut, transfer::FileFormat}};
+
+    use super::{export, parse_shortcuts, GOOGLE_SHORTCUT, G_SHORTCUT};
+
+    #[tokio::test]
+    async fn returns_exported_shortcuts_for_every_format() {
+      for format in [FileFormat::Json, FileFormat::Csv, FileFormat::Yaml] {
+        let contents = export(format, vec![GOOGLE_SHORTCUT.to_owned(), G_SHORTCUT.to_owned()]).await;
+        let result = parse_shortcuts(format, &contents).unwrap();
+
+        assert_eq!(result, vec![GOOGLE_SHORTCUT.to_owned(), G_SHORTCUT.to_owned()]);
+      }
+    }
+
+    #[test]
+    fn returns_shortcuts_when_only_keyword_and_url_given() {
+      let result = parse_shortcuts(FileFormat::Csv, "keyword,url\ngoogle,https://google.co.uk\n").unwrap();
+      let expected = Shortcut { id: None, created: None, updated: None, keyword: "google".to_owned(), url: "https://google.co.uk".to_owned(), version: None, managed: false };
+
+      assert_eq!(result, vec![expected]);
+    }
+
+    #[test]
+    fn returns_exception_when_url_missing() {
+      let result = parse_shortcuts(FileFormat::Yaml, "- keyword: google\n");
+
+      assert!(matches!(result, Err(ShortcutError::InvalidImport)));
+    }
+
+    #[test]
+    fn returns_exception_when_not_valid_format() {
+      let result = parse_shortcuts(FileFormat::Json, "keyword,url");
+
+      assert!(matches!(result, Err(ShortcutError::InvalidImport)));
+    }
+  }
+}
\ No newline at end of file
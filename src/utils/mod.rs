@@ -1 +1,5 @@
-pub mod system_util;
\ No newline at end of file
+pub mod bookmark_util;
+pub mod format_util;
+pub mod system_util;
+pub mod sync_util;
+pub mod url_util;
\ No newline at end of file
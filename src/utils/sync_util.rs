@@ -0,0 +1,267 @@
+use crate::{error::ShortcutError, models::{shortcut::Shortcut, sync::{ShortcutsFile, SyncChange, SyncPlan}}};
+
+use notify_debouncer_full::{
+  new_debouncer,
+  notify::{Error, EventKind, RecommendedWatcher, RecursiveMode},
+  DebounceEventResult,
+  Debouncer,
+  RecommendedCache,
+};
+use std::{collections::{BTreeMap, HashMap}, fs, path::{Path, PathBuf}, time::Duration};
+use tracing::error;
+
+/// Parses the contents of a shortcuts file, YAML or TOML depending on the extension of its name.
+///
+/// Keywords are lowercased, matching `Shortcut::from_request`.
+///
+/// # Parameters
+/// - `path`: The path of the file, only used for its extension.
+/// - `contents`: The contents of the file.
+///
+/// # Returns
+/// - `Result<BTreeMap<String, String>, ShortcutError>`, will be each declared keyword and its url,
+///   or `ShortcutError::InvalidShortcutsFile` if the file can't be read.
+pub fn parse_shortcuts_file(path: &Path, contents: &str) -> Result<BTreeMap<String, String>, ShortcutError> {
+  let extension: String = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default().to_lowercase();
+
+  let result: Result<ShortcutsFile, String> = match extension.as_str() {
+    "yaml" | "yml" => serde_yaml::from_str(contents).map_err(|err| err.to_string()),
+    "toml" => toml::from_str(contents).map_err(|err| err.to_string()),
+    _ => Err(format!("unsupported extension {:?}, expected yaml, yml or toml", extension)),
+  };
+
+  let file: ShortcutsFile = result.map_err(|err| {
+    error!("Failed to parse shortcuts file ({}): {}", path.display(), err);
+    ShortcutError::InvalidShortcutsFile
+  })?;
+
+  let mut declared: BTreeMap<String, String> = BTreeMap::new();
+  for (keyword, url) in file.shortcuts {
+    let keyword: String = keyword.trim().to_lowercase();
+    let url: String = url.trim().to_owned();
+
+    if keyword.is_empty() || url.is_empty() || declared.insert(keyword.clone(), url).is_some() {
+      error!("Failed to parse shortcuts file ({}): empty or duplicate keyword ({})", path.display(), keyword);
+      return Err(ShortcutError::InvalidShortcutsFile);
+    }
+  }
+
+  Ok(declared)
+}
+
+/// Reads and parses the shortcuts file at the given path, see `parse_shortcuts_file`.
+pub fn load_shortcuts_file(path: &Path) -> Result<BTreeMap<String, String>, ShortcutError> {
+  let contents: String = fs::read_to_string(path).map_err(|err| {
+    error!("Failed to read shortcuts file ({}): {}", path.display(), err);
+    ShortcutError::InvalidShortcutsFile
+  })?;
+
+  parse_shortcuts_file(path, &contents)
+}
+
+/// The directory of the shortcuts file, the current directory for a bare file name such as `shortcuts.yaml`.
+pub fn shortcuts_directory(path: &Path) -> &Path {
+  path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."))
+}
+
+/// Watches the shortcuts file, calling `on_change` once for each debounced batch of events that touch it.
+///
+/// Editors often save by replacing the file, so its directory is watched instead of the file itself. Only the directory
+/// is watched, not the ones within it, and events for its other files, e.g. the SQLite database and its WAL, are ignored.
+///
+/// # Parameters
+/// - `path`: The path of the shortcuts file, whose directory has to exist, see `Config::validate`.
+/// - `delay`: How long to wait for more events before calling `on_change`.
+/// - `on_change`: Called from the thread of the watcher whenever the file is created, changed or removed.
+///
+/// # Returns
+/// - `Result<Debouncer<RecommendedWatcher, RecommendedCache>, Error>`, will be the watcher, which stops when dropped,
+///   or the error if the directory can't be watched.
+pub fn watch_shortcuts_file(
+  path: &Path,
+  delay: Duration,
+  on_change: impl Fn() + Send + 'static,
+) -> Result<Debouncer<RecommendedWatcher, RecommendedCache>, Error> {
+  let name = path.file_name().ok_or_else(|| Error::generic("the shortcuts file has no file name"))?;
+  // The events name the canonical path, so the file is compared against that rather than the path as configured.
+  let file: PathBuf = fs::canonicalize(shortcuts_directory(path))?.join(name);
+  let directory: PathBuf = shortcuts_directory(&file).to_path_buf();
+
+  let mut debouncer = new_debouncer(delay, None, move |result: DebounceEventResult| match result {
+    Ok(events) => {
+      let changed: bool = events.iter().any(|event| {
+        matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) && event.paths.contains(&file)
+      });
+
+      if changed {
+        on_change();
+      }
+    },
+    Err(errors) => errors.iter().for_each(|err| error!("Failed to watch shortcuts file ({}): {}", file.display(), err)),
+  })?;
+  debouncer.watch(&directory, RecursiveMode::NonRecursive)?;
+
+  Ok(debouncer)
+}
+
+/// Works out the changes needed to bring the database in line with the shortcuts file.
+///
+/// Only managed `Shortcut`'s are ever deleted, so shortcuts created in the UI survive a sync.
+/// A declared keyword that already exists unmanaged is taken over by the file.
+///
+/// # Parameters
+/// - `existing`: Every `Shortcut` in the database.
+/// - `declared`: Each keyword in the shortcuts file and its url.
+///
+/// # Returns
+/// - `SyncPlan` with the creates and updates in keyword order, followed by the deletes.
+pub fn plan_sync(existing: &[Shortcut], declared: &BTreeMap<String, String>) -> SyncPlan {
+  let by_keyword: HashMap<&str, &Shortcut> = existing.iter().map(|shortcut| (shortcut.keyword.as_str(), shortcut)).collect();
+  let mut changes: Vec<SyncChange> = vec![];
+
+  for (keyword, url) in declared {
+    match by_keyword.get(keyword.as_str()) {
+      None => changes.push(SyncChange::Create { keyword: keyword.clone(), url: url.clone() }),
+      Some(shortcut) if !shortcut.managed || &shortcut.url != url => changes.push(SyncChange::Update {
+        keyword: keyword.clone(),
+        old_url: shortcut.url.clone(),
+        url: url.clone(),
+      }),
+      Some(_) => {},
+    }
+  }
+
+  let mut removed: Vec<&Shortcut> = existing.iter()
+    .filter(|shortcut| shortcut.managed && !declared.contains_key(&shortcut.keyword))
+    .collect();
+  removed.sort_by(|a, b| a.keyword.cmp(&b.keyword));
+
+  changes.extend(removed.into_iter().map(|shortcut| SyncChange::Delete { keyword: shortcut.keyword.clone(), url: shortcut.url.clone() }));
+
+  SyncPlan { changes }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod sync_util_tests {
+  mod parse_shortcuts_file_tests {
+    use std::{collections::BTreeMap, path::Path};
+
+    use crate::{error::ShortcutError, utils::sync_util::parse_shortcuts_file};
+
+    fn expected() -> BTreeMap<String, String> {
+      BTreeMap::from([
+        ("g".to_owned(), "https://google.com".to_owned()),
+        ("gh".to_owned(), "https://github.com/search?q={query}".to_owned()),
+      ])
+    }
+
+    #[test]
+    fn returns_shortcuts_from_yaml() {
+      let contents = "shortcuts:\n  GH: https://github.com/search?q={query}\n  g: https://google.com\n";
+      let result = parse_shortcuts_file(Path::new("shortcuts.yml"), contents).unwrap();
+
+      assert_eq!(result, expected());
+    }
+
+    #[test]
+    fn returns_shortcuts_from_toml() {
+      let contents = "[shortcuts]\ngh = \"https://github.com/search?q={query}\"\ng = \"https://google.com\"\n";
+      let result = parse_shortcuts_file(Path::new("shortcuts.toml"), contents).unwrap();
+
+      assert_eq!(result, expected());
+    }
+
+    #[test]
+    fn returns_exception_when_keyword_declared_twice() {
+      let contents = "shortcuts:\n  gh: https://github.com\n  GH: https://github.com/search\n";
+      let result = parse_shortcuts_file(Path::new("shortcuts.yaml"), contents);
+
+      assert!(matches!(result, Err(ShortcutError::InvalidShortcutsFile)));
+    }
+
+    #[test]
+    fn returns_exception_when_extension_unsupported() {
+      let result = parse_shortcuts_file(Path::new("shortcuts.json"), "{}");
+
+      assert!(matches!(result, Err(ShortcutError::InvalidShortcutsFile)));
+    }
+  }
+
+  mod plan_sync_tests {
+    use std::collections::BTreeMap;
+
+    use crate::{models::{shortcut::Shortcut, sync::SyncChange}, utils::sync_util::plan_sync};
+
+    fn shortcut(keyword: &str, url: &str, managed: bool) -> Shortcut {
+      Shortcut { managed, ..Shortcut::new(keyword.to_owned(), url.to_owned()) }
+    }
+
+    #[test]
+    fn returns_empty_plan_when_database_matches() {
+      let existing = vec![shortcut("g", "https://google.com", true), shortcut("mine", "https://mine.com", false)];
+      let declared = BTreeMap::from([("g".to_owned(), "https://google.com".to_owned())]);
+
+      assert!(plan_sync(&existing, &declared).is_empty());
+    }
+
+    #[test]
+    fn returns_creates_updates_and_deletes_when_drifted() {
+      let existing = vec![
+        shortcut("old", "https://old.com", true),
+        shortcut("g", "https://google.co.uk", true),
+        shortcut("gh", "https://github.com", false),
+        shortcut("mine", "https://mine.com", false),
+      ];
+      let declared = BTreeMap::from([
+        ("g".to_owned(), "https://google.com".to_owned()),
+        ("gh".to_owned(), "https://github.com".to_owned()),
+        ("new".to_owned(), "https://new.com".to_owned()),
+      ]);
+      let result = plan_sync(&existing, &declared);
+
+      assert_eq!(result.changes, vec![
+        SyncChange::Update { keyword: "g".to_owned(), old_url: "https://google.co.uk".to_owned(), url: "https://google.com".to_owned() },
+        SyncChange::Update { keyword: "gh".to_owned(), old_url: "https://github.com".to_owned(), url: "https://github.com".to_owned() },
+        SyncChange::Create { keyword: "new".to_owned(), url: "https://new.com".to_owned() },
+        SyncChange::Delete { keyword: "old".to_owned(), url: "https://old.com".to_owned() },
+      ]);
+      assert_eq!(result.counts(), (1, 2, 1));
+    }
+  }
+  mod watch_shortcuts_file_tests {
+    use std::{fs, path::PathBuf, sync::mpsc, time::Duration};
+
+    use crate::utils::sync_util::watch_shortcuts_file;
+
+    fn test_directory(name: &str) -> PathBuf {
+      let directory = std::env::temp_dir().join(format!("shortcut-sync-tests-{}-{}", std::process::id(), name));
+      let _ = fs::remove_dir_all(&directory);
+      fs::create_dir_all(directory.join("nested")).unwrap();
+
+      directory
+    }
+
+    #[test]
+    fn calls_on_change_only_for_shortcuts_file() {
+      let directory = test_directory("watch");
+      let path = directory.join("shortcuts.yaml");
+      let (changed, changes) = mpsc::channel();
+
+      let _debouncer = watch_shortcuts_file(&path, Duration::from_millis(50), move || changed.send(()).unwrap()).unwrap();
+      fs::write(directory.join("shortcut.db"), "").unwrap();
+      fs::write(directory.join("nested").join("shortcuts.yaml"), "").unwrap();
+      assert!(changes.recv_timeout(Duration::from_millis(500)).is_err());
+
+      fs::write(&path, "shortcuts: {}\n").unwrap();
+      assert!(changes.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn returns_exception_when_directory_missing() {
+      let path = test_directory("missing").join("missing").join("shortcuts.yaml");
+
+      assert!(watch_shortcuts_file(&path, Duration::from_millis(50), || {}).is_err());
+    }
+  }
+}
\ No newline at end of file
@@ -1,8 +1,61 @@
-use std::time::SystemTime;
+use std::{io, process::Command, time::SystemTime};
 
 pub(crate) fn get_timestamp() -> i64 {
   let duration_since_epoch = SystemTime::now()
     .duration_since(SystemTime::UNIX_EPOCH)
     .unwrap();
   duration_since_epoch.as_secs() as i64
+}
+
+/// Formats a unix timestamp as a compact UTC date and time, e.g. `20231114T221320Z`, which sorts in time order.
+pub(crate) fn format_timestamp(timestamp: i64) -> String {
+  let (days, seconds) = (timestamp.div_euclid(86_400), timestamp.rem_euclid(86_400));
+
+  // Converts days since the epoch into a civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+  let shifted = days + 719_468;
+  let era = shifted.div_euclid(146_097);
+  let day_of_era = shifted.rem_euclid(146_097);
+  let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let shifted_month = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+  let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", year, month, day, seconds / 3_600, seconds % 3_600 / 60, seconds % 60)
+}
+
+/// Opens the url in the default browser, without waiting for it to close.
+pub(crate) fn open_in_browser(url: &str) -> io::Result<()> {
+  #[cfg(target_os = "windows")]
+  let mut command: Command = {
+    let mut command = Command::new("cmd");
+    command.args(["/C", "start", ""]);
+    command
+  };
+  #[cfg(target_os = "macos")]
+  let mut command: Command = Command::new("open");
+  #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+  let mut command: Command = Command::new("xdg-open");
+
+  command.arg(url).spawn().map(|_| ())
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod system_util_tests {
+  mod format_timestamp_tests {
+    use crate::utils::system_util::format_timestamp;
+
+    #[test]
+    fn returns_epoch_when_zero() {
+      assert_eq!(format_timestamp(0), "19700101T000000Z");
+    }
+
+    #[test]
+    fn returns_utc_date_and_time() {
+      assert_eq!(format_timestamp(1_700_000_000), "20231114T221320Z");
+      assert_eq!(format_timestamp(951_782_400), "20000229T000000Z");
+    }
+  }
 }
\ No newline at end of file
@@ -0,0 +1,138 @@
+use crate::config::Config;
+
+use axum::http::{header::HOST, HeaderMap};
+
+/// The path the UI is served under for the request being handled, e.g. `/go`, or empty when it's served at the root.
+///
+/// Added to every request by `routes::middleware::base_path`, from the configured `base_path` or the `X-Forwarded-Prefix`
+/// of a trusted reverse proxy.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BasePath(pub String);
+
+impl BasePath {
+  /// The path within the UI, e.g. `/search` becomes `/go/search`.
+  pub fn join(&self, path: &str) -> String {
+    format!("{}{}", self.0, path)
+  }
+}
+
+/// Normalizes a base path, e.g. `/go/` to `/go` and `/` to nothing, so paths can be appended to it.
+///
+/// # Parameters
+/// - `value`: The base path, from the config or `X-Forwarded-Prefix`.
+///
+/// # Returns
+/// - `Option<String>`, will be the base path, or `None` if it doesn't start with `/` or has characters a path can't,
+///   e.g. `//evil.example` which would make redirects leave the site.
+pub fn normalize_base_path(value: &str) -> Option<String> {
+  let path: &str = value.trim().trim_end_matches('/');
+  let valid: bool = path.chars().all(|c| c.is_ascii_alphanumeric() || "/-._~%".contains(c)) && !path.contains("//");
+
+  match path {
+    "" if value.trim().is_empty() || value.trim().starts_with('/') => Some(String::new()),
+    _ if path.starts_with('/') && valid => Some(path.to_owned()),
+    _ => None,
+  }
+}
+
+/// The first value of a header, which is the one set by the proxy closest to the client when there are several.
+fn first_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+  headers.get(name)
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.split(',').next())
+    .map(str::trim)
+    .filter(|value| !value.is_empty())
+}
+
+/// The URL of the UI as the browser reaches it.
+///
+/// # Parameters
+/// - `config`: The config, whose `ui_url` is used as it is when set.
+/// - `headers`: The headers of the request, the `Host`, and the `X-Forwarded-Host` and `X-Forwarded-Proto` set by the reverse proxy,
+///   which are only read when `trust_forwarded_headers` is set.
+/// - `base_path`: The path the UI is served under for the request.
+///
+/// # Returns
+/// - `String` of the URL, e.g. `https://go.internal/go/`, or the default `ui_url` when the request has no usable host.
+pub fn public_ui_url(config: &Config, headers: &HeaderMap, base_path: &BasePath) -> String {
+  if let Some(ui_url) = &config.ui_url {
+    return ui_url.clone();
+  }
+  let forwarded = |name: &str| first_value(headers, name).filter(|_| config.trust_forwarded_headers);
+
+  let host: Option<&str> = forwarded("x-forwarded-host")
+    .or_else(|| first_value(headers, HOST.as_str()))
+    .filter(|host| host.chars().all(|c| c.is_ascii_alphanumeric() || "-.:[]".contains(c)));
+  let Some(host) = host else {
+    return config.ui_url();
+  };
+
+  let scheme: &str = match forwarded("x-forwarded-proto") {
+    Some(proto) if proto.eq_ignore_ascii_case("https") => "https",
+    Some(proto) if proto.eq_ignore_ascii_case("http") => "http",
+    _ if config.tls_cert.is_some() => "https",
+    _ => "http",
+  };
+
+  format!("{}://{}{}", scheme, host, base_path.join("/"))
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TESTS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+#[cfg(test)]
+mod url_util_tests {
+  mod normalize_base_path_tests {
+    use crate::utils::url_util::normalize_base_path;
+
+    #[test]
+    fn returns_path_without_trailing_slash() {
+      assert_eq!(normalize_base_path("/go/"), Some("/go".to_owned()));
+      assert_eq!(normalize_base_path("/tools/go"), Some("/tools/go".to_owned()));
+      assert_eq!(normalize_base_path("/"), Some(String::new()));
+      assert_eq!(normalize_base_path(""), Some(String::new()));
+    }
+
+    #[test]
+    fn returns_none_when_path_invalid() {
+      assert_eq!(normalize_base_path("go"), None);
+      assert_eq!(normalize_base_path("//evil.example"), None);
+      assert_eq!(normalize_base_path("/go\"><script>"), None);
+    }
+  }
+
+  mod public_ui_url_tests {
+    use axum::http::HeaderMap;
+
+    use crate::{config::Config, utils::url_util::{public_ui_url, BasePath}};
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+      pairs.iter().map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap())).collect()
+    }
+
+    #[test]
+    fn returns_url_forwarded_by_trusted_proxy() {
+      let config = Config { trust_forwarded_headers: true, ..Config::default() };
+      let headers = headers(&[("host", "127.0.0.1:3000"), ("x-forwarded-host", "go.internal"), ("x-forwarded-proto", "https, http")]);
+
+      let result = public_ui_url(&config, &headers, &BasePath("/go".to_owned()));
+
+      assert_eq!(result, "https://go.internal/go/");
+    }
+
+    #[test]
+    fn returns_url_ignoring_forwarded_headers_when_untrusted() {
+      let headers = headers(&[("host", "127.0.0.1:3000"), ("x-forwarded-host", "evil.example"), ("x-forwarded-proto", "https")]);
+
+      let result = public_ui_url(&Config::default(), &headers, &BasePath::default());
+
+      assert_eq!(result, "http://127.0.0.1:3000/");
+    }
+
+    #[test]
+    fn returns_configured_url_over_request() {
+      let config = Config { ui_url: Some("https://go.example/".to_owned()), ..Config::default() };
+
+      assert_eq!(public_ui_url(&config, &headers(&[("host", "go.internal")]), &BasePath::default()), "https://go.example/");
+      assert_eq!(public_ui_url(&Config::default(), &headers(&[("host", "evil.example/path")]), &BasePath::default()), "http://localhost:3000/");
+    }
+  }
+}
\ No newline at end of file